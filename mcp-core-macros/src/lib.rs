@@ -20,7 +20,7 @@ struct ToolArgs {
     annotations: ToolAnnotations,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 struct ToolAnnotations {
     title: Option<String>,
     read_only_hint: Option<bool>,
@@ -29,18 +29,6 @@ struct ToolAnnotations {
     open_world_hint: Option<bool>,
 }
 
-impl Default for ToolAnnotations {
-    fn default() -> Self {
-        Self {
-            title: None,
-            read_only_hint: None,
-            destructive_hint: None,
-            idempotent_hint: None,
-            open_world_hint: None,
-        }
-    }
-}
-
 impl Parse for ToolArgs {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let mut name = None;
@@ -242,7 +230,7 @@ pub fn tool(args: TokenStream, input: TokenStream) -> TokenStream {
                                     .path
                                     .segments
                                     .last()
-                                    .map_or(false, |segment| segment.ident == "Option");
+                                    .is_some_and(|segment| segment.ident == "Option");
                             }
                         }
                     }
@@ -268,7 +256,7 @@ pub fn tool(args: TokenStream, input: TokenStream) -> TokenStream {
                             .path
                             .segments
                             .last()
-                            .map_or(false, |segment| segment.ident == "Option")
+                            .is_some_and(|segment| segment.ident == "Option")
                     } else {
                         false
                     }
@@ -370,6 +358,7 @@ pub fn tool(args: TokenStream, input: TokenStream) -> TokenStream {
                     name: #tool_name.to_string(),
                     description: Some(#tool_description.to_string()),
                     input_schema: schema,
+                    output_schema: None,
                     annotations: Some(mcp_core::types::ToolAnnotations {
                         title: Some(#title.to_string()),
                         read_only_hint: Some(#read_only_hint),
@@ -398,6 +387,7 @@ pub fn tool(args: TokenStream, input: TokenStream) -> TokenStream {
                                         annotations: None,
                                     }
                                 )],
+                                structured_content: None,
                                 is_error: Some(true),
                                 meta: None,
                             },
@@ -421,6 +411,7 @@ pub fn tool(args: TokenStream, input: TokenStream) -> TokenStream {
 
                                 mcp_core::types::CallToolResponse {
                                     content,
+                                    structured_content: None,
                                     is_error: None,
                                     meta: None,
                                 }
@@ -433,6 +424,7 @@ pub fn tool(args: TokenStream, input: TokenStream) -> TokenStream {
                                         annotations: None,
                                     }
                                 )],
+                                structured_content: None,
                                 is_error: Some(true),
                                 meta: None,
                             },
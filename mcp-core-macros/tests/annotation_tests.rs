@@ -1,3 +1,6 @@
+// Tool functions below only exist to generate schemas, so most arguments go unused.
+#![allow(dead_code, unused_variables)]
+
 use anyhow::Result;
 use mcp_core::{tool_text_content, types::ToolResponseContent};
 use mcp_core_macros::{tool, tool_param};
@@ -40,6 +40,7 @@ impl EchoTool {
                 },
                 "required": ["message"]
             }),
+            output_schema: None,
             annotations: None,
         }
     }
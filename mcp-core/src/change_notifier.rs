@@ -0,0 +1,36 @@
+//! Change notification shared by the tool, resource and prompt registries.
+
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// A function called after every change to a registry
+type ChangeListener = Box<dyn Fn() + Send + Sync>;
+
+/// The change listeners of a registry.
+///
+/// Clones share the same listeners, like the clones of the registry that owns
+/// the notifier.
+#[derive(Clone, Default)]
+pub(crate) struct ChangeNotifier {
+    listeners: Arc<RwLock<Vec<ChangeListener>>>,
+}
+
+impl ChangeNotifier {
+    /// Registers a function called by every subsequent `notify`.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The function to call
+    pub(crate) fn on_change(&self, f: impl Fn() + Send + Sync + 'static) {
+        self.listeners
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Box::new(f));
+    }
+
+    /// Calls the registered listeners.
+    pub(crate) fn notify(&self) {
+        if let Ok(listeners) = self.listeners.read() {
+            listeners.iter().for_each(|listener| listener());
+        }
+    }
+}
@@ -10,23 +10,45 @@
 //! - Discovering available tools
 //! - Invoking tools with parameters
 //! - Handling server resources
+//! - Receiving the log messages of the server
+//! - Completing prompt arguments and resource template variables
+//! - Answering the sampling requests of the server
+//! - Exposing roots, the directories the server may operate on
+//! - Asking the user for the information the server requests
+//! - Checking that the server is still alive
 
-use std::{collections::HashMap, env, sync::Arc};
+use std::{
+    collections::HashMap,
+    env,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, PoisonError, Weak,
+    },
+};
 
 use crate::{
-    protocol::RequestOptions,
-    transport::Transport,
+    elicitation::{self, ElicitationHandler},
+    error::McpError,
+    protocol::{traced_request, LivenessCheck, RequestOptions, TimeoutPolicy},
+    sampling::{self, SamplingHandler},
+    transport::{from_raw_value, Transport},
     types::{
-        CallToolRequest, CallToolResponse, ClientCapabilities, Implementation, InitializeRequest,
-        InitializeResponse, ListRequest, ProtocolVersion, ReadResourceRequest, Resource,
-        ResourcesListResponse, ToolsListResponse, LATEST_PROTOCOL_VERSION,
+        CallToolRequest, CallToolResponse, ClientCapabilities, CompleteRequest, CompleteResponse,
+        CompletionArgument, CompletionReference, GetPromptRequest, GetPromptResponse,
+        Implementation, InitializeRequest, InitializeResponse, ListRequest, ListRootsResult,
+        LoggingLevel, LoggingMessageNotification, PromptsListResponse, ProtocolVersion,
+        ReadResourceRequest, ReadResourceResponse, ResourceTemplatesListResponse,
+        ResourcesListResponse, Root, RootCapabilities, SetLevelRequest, SubscribeRequest,
+        ToolResponseContent, ToolsListResponse, LATEST_PROTOCOL_VERSION,
     },
 };
 
 use anyhow::Result;
+use futures::channel::mpsc::UnboundedReceiver;
+use serde::de::DeserializeOwned;
 use serde_json::Value;
 use tokio::sync::RwLock;
-use tracing::debug;
+use tracing::{debug, warn};
 
 /// An MCP client for connecting to MCP servers and invoking their tools.
 ///
@@ -34,13 +56,17 @@ use tracing::debug;
 /// including initialization, tool discovery, and tool invocation.
 #[derive(Clone)]
 pub struct Client<T: Transport> {
-    transport: T,
+    transport: Arc<T>,
     strict: bool,
+    liveness_check: Option<LivenessCheck>,
+    peer_alive: Arc<AtomicBool>,
     protocol_version: ProtocolVersion,
     initialize_res: Arc<RwLock<Option<InitializeResponse>>>,
     env: Option<HashMap<String, SecureValue>>,
     client_info: Implementation,
     capabilities: ClientCapabilities,
+    timeout_policy: TimeoutPolicy,
+    roots: Option<Arc<std::sync::RwLock<Vec<Root>>>>,
 }
 
 impl<T: Transport> Client<T> {
@@ -73,11 +99,86 @@ impl<T: Transport> Client<T> {
 
     /// Opens the transport connection.
     ///
+    /// If a liveness check was configured, this also starts pinging the server
+    /// in the background.
+    ///
     /// # Returns
     ///
     /// A `Result` indicating success or failure
     pub async fn open(&self) -> Result<()> {
-        self.transport.open().await
+        self.transport.open().await?;
+        self.peer_alive.store(true, Ordering::SeqCst);
+        if let Some(liveness_check) = self.liveness_check.clone() {
+            tokio::spawn(Self::monitor_liveness(
+                Arc::downgrade(&self.transport),
+                liveness_check,
+                self.peer_alive.clone(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Periodically pings the server until it is declared dead or the client is dropped.
+    ///
+    /// Once `max_missed` consecutive pings have gone unanswered, the server is
+    /// marked as dead and the transport is closed.
+    async fn monitor_liveness(
+        transport: Weak<T>,
+        liveness_check: LivenessCheck,
+        peer_alive: Arc<AtomicBool>,
+    ) {
+        let mut missed = 0;
+        loop {
+            tokio::time::sleep(liveness_check.interval).await;
+            let Some(transport) = transport.upgrade() else {
+                break;
+            };
+            if !peer_alive.load(Ordering::SeqCst) {
+                break;
+            }
+
+            if liveness_check.ping(transport.as_ref()).await {
+                missed = 0;
+                continue;
+            }
+
+            missed += 1;
+            warn!(
+                "Server did not answer ping ({}/{})",
+                missed, liveness_check.max_missed
+            );
+            if missed >= liveness_check.max_missed {
+                warn!("Server is not responding, closing transport");
+                peer_alive.store(false, Ordering::SeqCst);
+                if let Err(e) = transport.close().await {
+                    debug!("Failed to close transport: {}", e);
+                }
+                break;
+            }
+        }
+    }
+
+    /// Checks whether the server is considered alive.
+    ///
+    /// This only changes when a liveness check is configured and the server
+    /// stops answering pings.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the server is considered alive, `false` otherwise
+    pub fn is_peer_alive(&self) -> bool {
+        self.peer_alive.load(Ordering::SeqCst)
+    }
+
+    /// Sends a `ping` request to the server.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating whether the server answered the ping
+    pub async fn ping(&self) -> Result<()> {
+        self.request("ping", None, self.timeout_policy.options_for("ping"))
+            .await
+            .map(|_| ())
     }
 
     /// Initializes the connection with the MCP server.
@@ -85,6 +186,10 @@ impl<T: Transport> Client<T> {
     /// This sends the initialize request to the server, negotiates protocol
     /// version and capabilities, and establishes the session.
     ///
+    /// The client asks for its configured protocol version and accepts any
+    /// version from `SUPPORTED_PROTOCOL_VERSIONS` the server answers with. The
+    /// negotiated version is available from `protocol_version` afterwards.
+    ///
     /// # Returns
     ///
     /// A `Result` containing the server's initialization response if successful
@@ -94,21 +199,30 @@ impl<T: Transport> Client<T> {
             capabilities: self.capabilities.clone(),
             client_info: self.client_info.clone(),
         };
-        let response = self
-            .request(
+        let response: InitializeResponse = self
+            .request_as(
                 "initialize",
                 Some(serde_json::to_value(request)?),
-                RequestOptions::default(),
+                self.timeout_policy.options_for("initialize"),
             )
             .await?;
-        let response: InitializeResponse = serde_json::from_value(response)
-            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
 
-        if response.protocol_version != self.protocol_version.as_str() {
+        let Some(protocol_version) = ProtocolVersion::parse(&response.protocol_version) else {
             return Err(anyhow::anyhow!(
                 "Unsupported protocol version: {}",
                 response.protocol_version
             ));
+        };
+
+        // Make the negotiated session visible to handlers of server requests
+        if let Some(protocol) = self.transport.protocol() {
+            protocol.update_peer_info(None, |info| {
+                info.protocol_version = Some(protocol_version.clone());
+                info.client_info = Some(self.client_info.clone());
+                info.client_capabilities = Some(self.capabilities.clone());
+                info.server_info = Some(response.server_info.clone());
+                info.server_capabilities = Some(response.capabilities.clone());
+            });
         }
 
         // Save the response for later use
@@ -126,6 +240,19 @@ impl<T: Transport> Client<T> {
         Ok(response)
     }
 
+    /// Returns the protocol version negotiated with the server.
+    ///
+    /// # Returns
+    ///
+    /// The negotiated version, or `None` if the client is not initialized
+    pub async fn protocol_version(&self) -> Option<ProtocolVersion> {
+        self.initialize_res
+            .read()
+            .await
+            .as_ref()
+            .and_then(|response| ProtocolVersion::parse(&response.protocol_version))
+    }
+
     /// Checks if the client has been initialized.
     ///
     /// # Returns
@@ -139,6 +266,28 @@ impl<T: Transport> Client<T> {
         }
     }
 
+    /// Checks that the server advertised the capability a request needs.
+    ///
+    /// In strict mode a missing capability is an error, otherwise it is only
+    /// logged. Nothing is checked before the client is initialized.
+    async fn check_capability(&self, method: &str) -> Result<()> {
+        let reader = self.initialize_res.read().await;
+        let Some(response) = reader.as_ref() else {
+            return Ok(());
+        };
+        let Some(version) = ProtocolVersion::parse(&response.protocol_version) else {
+            return Ok(());
+        };
+        match response.capabilities.check_request(method, &version) {
+            Err(e) if self.strict => Err(e.into()),
+            Err(e) => {
+                warn!("{}", e.message);
+                Ok(())
+            }
+            Ok(()) => Ok(()),
+        }
+    }
+
     /// Sends a request to the server.
     ///
     /// # Arguments
@@ -149,17 +298,37 @@ impl<T: Transport> Client<T> {
     ///
     /// # Returns
     ///
-    /// A `Result` containing the server's response if successful
+    /// A `Result` containing the server's response if successful. Error responses
+    /// are returned as an `McpError`, which can be recovered from the
+    /// `anyhow::Error` with `downcast_ref`. In strict mode, requests needing a
+    /// capability the server did not advertise fail with
+    /// `McpError::capability_not_supported` without being sent.
     pub async fn request(
         &self,
         method: &str,
         params: Option<serde_json::Value>,
         options: RequestOptions,
     ) -> Result<serde_json::Value> {
-        let response = self.transport.request(method, params, options).await?;
-        response
-            .result
-            .ok_or_else(|| anyhow::anyhow!("Request failed: {:?}", response.error))
+        self.request_as(method, params, options).await
+    }
+
+    /// Sends a request to the server and deserializes the result directly from
+    /// the raw response.
+    async fn request_as<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+        options: RequestOptions,
+    ) -> Result<R> {
+        self.check_capability(method).await?;
+        let response =
+            traced_request(self.transport.as_ref(), None, method, params, options).await?;
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(McpError::from(error).into()),
+            (Some(result), None) => from_raw_value(&result)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e)),
+            (None, None) => Err(McpError::internal("Response has neither result nor error").into()),
+        }
     }
 
     /// Lists tools available on the server.
@@ -183,20 +352,18 @@ impl<T: Transport> Client<T> {
 
         let list_request = ListRequest { cursor, meta: None };
 
-        let response = self
-            .request(
-                "tools/list",
-                Some(serde_json::to_value(list_request)?),
-                request_options.unwrap_or_else(RequestOptions::default),
-            )
-            .await?;
-
-        Ok(serde_json::from_value(response)
-            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?)
+        self.request_as(
+            "tools/list",
+            Some(serde_json::to_value(list_request)?),
+            request_options.unwrap_or_else(|| self.timeout_policy.options_for("tools/list")),
+        )
+        .await
     }
 
     /// Calls a tool on the server.
     ///
+    /// The request options come from the client's timeout policy for `tools/call`.
+    ///
     /// # Arguments
     ///
     /// * `name` - The name of the tool to call
@@ -206,9 +373,29 @@ impl<T: Transport> Client<T> {
     ///
     /// A `Result` containing the tool's response if successful
     pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Option<serde_json::Value>,
+    ) -> Result<CallToolResponse> {
+        self.call_tool_with_options(name, arguments, None).await
+    }
+
+    /// Calls a tool on the server with specific request options.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the tool to call
+    /// * `arguments` - Optional arguments for the tool
+    /// * `request_options` - Optional request options, overriding the timeout policy
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the tool's response if successful
+    pub async fn call_tool_with_options(
         &self,
         name: &str,
         arguements: Option<serde_json::Value>,
+        request_options: Option<RequestOptions>,
     ) -> Result<CallToolResponse> {
         if self.strict {
             self.assert_initialized().await?;
@@ -231,16 +418,45 @@ impl<T: Transport> Client<T> {
             meta: None,
         };
 
-        let response = self
-            .request(
-                "tools/call",
-                Some(serde_json::to_value(request)?),
-                RequestOptions::default(),
-            )
-            .await?;
+        self.request_as(
+            "tools/call",
+            Some(serde_json::to_value(request)?),
+            request_options.unwrap_or_else(|| self.timeout_policy.options_for("tools/call")),
+        )
+        .await
+    }
 
-        Ok(serde_json::from_value(response)
-            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?)
+    /// Calls a tool on the server and decodes its structured content.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the tool to call
+    /// * `arguments` - Optional arguments for the tool
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the decoded structured content, or an error if
+    /// the call ended in an error or the tool returned no structured content
+    /// of the expected type
+    pub async fn call_tool_structured<R: DeserializeOwned>(
+        &self,
+        name: &str,
+        arguments: Option<serde_json::Value>,
+    ) -> Result<R> {
+        let response = self.call_tool(name, arguments).await?;
+        if response.is_error == Some(true) {
+            let message = response
+                .content
+                .iter()
+                .filter_map(|content| match content {
+                    ToolResponseContent::Text(text) => Some(text.text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            return Err(anyhow::anyhow!("Tool {} failed: {}", name, message));
+        }
+        response.structured_content_as()
     }
 
     /// Lists resources available on the server.
@@ -264,16 +480,42 @@ impl<T: Transport> Client<T> {
 
         let list_request = ListRequest { cursor, meta: None };
 
-        let response = self
-            .request(
-                "resources/list",
-                Some(serde_json::to_value(list_request)?),
-                request_options.unwrap_or_else(RequestOptions::default),
-            )
-            .await?;
+        self.request_as(
+            "resources/list",
+            Some(serde_json::to_value(list_request)?),
+            request_options.unwrap_or_else(|| self.timeout_policy.options_for("resources/list")),
+        )
+        .await
+    }
 
-        Ok(serde_json::from_value(response)
-            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?)
+    /// Lists resource templates available on the server.
+    ///
+    /// # Arguments
+    ///
+    /// * `cursor` - Optional pagination cursor
+    /// * `request_options` - Optional request options
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the list of resource templates if successful
+    pub async fn list_resource_templates(
+        &self,
+        cursor: Option<String>,
+        request_options: Option<RequestOptions>,
+    ) -> Result<ResourceTemplatesListResponse> {
+        if self.strict {
+            self.assert_initialized().await?;
+        }
+
+        let list_request = ListRequest { cursor, meta: None };
+
+        self.request_as(
+            "resources/templates/list",
+            Some(serde_json::to_value(list_request)?),
+            request_options
+                .unwrap_or_else(|| self.timeout_policy.options_for("resources/templates/list")),
+        )
+        .await
     }
 
     /// Reads a resource from the server.
@@ -284,59 +526,275 @@ impl<T: Transport> Client<T> {
     ///
     /// # Returns
     ///
-    /// A `Result` containing the resource if successful
-    pub async fn read_resource(&self, uri: url::Url) -> Result<Resource> {
+    /// A `Result` containing the contents of the resource if successful
+    pub async fn read_resource(&self, uri: url::Url) -> Result<ReadResourceResponse> {
         if self.strict {
             self.assert_initialized().await?;
         }
 
         let read_request = ReadResourceRequest { uri };
 
-        let response = self
-            .request(
-                "resources/read",
-                Some(serde_json::to_value(read_request)?),
-                RequestOptions::default(),
-            )
-            .await?;
+        self.request_as(
+            "resources/read",
+            Some(serde_json::to_value(read_request)?),
+            self.timeout_policy.options_for("resources/read"),
+        )
+        .await
+    }
 
-        Ok(serde_json::from_value(response)
-            .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?)
+    /// Lists prompts available on the server.
+    ///
+    /// # Arguments
+    ///
+    /// * `cursor` - Optional pagination cursor
+    /// * `request_options` - Optional request options
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the list of prompts if successful
+    pub async fn list_prompts(
+        &self,
+        cursor: Option<String>,
+        request_options: Option<RequestOptions>,
+    ) -> Result<PromptsListResponse> {
+        if self.strict {
+            self.assert_initialized().await?;
+        }
+
+        let list_request = ListRequest { cursor, meta: None };
+
+        self.request_as(
+            "prompts/list",
+            Some(serde_json::to_value(list_request)?),
+            request_options.unwrap_or_else(|| self.timeout_policy.options_for("prompts/list")),
+        )
+        .await
     }
 
+    /// Gets a prompt from the server, filled in with arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the prompt
+    /// * `arguments` - The arguments of the prompt
+    /// * `request_options` - Optional request options
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the messages of the prompt if successful
+    pub async fn get_prompt(
+        &self,
+        name: &str,
+        arguments: Option<HashMap<String, String>>,
+        request_options: Option<RequestOptions>,
+    ) -> Result<GetPromptResponse> {
+        if self.strict {
+            self.assert_initialized().await?;
+        }
+
+        let request = GetPromptRequest {
+            name: name.to_string(),
+            arguments,
+        };
+
+        self.request_as(
+            "prompts/get",
+            Some(serde_json::to_value(request)?),
+            request_options.unwrap_or_else(|| self.timeout_policy.options_for("prompts/get")),
+        )
+        .await
+    }
+
+    /// Asks the server for the possible values of a prompt argument or of a
+    /// resource template variable.
+    ///
+    /// # Arguments
+    ///
+    /// * `reference` - The prompt or resource template the argument belongs to
+    /// * `argument` - The name of the argument or template variable
+    /// * `value` - The value typed so far
+    /// * `request_options` - Optional request options
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the possible values if successful
+    pub async fn complete(
+        &self,
+        reference: CompletionReference,
+        argument: &str,
+        value: &str,
+        request_options: Option<RequestOptions>,
+    ) -> Result<CompleteResponse> {
+        if self.strict {
+            self.assert_initialized().await?;
+        }
+
+        let request = CompleteRequest {
+            reference,
+            argument: CompletionArgument {
+                name: argument.to_string(),
+                value: value.to_string(),
+            },
+        };
+
+        self.request_as(
+            "completion/complete",
+            Some(serde_json::to_value(request)?),
+            request_options
+                .unwrap_or_else(|| self.timeout_policy.options_for("completion/complete")),
+        )
+        .await
+    }
+
+    /// Subscribes to updates of a resource.
+    ///
+    /// The server must advertise the `resources.subscribe` capability.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The URI of the resource
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or failure
     pub async fn subscribe_to_resource(&self, uri: url::Url) -> Result<()> {
         if self.strict {
             self.assert_initialized().await?;
         }
 
-        let subscribe_request = ReadResourceRequest { uri };
+        let subscribe_request = SubscribeRequest { uri };
 
         self.request(
             "resources/subscribe",
             Some(serde_json::to_value(subscribe_request)?),
-            RequestOptions::default(),
+            self.timeout_policy.options_for("resources/subscribe"),
         )
         .await?;
 
         Ok(())
     }
 
+    /// Unsubscribes from updates of a resource.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The URI of the resource
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or failure
     pub async fn unsubscribe_to_resource(&self, uri: url::Url) -> Result<()> {
         if self.strict {
             self.assert_initialized().await?;
         }
 
-        let unsubscribe_request = ReadResourceRequest { uri };
+        let unsubscribe_request = SubscribeRequest { uri };
 
         self.request(
             "resources/unsubscribe",
             Some(serde_json::to_value(unsubscribe_request)?),
-            RequestOptions::default(),
+            self.timeout_policy.options_for("resources/unsubscribe"),
         )
         .await?;
 
         Ok(())
     }
+
+    /// Replaces the roots exposed to the server.
+    ///
+    /// Once the client is initialized, the server is told about the change
+    /// with `notifications/roots/list_changed`, if the client advertises
+    /// `roots.list_changed`.
+    ///
+    /// # Arguments
+    ///
+    /// * `roots` - The new roots
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success, or an error if the client was built
+    /// without `ClientBuilder::with_roots`
+    pub async fn set_roots(&self, roots: Vec<Root>) -> Result<()> {
+        let Some(current) = &self.roots else {
+            return Err(anyhow::anyhow!(
+                "Roots are not enabled, declare them with ClientBuilder::with_roots"
+            ));
+        };
+        *current.write().unwrap_or_else(PoisonError::into_inner) = roots;
+
+        let list_changed = self
+            .capabilities
+            .roots
+            .as_ref()
+            .and_then(|roots| roots.list_changed)
+            .unwrap_or(false);
+        if list_changed && self.assert_initialized().await.is_ok() {
+            self.transport
+                .send_notification("notifications/roots/list_changed", None)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the roots exposed to the server.
+    pub fn roots(&self) -> Vec<Root> {
+        self.roots
+            .as_ref()
+            .map(|roots| roots.read().unwrap_or_else(PoisonError::into_inner).clone())
+            .unwrap_or_default()
+    }
+
+    /// Sets the minimum level of the log messages the server sends.
+    ///
+    /// The server must advertise the `logging` capability. Until this is
+    /// called, the server decides which messages to send, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - The level of the least severe messages to receive
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or failure
+    pub async fn set_logging_level(&self, level: LoggingLevel) -> Result<()> {
+        if self.strict {
+            self.assert_initialized().await?;
+        }
+
+        self.request(
+            "logging/setLevel",
+            Some(serde_json::to_value(SetLevelRequest { level })?),
+            self.timeout_policy.options_for("logging/setLevel"),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Returns a stream of the log messages sent by the server.
+    ///
+    /// Only the stream returned by the latest call receives messages.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the stream, or an error if the transport does not
+    /// handle messages from the server
+    pub fn log_messages(&self) -> Result<UnboundedReceiver<LoggingMessageNotification>> {
+        let protocol = self
+            .transport
+            .protocol()
+            .ok_or_else(|| anyhow::anyhow!("Transport does not handle server notifications"))?;
+        let (sender, receiver) = futures::channel::mpsc::unbounded();
+        protocol.set_notification_handler(
+            "notifications/message",
+            move |notification: LoggingMessageNotification, _| {
+                // The stream may have been dropped
+                let _ = sender.unbounded_send(notification);
+                Box::pin(async { Ok(()) })
+            },
+        );
+        Ok(receiver)
+    }
 }
 
 /// Represents a value that may contain sensitive information.
@@ -357,10 +815,15 @@ pub enum SecureValue {
 pub struct ClientBuilder<T: Transport> {
     transport: T,
     strict: bool,
+    liveness_check: Option<LivenessCheck>,
     env: Option<HashMap<String, SecureValue>>,
     protocol_version: ProtocolVersion,
     client_info: Implementation,
     capabilities: ClientCapabilities,
+    timeout_policy: TimeoutPolicy,
+    sampling_handler: Option<Arc<dyn SamplingHandler>>,
+    elicitation_handler: Option<Arc<dyn ElicitationHandler>>,
+    roots: Option<Vec<Root>>,
 }
 
 impl<T: Transport> ClientBuilder<T> {
@@ -377,6 +840,7 @@ impl<T: Transport> ClientBuilder<T> {
         Self {
             transport,
             strict: false,
+            liveness_check: None,
             env: None,
             protocol_version: LATEST_PROTOCOL_VERSION,
             client_info: Implementation {
@@ -384,9 +848,27 @@ impl<T: Transport> ClientBuilder<T> {
                 version: env::var("CARGO_PKG_VERSION").unwrap_or_else(|_| "0.1.0".to_string()),
             },
             capabilities: ClientCapabilities::default(),
+            timeout_policy: TimeoutPolicy::default(),
+            sampling_handler: None,
+            elicitation_handler: None,
+            roots: None,
         }
     }
 
+    /// Sets the timeout policy for requests made without explicit options.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout_policy` - The default and per-method request options
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn with_timeout_policy(mut self, timeout_policy: TimeoutPolicy) -> Self {
+        self.timeout_policy = timeout_policy;
+        self
+    }
+
     /// Sets the protocol version for the client.
     ///
     /// # Arguments
@@ -457,6 +939,10 @@ impl<T: Transport> ClientBuilder<T> {
 
     /// Enables strict mode, which requires initialization before operations.
     ///
+    /// In strict mode, requests needing a capability the server did not
+    /// advertise, e.g. `resources/subscribe` without `resources.subscribe`,
+    /// fail before being sent. In lenient mode they are sent with a warning.
+    ///
     /// # Returns
     ///
     /// The modified builder instance
@@ -479,20 +965,141 @@ impl<T: Transport> ClientBuilder<T> {
         self
     }
 
+    /// Enables periodic liveness checks of the server.
+    ///
+    /// Once the client is opened, it pings the server at the configured interval
+    /// and closes the transport when too many consecutive pings go unanswered.
+    ///
+    /// # Arguments
+    ///
+    /// * `liveness_check` - The liveness check configuration
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn with_liveness_check(mut self, liveness_check: LivenessCheck) -> Self {
+        self.liveness_check = Some(liveness_check);
+        self
+    }
+
+    /// Sets the handler answering the `sampling/createMessage` requests of the server.
+    ///
+    /// This advertises the `sampling` capability, unless the capabilities set
+    /// with `set_capabilities` already do. The transport must handle requests
+    /// from the server for the handler to be called.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - The sampling handler
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn with_sampling_handler(mut self, handler: impl SamplingHandler + 'static) -> Self {
+        self.sampling_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Sets the handler answering the `elicitation/create` requests of the server.
+    ///
+    /// This advertises the `elicitation` capability, unless the capabilities
+    /// set with `set_capabilities` already do. Servers only send these
+    /// requests with protocol version 2025-06-18 or later.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - The elicitation handler
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn with_elicitation_handler(mut self, handler: impl ElicitationHandler + 'static) -> Self {
+        self.elicitation_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Declares the roots exposed to the server, answering its `roots/list` requests.
+    ///
+    /// This advertises the `roots` capability with `list_changed`, unless the
+    /// capabilities set with `set_capabilities` already advertise `roots`.
+    /// The roots can be changed later with `Client::set_roots`.
+    ///
+    /// # Arguments
+    ///
+    /// * `roots` - The roots, e.g. the workspace folders of the host
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn with_roots(mut self, roots: Vec<Root>) -> Self {
+        self.roots = Some(roots);
+        self
+    }
+
     /// Builds the client with the configured settings.
     ///
     /// # Returns
     ///
     /// A new `Client` instance
-    pub fn build(self) -> Client<T> {
+    pub fn build(mut self) -> Client<T> {
+        if let Some(handler) = self.sampling_handler {
+            match self.transport.protocol() {
+                Some(protocol) => {
+                    sampling::register_handler(protocol, handler);
+                    if self.capabilities.sampling.is_none() {
+                        self.capabilities.sampling = Some(serde_json::json!({}));
+                    }
+                }
+                None => warn!("Transport does not handle server requests, sampling is disabled"),
+            }
+        }
+
+        if let Some(handler) = self.elicitation_handler {
+            match self.transport.protocol() {
+                Some(protocol) => {
+                    elicitation::register_handler(protocol, handler);
+                    if self.capabilities.elicitation.is_none() {
+                        self.capabilities.elicitation = Some(serde_json::json!({}));
+                    }
+                }
+                None => {
+                    warn!("Transport does not handle server requests, elicitation is disabled")
+                }
+            }
+        }
+
+        let roots = self
+            .roots
+            .map(|roots| Arc::new(std::sync::RwLock::new(roots)));
+        if let Some(roots) = roots.clone() {
+            match self.transport.protocol() {
+                Some(protocol) => {
+                    protocol.set_request_handler("roots/list", move |_: Value, _| {
+                        let roots = roots.read().unwrap_or_else(PoisonError::into_inner).clone();
+                        Box::pin(async move { Ok(ListRootsResult { roots, meta: None }) })
+                    });
+                    if self.capabilities.roots.is_none() {
+                        self.capabilities.roots = Some(RootCapabilities {
+                            list_changed: Some(true),
+                        });
+                    }
+                }
+                None => warn!("Transport does not handle server requests, roots are disabled"),
+            }
+        }
+
         Client {
-            transport: self.transport,
+            transport: Arc::new(self.transport),
             strict: self.strict,
+            liveness_check: self.liveness_check,
+            peer_alive: Arc::new(AtomicBool::new(true)),
             env: self.env,
             protocol_version: self.protocol_version,
             initialize_res: Arc::new(RwLock::new(None)),
             client_info: self.client_info,
             capabilities: self.capabilities,
+            timeout_policy: self.timeout_policy,
+            roots,
         }
     }
 }
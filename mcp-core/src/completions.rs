@@ -0,0 +1,234 @@
+//! # MCP Completions
+//!
+//! This module provides argument completion for servers. Completion providers
+//! are registered per prompt argument and per resource template variable, and
+//! answer `completion/complete` requests with the possible values of the
+//! argument, given the value typed so far.
+
+use crate::protocol::RequestContext;
+use crate::resources::UriTemplate;
+use crate::types::{CompleteRequest, CompleteResponse, Completion, CompletionReference};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// The maximum number of values in a completion
+const MAX_VALUES: usize = 100;
+
+/// Registry of the completion providers of a server.
+pub struct Completions {
+    completion_handlers: RwLock<HashMap<(CompletionReference, String), Arc<CompletionHandlerFn>>>,
+}
+
+impl Completions {
+    /// Creates a new, empty completion registry.
+    pub(crate) fn new() -> Self {
+        Self {
+            completion_handlers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Completes an argument.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - The request containing the reference and the argument
+    /// * `context` - The context of the `completion/complete` request
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the possible values, empty if no provider is
+    /// registered for the argument. Values beyond the first 100 are dropped,
+    /// and `has_more` is set.
+    pub async fn complete(
+        &self,
+        req: CompleteRequest,
+        context: RequestContext,
+    ) -> Result<CompleteResponse> {
+        let handler = self
+            .completion_handlers
+            .read()
+            .ok()
+            .and_then(|handlers| handlers.get(&(req.reference, req.argument.name)).cloned());
+        let mut completion = match handler {
+            Some(handler) => handler(req.argument.value, context).await?,
+            None => Completion::default(),
+        };
+        if completion.values.len() > MAX_VALUES {
+            completion.total = completion
+                .total
+                .or(u32::try_from(completion.values.len()).ok());
+            completion.values.truncate(MAX_VALUES);
+            completion.has_more = Some(true);
+        }
+        Ok(CompleteResponse {
+            completion,
+            meta: None,
+        })
+    }
+
+    /// Returns `true` if no completion provider is registered.
+    pub fn is_empty(&self) -> bool {
+        self.completion_handlers
+            .read()
+            .map(|handlers| handlers.is_empty())
+            .unwrap_or(true)
+    }
+}
+
+/// A handle for registering completion providers, shared with the server.
+///
+/// # Examples
+///
+/// ```
+/// use mcp_core::server::Server;
+/// use mcp_core::types::Completion;
+///
+/// let builder = Server::builder(
+///     "repos".to_string(),
+///     "1.0".to_string(),
+///     mcp_core::types::ProtocolVersion::V2025_03_26,
+/// )
+/// .register_prompt_completion("review", "branch", |value, _| {
+///     Box::pin(async move {
+///         let values = ["main", "develop", "release"]
+///             .into_iter()
+///             .filter(|branch| branch.starts_with(&value))
+///             .map(str::to_string)
+///             .collect();
+///         Ok(Completion {
+///             values,
+///             ..Default::default()
+///         })
+///     })
+/// });
+/// let protocol = builder.build();
+/// ```
+#[derive(Clone)]
+pub struct CompletionRegistry {
+    completions: Arc<Completions>,
+}
+
+impl CompletionRegistry {
+    /// Creates a new, empty completion registry.
+    ///
+    /// # Returns
+    ///
+    /// A new `CompletionRegistry` instance
+    pub fn new() -> Self {
+        Self {
+            completions: Arc::new(Completions::new()),
+        }
+    }
+
+    /// Returns the completion providers of the registry.
+    pub fn completions(&self) -> Arc<Completions> {
+        self.completions.clone()
+    }
+
+    /// Adds a completion provider for an argument of a prompt, replacing any
+    /// provider of the same argument.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The name of the prompt
+    /// * `argument` - The name of the argument
+    /// * `f` - The provider, called with the value typed so far and the context
+    ///   of the `completion/complete` request
+    pub fn add_prompt_completion(
+        &self,
+        prompt: &str,
+        argument: &str,
+        f: impl Fn(String, RequestContext) -> Pin<Box<dyn Future<Output = Result<Completion>> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        let reference = CompletionReference::Prompt {
+            name: prompt.to_string(),
+        };
+        self.insert(reference, argument, Arc::new(f));
+    }
+
+    /// Adds a completion provider for a variable of a resource template,
+    /// replacing any provider of the same variable.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri_template` - The URI template of the resource template
+    /// * `variable` - The name of the template variable
+    /// * `f` - The provider, called with the value typed so far and the context
+    ///   of the `completion/complete` request
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success, or an error if the URI template is
+    /// invalid or has no such variable
+    pub fn add_resource_completion(
+        &self,
+        uri_template: &str,
+        variable: &str,
+        f: impl Fn(String, RequestContext) -> Pin<Box<dyn Future<Output = Result<Completion>> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Result<()> {
+        let template = UriTemplate::parse(uri_template)?;
+        if !template.variables().any(|name| name == variable) {
+            return Err(anyhow::anyhow!(
+                "URI template {} has no variable {}",
+                uri_template,
+                variable
+            ));
+        }
+        let reference = CompletionReference::Resource {
+            uri: uri_template.to_string(),
+        };
+        self.insert(reference, variable, Arc::new(f));
+        Ok(())
+    }
+
+    /// Removes a completion provider.
+    ///
+    /// # Arguments
+    ///
+    /// * `reference` - The prompt or resource template
+    /// * `argument` - The name of the argument or template variable
+    ///
+    /// # Returns
+    ///
+    /// `true` if the provider was removed, `false` if there was no such provider
+    pub fn remove_completion(&self, reference: &CompletionReference, argument: &str) -> bool {
+        self.completions
+            .completion_handlers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&(reference.clone(), argument.to_string()))
+            .is_some()
+    }
+
+    fn insert(&self, reference: CompletionReference, argument: &str, f: Arc<CompletionHandlerFn>) {
+        self.completions
+            .completion_handlers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert((reference, argument.to_string()), f);
+    }
+}
+
+impl Default for CompletionRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Type alias for a completion provider.
+///
+/// A completion provider is called with the value of the argument typed so
+/// far and the `RequestContext` of the `completion/complete` request, and
+/// returns a future that resolves to the possible values.
+pub type CompletionHandlerFn = dyn Fn(String, RequestContext) -> Pin<Box<dyn Future<Output = Result<Completion>> + Send>>
+    + Send
+    + Sync;
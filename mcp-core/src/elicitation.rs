@@ -0,0 +1,259 @@
+//! # MCP Elicitation
+//!
+//! Elicitation lets a server ask the user for information in the middle of a
+//! request, e.g. to confirm a destructive operation or to fill in a missing
+//! parameter. The server sends `elicitation/create` with `Peer::elicit`, and
+//! the client answers with the action of the user and the information they
+//! submitted, which the server validates against the requested schema.
+//!
+//! Elicitation is part of protocol version 2025-06-18. Clients handle the
+//! requests with an `ElicitationHandler`, registered with
+//! `ClientBuilder::with_elicitation_handler`, which also advertises the
+//! `elicitation` capability.
+
+use crate::error::McpError;
+use crate::protocol::{Protocol, RequestContext};
+use crate::types::{ElicitRequest, ElicitResult, ElicitationSchema, NumberSchema, PrimitiveSchema};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+/// Handles the `elicitation/create` requests of a server.
+///
+/// Implementations present the message to the user, collect the requested
+/// information, and return whether the user accepted, declined or cancelled.
+///
+/// # Examples
+///
+/// ```
+/// use async_trait::async_trait;
+/// use mcp_core::elicitation::ElicitationHandler;
+/// use mcp_core::protocol::RequestContext;
+/// use mcp_core::types::{ElicitAction, ElicitRequest, ElicitResult};
+///
+/// struct Confirm;
+///
+/// #[async_trait]
+/// impl ElicitationHandler for Confirm {
+///     async fn elicit(
+///         &self,
+///         request: ElicitRequest,
+///         _context: RequestContext,
+///     ) -> anyhow::Result<ElicitResult> {
+///         println!("{}", request.message);
+///         let mut content = serde_json::Map::new();
+///         content.insert("confirm".to_string(), true.into());
+///         Ok(ElicitResult {
+///             action: ElicitAction::Accept,
+///             content: Some(content),
+///             meta: None,
+///         })
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait ElicitationHandler: Send + Sync {
+    /// Asks the user for the requested information.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The message to present and the schema of the information
+    /// * `context` - The context of the `elicitation/create` request
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the action of the user, and the information they
+    /// submitted when they accepted
+    async fn elicit(&self, request: ElicitRequest, context: RequestContext)
+        -> Result<ElicitResult>;
+}
+
+/// Registers an elicitation handler for the `elicitation/create` requests
+/// received by a protocol.
+pub(crate) fn register_handler(protocol: &Protocol, handler: Arc<dyn ElicitationHandler>) {
+    protocol.set_request_handler(
+        "elicitation/create",
+        move |request: ElicitRequest, context| {
+            let handler = handler.clone();
+            Box::pin(async move { handler.elicit(request, context).await })
+        },
+    );
+}
+
+impl ElicitationSchema {
+    /// Validates the information submitted for an elicitation.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - The submitted information
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the content matches the schema, or an invalid params error
+    /// describing the first mismatch
+    pub fn validate(&self, content: &Map<String, Value>) -> Result<(), McpError> {
+        for name in self.required.iter().flatten() {
+            if !content.contains_key(name) {
+                return Err(McpError::invalid_params(format!(
+                    "Missing required property: {}",
+                    name
+                )));
+            }
+        }
+        for (name, value) in content {
+            let schema = self
+                .properties
+                .get(name)
+                .ok_or_else(|| McpError::invalid_params(format!("Unknown property: {}", name)))?;
+            validate_property(schema, value)
+                .map_err(|e| McpError::invalid_params(format!("Invalid {}: {}", name, e)))?;
+        }
+        Ok(())
+    }
+}
+
+/// Validates the value of a property, returning what is wrong with it.
+fn validate_property(schema: &PrimitiveSchema, value: &Value) -> Result<(), String> {
+    match schema {
+        PrimitiveSchema::String(schema) => {
+            let value = value.as_str().ok_or("expected a string")?;
+            let len = value.chars().count();
+            if schema.min_length.is_some_and(|min| len < min as usize) {
+                return Err("too short".to_string());
+            }
+            if schema.max_length.is_some_and(|max| len > max as usize) {
+                return Err("too long".to_string());
+            }
+            if let Some(values) = &schema.enum_values {
+                if !values.iter().any(|allowed| allowed == value) {
+                    return Err(format!("expected one of {}", values.join(", ")));
+                }
+            }
+            match schema.format.as_deref() {
+                Some(format) if !matches_format(format, value) => {
+                    Err(format!("expected a {} string", format))
+                }
+                _ => Ok(()),
+            }
+        }
+        PrimitiveSchema::Number(schema) => validate_number(schema, value, false),
+        PrimitiveSchema::Integer(schema) => validate_number(schema, value, true),
+        PrimitiveSchema::Boolean(_) => value
+            .is_boolean()
+            .then_some(())
+            .ok_or_else(|| "expected a boolean".to_string()),
+    }
+}
+
+fn validate_number(schema: &NumberSchema, value: &Value, integer: bool) -> Result<(), String> {
+    let number = value.as_f64().ok_or("expected a number")?;
+    if integer && number.fract() != 0.0 {
+        return Err("expected an integer".to_string());
+    }
+    if schema.minimum.is_some_and(|min| number < min) {
+        return Err("below the minimum".to_string());
+    }
+    if schema.maximum.is_some_and(|max| number > max) {
+        return Err("above the maximum".to_string());
+    }
+    Ok(())
+}
+
+/// Checks a string against one of the formats allowed in elicitation schemas.
+/// Unknown formats are accepted.
+fn matches_format(format: &str, value: &str) -> bool {
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let is_date = |s: &str| {
+        let parts: Vec<_> = s.split('-').collect();
+        matches!(parts.as_slice(), [y, m, d] if y.len() == 4 && m.len() == 2 && d.len() == 2
+            && digits(y) && digits(m) && digits(d))
+    };
+    match format {
+        "email" => value
+            .split_once('@')
+            .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.')),
+        "uri" => url::Url::parse(value).is_ok(),
+        "date" => is_date(value),
+        "date-time" => value.split_once(['T', 't']).is_some_and(|(date, time)| {
+            is_date(date) && time.len() >= 8 && time.as_bytes()[2] == b':'
+        }),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{BooleanSchema, StringSchema};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_elicit_requires_2025_06_18() {
+        let protocol = Protocol::builder().build();
+        let peer = crate::protocol::Peer::detached(protocol, None);
+        peer.update_info(|info| {
+            info.protocol_version = Some(crate::types::ProtocolVersion::V2025_03_26)
+        });
+        let schema = ElicitationSchema {
+            schema_type: "object".to_string(),
+            properties: Default::default(),
+            required: None,
+        };
+        let error = peer.elicit("Proceed?", schema, None).await.unwrap_err();
+        let error = error.downcast_ref::<McpError>().unwrap();
+        assert_eq!(error.data, Some(json!({ "capability": "elicitation" })));
+    }
+
+    #[test]
+    fn test_validate_content() {
+        let schema: ElicitationSchema = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "confirm": { "type": "boolean" },
+                "email": { "type": "string", "format": "email" },
+                "env": { "type": "string", "enum": ["staging", "production"] },
+                "replicas": { "type": "integer", "minimum": 1, "maximum": 10 },
+            },
+            "required": ["confirm"],
+        }))
+        .unwrap();
+        assert_eq!(
+            schema.properties["confirm"],
+            PrimitiveSchema::Boolean(BooleanSchema::default())
+        );
+        assert!(matches!(
+            &schema.properties["env"],
+            PrimitiveSchema::String(StringSchema { enum_values: Some(values), .. }) if values.len() == 2
+        ));
+
+        let content = |value: Value| value.as_object().unwrap().clone();
+        assert!(schema
+            .validate(&content(json!({
+                "confirm": true,
+                "email": "ops@example.com",
+                "env": "staging",
+                "replicas": 3,
+            })))
+            .is_ok());
+        assert!(schema.validate(&content(json!({}))).is_err());
+        assert!(schema
+            .validate(&content(json!({ "confirm": "yes" })))
+            .is_err());
+        assert!(schema
+            .validate(&content(json!({ "confirm": true, "env": "dev" })))
+            .is_err());
+        assert!(schema
+            .validate(&content(json!({ "confirm": true, "replicas": 2.5 })))
+            .is_err());
+        assert!(schema
+            .validate(&content(json!({ "confirm": true, "replicas": 11 })))
+            .is_err());
+        assert!(schema
+            .validate(&content(json!({ "confirm": true, "email": "ops" })))
+            .is_err());
+        assert!(schema
+            .validate(&content(json!({ "confirm": true, "extra": 1 })))
+            .is_err());
+    }
+}
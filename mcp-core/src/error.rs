@@ -0,0 +1,236 @@
+//! # MCP Errors
+//!
+//! This module defines `McpError`, the error type carried in JSON-RPC error
+//! responses of the Model Context Protocol (MCP).
+//!
+//! Request handlers keep returning `anyhow::Result`, but any `McpError` they
+//! return (directly or wrapped in an `anyhow::Error`) is sent to the peer with
+//! its own code, message and data instead of a generic internal error. On the
+//! client side, error responses are surfaced as `McpError` so callers can tell
+//! invalid requests apart from server failures.
+
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::transport::JsonRpcError;
+use crate::types::ErrorCode;
+
+/// An error with a JSON-RPC error code, a message and optional data.
+///
+/// # Examples
+///
+/// ```
+/// use mcp_core::error::McpError;
+/// use mcp_core::types::ErrorCode;
+///
+/// let error = McpError::invalid_params("Missing argument: path");
+/// assert_eq!(error.error_code(), Some(ErrorCode::InvalidParams));
+///
+/// // Handlers return `anyhow::Result`, so the error is converted with `into()`
+/// let result: anyhow::Result<()> = Err(error.into());
+/// assert!(result.unwrap_err().downcast_ref::<McpError>().is_some());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct McpError {
+    /// The JSON-RPC error code
+    pub code: i32,
+    /// A short description of the error
+    pub message: String,
+    /// Optional structured information about the error
+    pub data: Option<Value>,
+}
+
+impl McpError {
+    /// Creates a new error with the given code and message.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The error code
+    /// * `message` - A short description of the error
+    ///
+    /// # Returns
+    ///
+    /// A new `McpError` instance
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code: code as i32,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Attaches structured data to the error.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Additional information about the error
+    ///
+    /// # Returns
+    ///
+    /// The modified error instance
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    /// Creates a parse error (-32700).
+    pub fn parse_error(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::ParseError, message)
+    }
+
+    /// Creates an invalid request error (-32600).
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidRequest, message)
+    }
+
+    /// Creates a method not found error (-32601).
+    pub fn method_not_found(method: &str) -> Self {
+        Self::new(
+            ErrorCode::MethodNotFound,
+            format!("Method not found: {}", method),
+        )
+    }
+
+    /// Creates an invalid params error (-32602).
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidParams, message)
+    }
+
+    /// Creates an internal error (-32603).
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InternalError, message)
+    }
+
+    /// Creates a resource not found error (-32002) for the given URI.
+    ///
+    /// The URI is attached as error data, as recommended by the specification.
+    pub fn resource_not_found(uri: &str) -> Self {
+        Self::new(
+            ErrorCode::ResourceNotFound,
+            format!("Resource not found: {}", uri),
+        )
+        .with_data(serde_json::json!({ "uri": uri }))
+    }
+
+    /// Creates an error for a request the peer did not advertise support for.
+    ///
+    /// The error is raised locally, before the request is sent, and uses the
+    /// method not found code (-32601) the peer would answer with. The missing
+    /// capability is attached as error data, e.g. `{ "capability": "resources.subscribe" }`.
+    pub fn capability_not_supported(method: &str, capability: &str) -> Self {
+        Self::new(
+            ErrorCode::MethodNotFound,
+            format!(
+                "Cannot send {}: the peer does not support the {} capability",
+                method, capability
+            ),
+        )
+        .with_data(serde_json::json!({ "capability": capability }))
+    }
+
+    /// Returns the known `ErrorCode` for this error, if any.
+    ///
+    /// # Returns
+    ///
+    /// `None` for application-defined error codes
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        ErrorCode::from_code(self.code)
+    }
+}
+
+impl fmt::Display for McpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MCP error {}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for McpError {}
+
+impl From<anyhow::Error> for McpError {
+    /// Recovers an `McpError` wrapped in the `anyhow::Error`, falling back to
+    /// an internal error carrying the error message.
+    ///
+    /// Other errors, including `serde_json::Error`, are not mapped to client
+    /// error codes: a handler failing to parse its own data is a server error.
+    fn from(error: anyhow::Error) -> Self {
+        match error.downcast::<McpError>() {
+            Ok(error) => error,
+            Err(error) => Self::internal(error.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for McpError {
+    /// Maps malformed JSON to a parse error and JSON that does not match the
+    /// expected structure to an invalid params error.
+    fn from(error: serde_json::Error) -> Self {
+        match error.classify() {
+            serde_json::error::Category::Syntax | serde_json::error::Category::Eof => {
+                Self::parse_error(error.to_string())
+            }
+            serde_json::error::Category::Data => Self::invalid_params(error.to_string()),
+            serde_json::error::Category::Io => Self::internal(error.to_string()),
+        }
+    }
+}
+
+impl From<JsonRpcError> for McpError {
+    fn from(error: JsonRpcError) -> Self {
+        Self {
+            code: error.code,
+            message: error.message,
+            data: error.data,
+        }
+    }
+}
+
+impl From<McpError> for JsonRpcError {
+    fn from(error: McpError) -> Self {
+        Self {
+            code: error.code,
+            message: error.message,
+            data: error.data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_anyhow() {
+        let error: anyhow::Error = McpError::invalid_params("bad").into();
+        assert_eq!(
+            McpError::from(error).error_code(),
+            Some(ErrorCode::InvalidParams)
+        );
+
+        let error = anyhow::anyhow!("boom");
+        let error = McpError::from(error);
+        assert_eq!(error.error_code(), Some(ErrorCode::InternalError));
+        assert_eq!(error.message, "boom");
+
+        let error: anyhow::Error = serde_json::from_str::<Value>("{").unwrap_err().into();
+        assert_eq!(
+            McpError::from(error).error_code(),
+            Some(ErrorCode::InternalError)
+        );
+    }
+
+    #[test]
+    fn test_from_serde_json() {
+        let error = serde_json::from_str::<Value>("{").unwrap_err();
+        assert_eq!(
+            McpError::from(error).error_code(),
+            Some(ErrorCode::ParseError)
+        );
+
+        let error = serde_json::from_value::<String>(serde_json::json!(1)).unwrap_err();
+        assert_eq!(
+            McpError::from(error).error_code(),
+            Some(ErrorCode::InvalidParams)
+        );
+    }
+}
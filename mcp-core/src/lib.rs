@@ -18,10 +18,20 @@
 //!
 //! - **Client**: Implementation of the MCP client for connecting to servers
 //! - **Server**: Implementation of the MCP server for exposing tools to clients
+//! - **Completions**: Completion providers for prompt arguments and template variables
+//! - **Logging**: A `tracing` layer forwarding server logs to clients
+//! - **Prompts**: Framework for registering prompt templates
 //! - **Protocol**: Core protocol implementation using JSON-RPC
+//! - **Resources**: Framework for exposing static and dynamic resources
+//! - **Sampling**: Handling of server requests to sample the client's LLM
+//! - **Error**: Typed errors carrying JSON-RPC error codes
+//! - **Elicitation**: Requests from servers for information from the user
+//! - **Metrics**: Request, session and queue metrics, with an optional Prometheus exporter
+//! - **Middleware**: Hooks wrapping the dispatch of every request and notification
 //! - **Types**: Data structures representing MCP concepts
 //! - **Transport**: Network transport abstraction (SSE, stdio)
 //! - **Tools**: Framework for registering and invoking tools
+//! - **Trace Context**: W3C `traceparent` propagation through request `_meta`
 //!
 //! ## Usage
 //!
@@ -35,13 +45,29 @@
 //! This library includes a set of utility macros to make working with the MCP protocol
 //! easier, including helpers for creating various types of tool responses.
 
+mod change_notifier;
 pub mod client;
+pub mod completions;
+pub mod elicitation;
+pub mod error;
+pub mod logging;
+pub mod metrics;
+pub mod middleware;
+pub mod prompts;
 pub mod protocol;
+pub mod resources;
+pub mod sampling;
+mod schema;
 pub mod server;
 pub mod tools;
+pub mod trace_context;
 pub mod transport;
 pub mod types;
 
+// Used by the exported macros, so that callers do not need a direct dependency on `serde_json`
+#[doc(hidden)]
+pub use serde_json as __serde_json;
+
 /// Creates a tool response with error information.
 ///
 /// This macro generates a `CallToolResponse` containing a text error message
@@ -69,6 +95,7 @@ macro_rules! tool_error_response {
                     annotations: None,
                 },
             )],
+            structured_content: None,
             is_error: Some(true),
             meta: None,
         }
@@ -97,12 +124,46 @@ macro_rules! tool_text_response {
                     annotations: None,
                 },
             )],
+            structured_content: None,
             is_error: None,
             meta: None,
         }
     }};
 }
 
+/// Creates a tool response with structured content.
+///
+/// This macro generates a `CallToolResponse` whose structured content is the
+/// provided value serialized to JSON, with the same JSON as text content for
+/// clients that do not support structured content.
+///
+/// # Examples
+///
+/// ```
+/// use mcp_core::tool_structured_response;
+/// use serde_json::json;
+///
+/// let response = tool_structured_response!(json!({ "temperature": 21.5 })).unwrap();
+/// assert_eq!(response.structured_content, Some(json!({ "temperature": 21.5 })));
+/// ```
+#[macro_export]
+macro_rules! tool_structured_response {
+    ($e:expr) => {{
+        $crate::__serde_json::to_value(&$e).map(|value| $crate::types::CallToolResponse {
+            content: vec![$crate::types::ToolResponseContent::Text(
+                $crate::types::TextContent {
+                    content_type: "text".to_string(),
+                    text: value.to_string(),
+                    annotations: None,
+                },
+            )],
+            structured_content: Some(value),
+            is_error: None,
+            meta: None,
+        })
+    }};
+}
+
 /// Creates a text content object for tool responses.
 ///
 /// This macro generates a `ToolResponseContent::Text` object with the provided text.
@@ -0,0 +1,297 @@
+//! # MCP Logging
+//!
+//! This module bridges `tracing` to the MCP logging capability. The
+//! `LoggingLayer` forwards the events of the server to the clients as
+//! `notifications/message`, each client receiving the events at or above the
+//! level it set with `logging/setLevel`.
+//!
+//! The server answers `logging/setLevel` and advertises the `logging`
+//! capability once a `LoggingLayer` is created for it, or when its
+//! capabilities enable `logging`. Clients that never set a level receive no
+//! messages.
+
+use crate::protocol::{HandlerFuture, Peer, Protocol, RequestContext};
+use crate::types::{LoggingLevel, LoggingMessageNotification, SetLevelRequest};
+use serde_json::{Map, Value};
+use std::fmt::Debug;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::mpsc;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// A `tracing_subscriber::Layer` forwarding events to the clients of a server.
+///
+/// Each event becomes a `notifications/message` whose logger is the target of
+/// the event and whose data is an object of its fields, including `message`.
+/// By default the events of every target are forwarded, except those of the
+/// transports of this crate (`mcp_core::transport`), which sending the
+/// notifications emits itself. `with_target_filter` selects other targets.
+///
+/// Creating the layer registers a `logging/setLevel` handler on the protocol,
+/// if it has none, and the server then advertises the `logging` capability.
+///
+/// Notifications are sent from a background task, spawned on the Tokio
+/// runtime of the first forwarded event; events recorded outside of a runtime
+/// are dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mcp_core::logging::LoggingLayer;
+/// use mcp_core::server::Server;
+/// use tracing_subscriber::prelude::*;
+///
+/// let protocol = Server::builder(
+///     "logs".to_string(),
+///     "1.0".to_string(),
+///     mcp_core::types::ProtocolVersion::V2025_03_26,
+/// )
+/// .build();
+/// let layer = LoggingLayer::new(protocol).with_target_filter(|target| target.starts_with("my_app"));
+/// tracing_subscriber::registry().with(layer).init();
+/// ```
+#[derive(Clone)]
+pub struct LoggingLayer {
+    protocol: Protocol,
+    target_filter: Arc<TargetFilter>,
+    sender: Arc<OnceLock<MessageSender>>,
+}
+
+/// Decides from its target whether an event is forwarded
+type TargetFilter = dyn Fn(&str) -> bool + Send + Sync;
+
+/// The targets whose events are emitted while forwarding events
+const TRANSPORT_TARGET: &str = concat!(env!("CARGO_CRATE_NAME"), "::transport");
+
+impl LoggingLayer {
+    /// Creates a layer forwarding events to the clients of a server.
+    ///
+    /// # Arguments
+    ///
+    /// * `protocol` - The protocol of the server
+    ///
+    /// # Returns
+    ///
+    /// A new `LoggingLayer`
+    pub fn new(protocol: Protocol) -> Self {
+        if !protocol.has_request_handler("logging/setLevel") {
+            protocol.set_request_handler("logging/setLevel", set_level);
+        }
+        Self {
+            protocol,
+            target_filter: Arc::new(|target| !target.starts_with(TRANSPORT_TARGET)),
+            sender: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// Sets which targets the events are forwarded from.
+    ///
+    /// Forwarding the events of `mcp_core::transport` makes every forwarded
+    /// event emit more events to forward.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - A function returning `true` for the targets to forward
+    ///
+    /// # Returns
+    ///
+    /// The modified layer
+    pub fn with_target_filter(
+        mut self,
+        filter: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.target_filter = Arc::new(filter);
+        self
+    }
+
+    /// Returns the peers of the sessions that asked for messages of a level.
+    fn recipients(&self, level: LoggingLevel) -> Vec<Peer> {
+        let sessions = self.protocol.sessions();
+        sessions
+            .ids()
+            .into_iter()
+            .filter(|session_id| {
+                sessions
+                    .get(session_id.as_deref())
+                    .and_then(|info| info.logging_level)
+                    .is_some_and(|min_level| level >= min_level)
+            })
+            .filter_map(|session_id| sessions.peer(session_id.as_deref()))
+            .collect()
+    }
+
+    /// Returns the sender of the forwarding task, spawning it on first use.
+    fn sender(&self) -> Option<&MessageSender> {
+        if let Some(sender) = self.sender.get() {
+            return Some(sender);
+        }
+        let runtime = tokio::runtime::Handle::try_current().ok()?;
+        Some(self.sender.get_or_init(|| {
+            let (sender, mut receiver): (MessageSender, _) = mpsc::unbounded_channel();
+            // Sends messages one at a time, so that clients receive them in order
+            runtime.spawn(async move {
+                while let Some((peers, params)) = receiver.recv().await {
+                    for peer in peers {
+                        // Failures are not logged, which would forward them again
+                        let _ = peer
+                            .send_notification("notifications/message", Some(params.clone()))
+                            .await;
+                    }
+                }
+            });
+            sender
+        }))
+    }
+}
+
+impl<S: Subscriber> Layer<S> for LoggingLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if !(self.target_filter)(metadata.target()) {
+            return;
+        }
+        let level = LoggingLevel::from(*metadata.level());
+        let peers = self.recipients(level);
+        if peers.is_empty() {
+            return;
+        }
+        let Some(sender) = self.sender() else {
+            return;
+        };
+
+        let mut visitor = JsonVisitor::default();
+        event.record(&mut visitor);
+        let notification = LoggingMessageNotification {
+            level,
+            logger: Some(metadata.target().to_string()),
+            data: Value::Object(visitor.0),
+        };
+        if let Ok(params) = serde_json::to_value(notification) {
+            let _ = sender.send((peers, params));
+        }
+    }
+}
+
+/// Handles `logging/setLevel`, recording the level in the `PeerInfo` of the session.
+pub(crate) fn set_level(req: SetLevelRequest, context: RequestContext) -> HandlerFuture<Value> {
+    Box::pin(async move {
+        context
+            .peer
+            .update_info(|info| info.logging_level = Some(req.level));
+        Ok(serde_json::json!({}))
+    })
+}
+
+/// Sends the params of a `notifications/message` to the peers that asked for it
+type MessageSender = mpsc::UnboundedSender<(Vec<Peer>, Value)>;
+
+/// Collects the fields of an event into a JSON object.
+#[derive(Default)]
+struct JsonVisitor(Map<String, Value>);
+
+impl Visit for JsonVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value).into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::Server;
+    use crate::transport::{to_raw_value, JsonRpcNotification, JsonRpcRequest, Recorder};
+    use crate::types::{InitializeResponse, ProtocolVersion};
+    use serde_json::json;
+    use std::time::Duration;
+    use tracing_subscriber::prelude::*;
+
+    #[tokio::test]
+    async fn test_forwarded_events() {
+        let protocol = Server::builder(
+            "server".to_string(),
+            "1.0".to_string(),
+            ProtocolVersion::V2025_03_26,
+        )
+        .build();
+        let layer = LoggingLayer::new(protocol.clone());
+        let recorder = Arc::new(Recorder::default());
+        let peer = protocol.peer(Some("a".to_string()), recorder.clone());
+        let request = |method: &str, params: Value| JsonRpcRequest {
+            id: 1,
+            method: method.to_string(),
+            params: Some(to_raw_value(&params).unwrap()),
+            jsonrpc: Default::default(),
+        };
+
+        let params = json!({
+            "protocolVersion": "2025-03-26",
+            "capabilities": {},
+            "clientInfo": { "name": "client", "version": "1.0" },
+        });
+        let response = protocol
+            .handle_request_from(request("initialize", params), peer.clone())
+            .await;
+        let result: InitializeResponse =
+            serde_json::from_str(response.result.unwrap().get()).unwrap();
+        assert!(result.capabilities.logging.is_some());
+        protocol
+            .handle_notification_from(
+                JsonRpcNotification {
+                    method: "notifications/initialized".to_string(),
+                    params: None,
+                    jsonrpc: Default::default(),
+                },
+                peer.clone(),
+            )
+            .await;
+        let response = protocol
+            .handle_request_from(
+                request("logging/setLevel", json!({ "level": "warning" })),
+                peer,
+            )
+            .await;
+        assert!(response.error.is_none());
+
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+            tracing::info!(target: "app", "below the level");
+            tracing::warn!(target: "app", "forwarded");
+            tracing::error!(target: "mcp_core::transport::server::sse", "sending failed");
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let notifications = recorder.notifications.lock().unwrap().clone();
+        assert_eq!(
+            notifications,
+            vec![(
+                "notifications/message".to_string(),
+                Some(json!({
+                    "level": "warning",
+                    "logger": "app",
+                    "data": { "message": "forwarded" },
+                })),
+            )]
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! # Metrics
+//!
+//! This module defines the `Metrics` trait, through which the protocol and the
+//! transports report request counts, latencies, errors, active sessions,
+//! in-flight requests and the depth of the SSE message queues.
+//!
+//! A `Metrics` implementation is set with `ProtocolBuilder::metrics` (or
+//! `ServerProtocolBuilder::metrics`). With the `prometheus` feature enabled,
+//! `PrometheusMetrics` records them in a Prometheus registry, and the SSE server
+//! can serve them at `/metrics`.
+
+use std::time::Duration;
+
+#[cfg(feature = "prometheus")]
+mod prometheus;
+#[cfg(feature = "prometheus")]
+pub use self::prometheus::PrometheusMetrics;
+
+/// Receives the measurements of the protocol and the transports.
+///
+/// All methods have an empty default implementation, so an implementation only
+/// needs to provide the measurements it is interested in. Methods are called on
+/// the hot path and must not block.
+///
+/// Requests are broken down by method and, for `tools/call`, by tool name. As
+/// both come from the peer, requests for methods without a handler are reported
+/// with the method `other`, and servers report calls to tools that are not
+/// registered with the tool `other`.
+pub trait Metrics: Send + Sync {
+    /// Called when the handling of an incoming request starts.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method of the request
+    fn request_started(&self, method: &str) {
+        let _ = method;
+    }
+
+    /// Called when the handling of an incoming request finishes.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method of the request
+    /// * `tool` - The name of the called tool, for `tools/call` requests
+    /// * `duration` - The time taken to handle the request
+    /// * `error_code` - The error code of the response, if it is an error
+    fn request_finished(
+        &self,
+        method: &str,
+        tool: Option<&str>,
+        duration: Duration,
+        error_code: Option<i32>,
+    ) {
+        let _ = (method, tool, duration, error_code);
+    }
+
+    /// Called when a transport opens a session with a client.
+    fn session_opened(&self) {}
+
+    /// Called when a transport closes a session with a client.
+    fn session_closed(&self) {}
+
+    /// Called when a message is queued for delivery over an SSE stream.
+    fn sse_message_queued(&self) {}
+
+    /// Called when messages leave the queue of an SSE stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - The number of messages, which are either sent or dropped with their session
+    fn sse_messages_dequeued(&self, count: usize) {
+        let _ = count;
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    ///
+    /// # Returns
+    ///
+    /// The rendered metrics, or `None` if the implementation cannot render them
+    fn render(&self) -> Option<String> {
+        None
+    }
+}
@@ -0,0 +1,185 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use prometheus::{
+    HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+
+use super::Metrics;
+
+/// `Metrics` implementation recording into a Prometheus registry.
+///
+/// The following metrics are registered:
+/// - `mcp_requests_total{method, tool}`: handled requests
+/// - `mcp_request_duration_seconds{method, tool}`: request latency histogram
+/// - `mcp_request_errors_total{method, tool, code}`: requests answered with an error
+/// - `mcp_in_flight_requests`: requests being handled
+/// - `mcp_active_sessions`: open client sessions
+/// - `mcp_sse_queue_depth`: messages waiting to be sent over SSE streams
+///
+/// Unknown methods and, on servers, unregistered tools are labelled `other`.
+///
+/// # Examples
+///
+/// ```
+/// use mcp_core::metrics::{Metrics, PrometheusMetrics};
+/// use mcp_core::protocol::Protocol;
+/// use std::sync::Arc;
+///
+/// let metrics = Arc::new(PrometheusMetrics::new().unwrap());
+/// let protocol = Protocol::builder().metrics(metrics.clone()).build();
+/// assert!(metrics.render().unwrap().contains("mcp_active_sessions"));
+/// ```
+pub struct PrometheusMetrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    durations: HistogramVec,
+    errors: IntCounterVec,
+    in_flight: IntGauge,
+    sessions: IntGauge,
+    sse_queue_depth: IntGauge,
+}
+
+impl PrometheusMetrics {
+    /// Creates the metrics in a new registry.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the metrics
+    pub fn new() -> Result<Self> {
+        Self::with_registry(Registry::new())
+    }
+
+    /// Creates the metrics in an existing registry.
+    ///
+    /// # Arguments
+    ///
+    /// * `registry` - The registry to register the metrics with
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the metrics, or an error if they are already registered
+    pub fn with_registry(registry: Registry) -> Result<Self> {
+        let requests = IntCounterVec::new(
+            Opts::new("mcp_requests_total", "Number of handled MCP requests"),
+            &["method", "tool"],
+        )?;
+        let durations = HistogramVec::new(
+            HistogramOpts::new(
+                "mcp_request_duration_seconds",
+                "Time taken to handle MCP requests",
+            ),
+            &["method", "tool"],
+        )?;
+        let errors = IntCounterVec::new(
+            Opts::new(
+                "mcp_request_errors_total",
+                "Number of MCP requests answered with an error",
+            ),
+            &["method", "tool", "code"],
+        )?;
+        let in_flight = IntGauge::new(
+            "mcp_in_flight_requests",
+            "Number of MCP requests being handled",
+        )?;
+        let sessions = IntGauge::new("mcp_active_sessions", "Number of open MCP sessions")?;
+        let sse_queue_depth = IntGauge::new(
+            "mcp_sse_queue_depth",
+            "Number of messages waiting to be sent over SSE streams",
+        )?;
+
+        registry.register(Box::new(requests.clone()))?;
+        registry.register(Box::new(durations.clone()))?;
+        registry.register(Box::new(errors.clone()))?;
+        registry.register(Box::new(in_flight.clone()))?;
+        registry.register(Box::new(sessions.clone()))?;
+        registry.register(Box::new(sse_queue_depth.clone()))?;
+
+        Ok(Self {
+            registry,
+            requests,
+            durations,
+            errors,
+            in_flight,
+            sessions,
+            sse_queue_depth,
+        })
+    }
+
+    /// Returns the registry of the metrics, e.g. to register further metrics.
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+}
+
+impl Metrics for PrometheusMetrics {
+    fn request_started(&self, _method: &str) {
+        self.in_flight.inc();
+    }
+
+    fn request_finished(
+        &self,
+        method: &str,
+        tool: Option<&str>,
+        duration: Duration,
+        error_code: Option<i32>,
+    ) {
+        let tool = tool.unwrap_or_default();
+        self.in_flight.dec();
+        self.requests.with_label_values(&[method, tool]).inc();
+        self.durations
+            .with_label_values(&[method, tool])
+            .observe(duration.as_secs_f64());
+        if let Some(code) = error_code {
+            self.errors
+                .with_label_values(&[method, tool, &code.to_string()])
+                .inc();
+        }
+    }
+
+    fn session_opened(&self) {
+        self.sessions.inc();
+    }
+
+    fn session_closed(&self) {
+        self.sessions.dec();
+    }
+
+    fn sse_message_queued(&self) {
+        self.sse_queue_depth.inc();
+    }
+
+    fn sse_messages_dequeued(&self, count: usize) {
+        self.sse_queue_depth.sub(count as i64);
+    }
+
+    fn render(&self) -> Option<String> {
+        TextEncoder::new()
+            .encode_to_string(&self.registry.gather())
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_metrics() {
+        let metrics = PrometheusMetrics::new().unwrap();
+        metrics.request_started("tools/call");
+        metrics.request_finished(
+            "tools/call",
+            Some("echo"),
+            Duration::from_millis(5),
+            Some(-32602),
+        );
+
+        let rendered = metrics.render().unwrap();
+        assert!(rendered.contains(r#"mcp_requests_total{method="tools/call",tool="echo"} 1"#));
+        assert!(rendered.contains(
+            r#"mcp_request_errors_total{code="-32602",method="tools/call",tool="echo"} 1"#
+        ));
+        assert!(rendered.contains("mcp_in_flight_requests 0"));
+    }
+}
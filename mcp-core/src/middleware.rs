@@ -0,0 +1,183 @@
+//! # Protocol Middleware
+//!
+//! This module defines the `Middleware` trait, which wraps the dispatch of every
+//! incoming request and notification of a `Protocol`.
+//!
+//! Middleware is registered on the `ProtocolBuilder` (or `ServerProtocolBuilder`)
+//! and is a good fit for concerns shared by all handlers, such as authentication,
+//! audit logging, metrics, rate limiting or redaction of arguments.
+//!
+//! For requests, the `before_request` hooks run in registration order before the
+//! handler is looked up, and the `after_request` hooks run in reverse order once
+//! the response is known. A `before_request` hook can short-circuit the request
+//! by returning an error, in which case the handler is not called and only the
+//! middleware that already ran sees the error response.
+//!
+//! The `Lifecycle` middleware, which the server installs before any other,
+//! enforces the initialization sequence of each session.
+
+use async_trait::async_trait;
+
+use crate::error::McpError;
+use crate::protocol::{LifecycleState, RequestContext};
+use crate::transport::{JsonRpcNotification, JsonRpcRequest, JsonRpcResponse};
+
+/// Hooks wrapping the dispatch of incoming messages.
+///
+/// All methods have default implementations that do nothing, so implementors
+/// only override the hooks they need. The `RequestContext` gives access to the
+/// method name, the session the message arrived on and the peer that sent it.
+///
+/// # Examples
+///
+/// ```
+/// use async_trait::async_trait;
+/// use mcp_core::error::McpError;
+/// use mcp_core::middleware::Middleware;
+/// use mcp_core::protocol::RequestContext;
+/// use mcp_core::transport::JsonRpcRequest;
+///
+/// struct RequireSession;
+///
+/// #[async_trait]
+/// impl Middleware for RequireSession {
+///     async fn before_request(
+///         &self,
+///         _request: &mut JsonRpcRequest,
+///         context: &RequestContext,
+///     ) -> Result<(), McpError> {
+///         if context.method == "tools/call" && context.session_id().is_none() {
+///             return Err(McpError::invalid_request("Anonymous tool calls are not allowed"));
+///         }
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    /// Called before a request is dispatched to its handler.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The incoming request, which may be modified
+    /// * `context` - The context the handler will receive
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` to continue, or an error that is sent back instead of calling the handler
+    async fn before_request(
+        &self,
+        _request: &mut JsonRpcRequest,
+        _context: &RequestContext,
+    ) -> Result<(), McpError> {
+        Ok(())
+    }
+
+    /// Called after a request has been handled, before the response is sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The context of the request
+    /// * `response` - The response, which may be modified
+    async fn after_request(&self, _context: &RequestContext, _response: &mut JsonRpcResponse) {}
+
+    /// Called before a notification is dispatched to its handler.
+    ///
+    /// # Arguments
+    ///
+    /// * `notification` - The incoming notification, which may be modified
+    /// * `context` - The context the handler will receive
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` to continue, or an error to drop the notification
+    async fn before_notification(
+        &self,
+        _notification: &mut JsonRpcNotification,
+        _context: &RequestContext,
+    ) -> Result<(), McpError> {
+        Ok(())
+    }
+}
+
+/// Middleware enforcing the lifecycle of each session.
+///
+/// Before a session is initialized, only `initialize` and `ping` requests are
+/// accepted; any other request, including those for custom handlers, is
+/// rejected with an `InvalidRequest` error. A second `initialize` is rejected
+/// as well. Notifications other than `notifications/initialized` and
+/// `notifications/cancelled` are dropped until the session is initialized.
+/// Once the session is closed, every request and notification is rejected.
+///
+/// `ServerProtocolBuilder` installs this middleware automatically. It can be
+/// added to a `ProtocolBuilder` to get the same behavior for custom servers.
+pub struct Lifecycle;
+
+#[async_trait]
+impl Middleware for Lifecycle {
+    async fn before_request(
+        &self,
+        _request: &mut JsonRpcRequest,
+        context: &RequestContext,
+    ) -> Result<(), McpError> {
+        if context.peer.info().state == LifecycleState::Closed {
+            return Err(McpError::invalid_request("Session is closed"));
+        }
+        match context.method.as_str() {
+            "ping" => Ok(()),
+            "initialize" => {
+                // Check and update the state at once, so that concurrent
+                // `initialize` requests cannot both pass
+                let mut previous = LifecycleState::Uninitialized;
+                context.peer.update_info(|info| {
+                    previous = info.state;
+                    if info.state == LifecycleState::Uninitialized {
+                        info.state = LifecycleState::Initializing;
+                    }
+                });
+                match previous {
+                    LifecycleState::Uninitialized => Ok(()),
+                    _ => Err(McpError::invalid_request("Session is already initialized")),
+                }
+            }
+            _ => match context.peer.info().state {
+                LifecycleState::Initialized => Ok(()),
+                LifecycleState::Closed => Err(McpError::invalid_request("Session is closed")),
+                LifecycleState::Initializing => Err(McpError::invalid_request(
+                    "Session is not initialized: waiting for notifications/initialized",
+                )),
+                LifecycleState::Uninitialized => Err(McpError::invalid_request(
+                    "Session is not initialized: send initialize first",
+                )),
+            },
+        }
+    }
+
+    async fn after_request(&self, context: &RequestContext, response: &mut JsonRpcResponse) {
+        // A failed `initialize` can be retried
+        if context.method == "initialize" && response.error.is_some() {
+            context.peer.update_info(|info| {
+                if info.state == LifecycleState::Initializing {
+                    info.state = LifecycleState::Uninitialized;
+                }
+            });
+        }
+    }
+
+    async fn before_notification(
+        &self,
+        notification: &mut JsonRpcNotification,
+        context: &RequestContext,
+    ) -> Result<(), McpError> {
+        let state = context.peer.info().state;
+        match (notification.method.as_str(), state) {
+            (_, LifecycleState::Closed) => Err(McpError::invalid_request("Session is closed")),
+            (_, LifecycleState::Initialized) | ("notifications/cancelled", _) => Ok(()),
+            ("notifications/initialized", LifecycleState::Initializing) => Ok(()),
+            _ => Err(McpError::invalid_request(format!(
+                "Unexpected notification in state {:?}",
+                state
+            ))),
+        }
+    }
+}
@@ -0,0 +1,283 @@
+//! # MCP Prompts Management
+//!
+//! This module provides the infrastructure for exposing prompts from a server.
+//! Prompts are templates of messages, filled in with the arguments given by the
+//! client, e.g. to start a code review of a file.
+//!
+//! Like the tool registry, the prompt registry can be changed while the server
+//! is running; the server announces changes with
+//! `notifications/prompts/list_changed`.
+
+use crate::change_notifier::ChangeNotifier;
+use crate::error::McpError;
+use crate::protocol::RequestContext;
+use crate::types::{GetPromptRequest, GetPromptResponse, Prompt, PromptMessage};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Registry of the prompts of a server.
+///
+/// The `Prompts` struct manages a collection of prompts and their associated
+/// handlers, providing methods to list and get them.
+pub struct Prompts {
+    prompt_handlers: RwLock<HashMap<String, Arc<PromptHandler>>>,
+}
+
+impl Prompts {
+    /// Creates a new, empty prompt registry.
+    pub(crate) fn new() -> Self {
+        Self {
+            prompt_handlers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Retrieves a prompt definition by name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the prompt to retrieve
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the prompt if found, or `None` if not found.
+    pub fn get_prompt(&self, name: &str) -> Option<Prompt> {
+        self.prompt_handlers
+            .read()
+            .ok()?
+            .get(name)
+            .map(|prompt_handler| prompt_handler.prompt.clone())
+    }
+
+    /// Fills in a prompt with the arguments of a request.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - The request containing the prompt name and arguments
+    /// * `context` - The context of the `prompts/get` request
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the messages of the prompt, or an invalid params
+    /// error if the prompt is not found or a required argument is missing.
+    pub async fn render_prompt(
+        &self,
+        req: GetPromptRequest,
+        context: RequestContext,
+    ) -> Result<GetPromptResponse> {
+        let handler = self
+            .prompt_handlers
+            .read()
+            .ok()
+            .and_then(|prompt_handlers| prompt_handlers.get(&req.name).cloned())
+            .ok_or_else(|| McpError::invalid_params(format!("Prompt not found: {}", req.name)))?;
+
+        let arguments = req.arguments.unwrap_or_default();
+        let missing = handler.prompt.arguments.iter().flatten().find(|argument| {
+            argument.required == Some(true) && !arguments.contains_key(&argument.name)
+        });
+        if let Some(argument) = missing {
+            return Err(McpError::invalid_params(format!(
+                "Missing required argument: {}",
+                argument.name
+            ))
+            .into());
+        }
+
+        let messages = (handler.f)(arguments, context).await?;
+        Ok(GetPromptResponse {
+            description: handler.prompt.description.clone(),
+            messages,
+            meta: None,
+        })
+    }
+
+    /// Lists all registered prompts.
+    ///
+    /// # Returns
+    ///
+    /// A vector containing all registered prompts.
+    pub fn list_prompts(&self) -> Vec<Prompt> {
+        self.prompt_handlers
+            .read()
+            .map(|prompt_handlers| {
+                prompt_handlers
+                    .values()
+                    .map(|prompt_handler| prompt_handler.prompt.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// A handle for changing the prompts of a server while it is running.
+///
+/// Clones of a `PromptRegistry` share the same prompts. After each change, the
+/// registered change listeners are called; the server uses this to send
+/// `notifications/prompts/list_changed` when its
+/// `PromptCapabilities.list_changed` is set.
+///
+/// # Examples
+///
+/// ```
+/// use mcp_core::server::Server;
+/// use mcp_core::types::{
+///     Prompt, PromptArgument, PromptMessage, PromptMessageContent, Role, TextContent,
+/// };
+///
+/// let review = Prompt {
+///     name: "review".to_string(),
+///     description: Some("Review a file".to_string()),
+///     arguments: Some(vec![PromptArgument {
+///         name: "path".to_string(),
+///         description: None,
+///         required: Some(true),
+///     }]),
+/// };
+/// let builder = Server::builder(
+///     "prompts".to_string(),
+///     "1.0".to_string(),
+///     mcp_core::types::ProtocolVersion::V2025_03_26,
+/// )
+/// .register_prompt(review, |arguments, _| {
+///     Box::pin(async move {
+///         Ok(vec![PromptMessage {
+///             role: Role::User,
+///             content: PromptMessageContent::Text(TextContent {
+///                 content_type: "text".to_string(),
+///                 text: format!("Please review {}", arguments["path"]),
+///                 annotations: None,
+///             }),
+///         }])
+///     })
+/// });
+/// let registry = builder.prompt_registry();
+/// let protocol = builder.build();
+/// assert!(registry.remove_prompt("review"));
+/// ```
+#[derive(Clone)]
+pub struct PromptRegistry {
+    prompts: Arc<Prompts>,
+    changes: ChangeNotifier,
+}
+
+impl PromptRegistry {
+    /// Creates a new, empty prompt registry.
+    ///
+    /// # Returns
+    ///
+    /// A new `PromptRegistry` instance
+    pub fn new() -> Self {
+        Self {
+            prompts: Arc::new(Prompts::new()),
+            changes: ChangeNotifier::default(),
+        }
+    }
+
+    /// Returns the prompts of the registry, for listing and getting them.
+    pub fn prompts(&self) -> Arc<Prompts> {
+        self.prompts.clone()
+    }
+
+    /// Adds a prompt, replacing any prompt with the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The prompt definition
+    /// * `f` - The handler, called with the arguments of the request once the
+    ///   required arguments are checked, and the context of the `prompts/get` request
+    pub fn add_prompt(
+        &self,
+        prompt: Prompt,
+        f: impl Fn(
+                HashMap<String, String>,
+                RequestContext,
+            ) -> Pin<Box<dyn Future<Output = Result<Vec<PromptMessage>>> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.prompts
+            .prompt_handlers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(
+                prompt.name.clone(),
+                Arc::new(PromptHandler {
+                    prompt,
+                    f: Box::new(f),
+                }),
+            );
+        self.changes.notify();
+    }
+
+    /// Removes a prompt.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the prompt to remove
+    ///
+    /// # Returns
+    ///
+    /// `true` if the prompt was removed, `false` if there was no such prompt
+    pub fn remove_prompt(&self, name: &str) -> bool {
+        let removed = self
+            .prompts
+            .prompt_handlers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(name)
+            .is_some();
+        if removed {
+            self.changes.notify();
+        }
+        removed
+    }
+
+    /// Retrieves a prompt definition by name.
+    pub fn get_prompt(&self, name: &str) -> Option<Prompt> {
+        self.prompts.get_prompt(name)
+    }
+
+    /// Lists all registered prompts.
+    pub fn list_prompts(&self) -> Vec<Prompt> {
+        self.prompts.list_prompts()
+    }
+
+    /// Registers a function called after every change to the prompts.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The function to call
+    pub fn on_change(&self, f: impl Fn() + Send + Sync + 'static) {
+        self.changes.on_change(f);
+    }
+}
+
+impl Default for PromptRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Type alias for a prompt handler.
+///
+/// A prompt handler is called with the arguments of a `prompts/get` request
+/// and its `RequestContext`, and returns a future that resolves to the
+/// messages of the prompt.
+pub type PromptHandlerFn = dyn Fn(
+        HashMap<String, String>,
+        RequestContext,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<PromptMessage>>> + Send>>
+    + Send
+    + Sync;
+
+/// Container for a prompt definition and its handler.
+pub(crate) struct PromptHandler {
+    /// The prompt definition (name, description, arguments)
+    pub prompt: Prompt,
+    /// The handler producing the messages of the prompt
+    pub f: Box<PromptHandlerFn>,
+}
@@ -12,9 +12,22 @@
 //! - `ProtocolBuilder`: A builder for configuring protocols
 //! - Request and notification handlers
 //! - Timeout and error handling
+//! - Liveness checks using `ping` requests
 
-use super::transport::{JsonRpcError, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse};
-use super::types::ErrorCode;
+use super::error::McpError;
+use super::metrics::Metrics;
+use super::middleware::Middleware;
+use super::trace_context::{self, TraceParent};
+use super::transport::{
+    from_raw_value, to_raw_value, JsonRpcError, JsonRpcNotification, JsonRpcRequest,
+    JsonRpcResponse, RawValue, RequestId, Transport,
+};
+use super::types::{
+    CancelledNotification, ClientCapabilities, CreateMessageRequest, CreateMessageResult,
+    ElicitAction, ElicitRequest, ElicitResult, ElicitationSchema, ErrorCode, Implementation,
+    ListRootsResult, LoggingLevel, ProtocolVersion, Root, ServerCapabilities,
+    LATEST_PROTOCOL_VERSION,
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::de::DeserializeOwned;
@@ -22,10 +35,17 @@ use serde::Serialize;
 use serde_json::json;
 use std::pin::Pin;
 
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError, RwLock};
 use std::time::Duration;
-use std::{collections::HashMap, sync::Arc};
-use tokio::sync::{oneshot, Mutex};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+use tokio::sync::{oneshot, Notify};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 
 /// The core protocol handler for MCP.
 ///
@@ -35,9 +55,17 @@ use tokio::sync::{oneshot, Mutex};
 #[derive(Clone)]
 pub struct Protocol {
     request_id: Arc<AtomicU64>,
-    pending_requests: Arc<Mutex<HashMap<u64, oneshot::Sender<JsonRpcResponse>>>>,
-    request_handlers: Arc<Mutex<HashMap<String, Box<dyn RequestHandler>>>>,
-    notification_handlers: Arc<Mutex<HashMap<String, Box<dyn NotificationHandler>>>>,
+    pending_requests: Arc<Mutex<HashMap<RequestId, PendingRequest>>>,
+    closed: Arc<AtomicBool>,
+    request_handlers: RequestHandlers,
+    notification_handlers: NotificationHandlers,
+    in_flight: Arc<RwLock<HashMap<InFlightKey, CancellationToken>>>,
+    peer_infos: Arc<RwLock<HashMap<Option<String>, PeerInfo>>>,
+    sessions: Arc<RwLock<HashMap<Option<String>, SessionTransport>>>,
+    progress_waiters: Arc<RwLock<HashMap<String, Arc<Notify>>>>,
+    middlewares: Arc<Vec<Arc<dyn Middleware>>>,
+    timeout_policy: Arc<TimeoutPolicy>,
+    metrics: Option<Arc<dyn Metrics>>,
 }
 
 impl Protocol {
@@ -50,10 +78,271 @@ impl Protocol {
         ProtocolBuilder::new()
     }
 
+    /// Creates a handle to the peer on the other end of a transport.
+    ///
+    /// Transports create a peer for each session and pass it along with incoming
+    /// messages, so that handlers can reach back to the caller.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The ID of the session, if the transport has several
+    /// * `transport` - The transport used to reach the peer
+    ///
+    /// # Returns
+    ///
+    /// A new `Peer` instance
+    pub fn peer(&self, session_id: Option<String>, transport: Arc<dyn Transport>) -> Peer {
+        Peer {
+            session_id,
+            transport: Some(transport),
+            protocol: self.clone(),
+        }
+    }
+
+    /// Returns what is known about the peer of a session.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The ID of the session
+    ///
+    /// # Returns
+    ///
+    /// The `PeerInfo` of the session, empty if the peer has not initialized yet
+    pub fn peer_info(&self, session_id: Option<&str>) -> PeerInfo {
+        self.peer_infos
+            .read()
+            .ok()
+            .and_then(|infos| infos.get(&session_id.map(str::to_string)).cloned())
+            .unwrap_or_default()
+    }
+
+    /// Updates what is known about the peer of a session.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The ID of the session
+    /// * `f` - A function that modifies the `PeerInfo` of the session
+    pub fn update_peer_info(&self, session_id: Option<&str>, f: impl FnOnce(&mut PeerInfo)) {
+        if let Ok(mut infos) = self.peer_infos.write() {
+            f(infos.entry(session_id.map(str::to_string)).or_default());
+        }
+    }
+
+    /// Updates what is known about the peers of all sessions.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - A function that modifies the `PeerInfo` of a session
+    pub(crate) fn update_peer_infos(&self, mut f: impl FnMut(&mut PeerInfo)) {
+        if let Ok(mut infos) = self.peer_infos.write() {
+            infos.values_mut().for_each(&mut f);
+        }
+    }
+
+    /// Returns a view of the sessions of the protocol.
+    pub fn sessions(&self) -> SessionRegistry {
+        SessionRegistry {
+            protocol: self.clone(),
+        }
+    }
+
+    /// Registers a request handler on a running protocol.
+    ///
+    /// Any handler previously registered for the method is replaced. Requests
+    /// that are already being handled keep using the old handler.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method name to handle
+    /// * `handler` - The handler function, called with the params and the context
+    pub fn set_request_handler<Req, Resp>(
+        &self,
+        method: &str,
+        handler: impl Fn(Req, RequestContext) -> HandlerFuture<Resp> + Send + Sync + 'static,
+    ) where
+        Req: DeserializeOwned + Send + Sync + 'static,
+        Resp: Serialize + Send + Sync + 'static,
+    {
+        let handler = TypedRequestHandler {
+            handler: Box::new(handler),
+            _phantom: std::marker::PhantomData,
+        };
+        self.request_handlers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(method.to_string(), Arc::new(handler));
+    }
+
+    /// Checks if a request handler is registered for a method.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method name to check
+    ///
+    /// # Returns
+    ///
+    /// `true` if a handler exists, `false` otherwise
+    pub fn has_request_handler(&self, method: &str) -> bool {
+        self.request_handlers
+            .read()
+            .map(|handlers| handlers.contains_key(method))
+            .unwrap_or(false)
+    }
+
+    /// Removes the request handler of a method from a running protocol.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method name
+    ///
+    /// # Returns
+    ///
+    /// `true` if a handler was removed, `false` otherwise
+    pub fn remove_request_handler(&self, method: &str) -> bool {
+        self.request_handlers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(method)
+            .is_some()
+    }
+
+    /// Registers a notification handler on a running protocol.
+    ///
+    /// Any handler previously registered for the method is replaced.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method name to handle
+    /// * `handler` - The handler function, called with the params and the context
+    pub fn set_notification_handler<N>(
+        &self,
+        method: &str,
+        handler: impl Fn(N, RequestContext) -> HandlerFuture<()> + Send + Sync + 'static,
+    ) where
+        N: DeserializeOwned + Send + Sync + 'static,
+    {
+        let handler = TypedNotificationHandler {
+            handler: Box::new(handler),
+            _phantom: std::marker::PhantomData,
+        };
+        self.notification_handlers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(method.to_string(), Arc::new(handler));
+    }
+
+    /// Removes the notification handler of a method from a running protocol.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method name
+    ///
+    /// # Returns
+    ///
+    /// `true` if a handler was removed, `false` otherwise
+    pub fn remove_notification_handler(&self, method: &str) -> bool {
+        self.notification_handlers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(method)
+            .is_some()
+    }
+
+    /// Closes a session, e.g. when its transport is closed.
+    ///
+    /// The session moves to the `Closed` state, in which the `Lifecycle`
+    /// middleware rejects any further request or notification. It no longer
+    /// receives broadcast notifications, and the requests it sent that are
+    /// still being handled are cancelled, as their responses can no longer be
+    /// delivered. Requests sent to the session that are still waiting for a
+    /// response fail with a `ConnectionClosed` error.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The ID of the session
+    pub fn close_session(&self, session_id: Option<&str>) {
+        let key = session_id.map(str::to_string);
+        if let Ok(mut infos) = self.peer_infos.write() {
+            if let Some(info) = infos.get_mut(&key) {
+                info.state = LifecycleState::Closed;
+            }
+        }
+        if let Ok(mut sessions) = self.sessions.write() {
+            sessions.remove(&key);
+        }
+        if let Ok(in_flight) = self.in_flight.read() {
+            in_flight
+                .iter()
+                .filter(|((id, _), _)| *id == key)
+                .for_each(|(_, token)| token.cancel());
+        }
+
+        let closed: HashMap<_, _> = {
+            let mut pending = self.pending_requests();
+            let (closed, kept) = std::mem::take(&mut *pending)
+                .into_iter()
+                .partition(|(_, request)| request.session_id == key);
+            *pending = kept;
+            closed
+        };
+        for (id, request) in closed {
+            let _ = request.tx.send(connection_closed_response(id));
+        }
+    }
+
+    /// Forgets a session, e.g. when its connection is closed.
+    ///
+    /// The session is closed as with `close_session`, then its `PeerInfo` is
+    /// dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The ID of the session
+    pub fn remove_session(&self, session_id: Option<&str>) {
+        self.close_session(session_id);
+        if let Ok(mut infos) = self.peer_infos.write() {
+            infos.remove(&session_id.map(str::to_string));
+        }
+    }
+
+    /// Sends a notification to every initialized session.
+    ///
+    /// A session counts as initialized once its peer has sent
+    /// `notifications/initialized`. Failures to reach a session are logged.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The notification method
+    /// * `params` - Optional parameters for the notification
+    pub async fn broadcast_notification(&self, method: &str, params: Option<serde_json::Value>) {
+        let sessions: Vec<_> = self
+            .sessions
+            .read()
+            .map(|sessions| {
+                sessions
+                    .iter()
+                    .map(|(id, transport)| (id.clone(), transport.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        for (session_id, transport) in sessions {
+            if let Err(e) = transport.send_notification(method, params.clone()).await {
+                tracing::debug!(
+                    "Failed to send {} to session {:?}: {}",
+                    method,
+                    session_id,
+                    e
+                );
+            }
+        }
+    }
+
     /// Handles an incoming JSON-RPC request.
     ///
     /// This method dispatches the request to the appropriate handler based on
-    /// the request method, and returns the handler's response.
+    /// the request method, and returns the handler's response. Handlers that
+    /// use the `RequestContext` get a peer that is not connected to any
+    /// transport; transports use `handle_request_from` instead.
     ///
     /// # Arguments
     ///
@@ -61,33 +350,167 @@ impl Protocol {
     ///
     /// # Returns
     ///
-    /// A `JsonRpcResponse` containing the handler's response or an error
+    /// A `JsonRpcResponse` containing the handler's response or an error.
+    /// Errors returned by the handler are reported with the code of the
+    /// `McpError` they carry, or as an internal error otherwise.
     pub async fn handle_request(&self, request: JsonRpcRequest) -> JsonRpcResponse {
-        let handlers = self.request_handlers.lock().await;
-        if let Some(handler) = handlers.get(&request.method) {
-            match handler.handle(request.clone()).await {
-                Ok(response) => response,
-                Err(e) => JsonRpcResponse {
-                    id: request.id,
-                    result: None,
-                    error: Some(JsonRpcError {
-                        code: ErrorCode::InternalError as i32,
-                        message: e.to_string(),
-                        data: None,
-                    }),
-                    ..Default::default()
-                },
+        self.handle_request_from(request, Peer::detached(self.clone(), None))
+            .await
+    }
+
+    /// Handles an incoming JSON-RPC request received from a peer.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The incoming JSON-RPC request
+    /// * `peer` - The peer that sent the request
+    ///
+    /// # Returns
+    ///
+    /// A `JsonRpcResponse` containing the handler's response or an error
+    pub async fn handle_request_from(
+        &self,
+        request: JsonRpcRequest,
+        peer: Peer,
+    ) -> JsonRpcResponse {
+        let method = request.method.clone();
+        let tool = tool_name(&method, request.params.as_deref());
+        let (span, trace_parent) = Self::request_span(&request, tool.as_deref(), &peer);
+        // Unknown methods are not used as labels, so that peers cannot add series at will
+        let known = self.has_request_handler(&method);
+        let label = if known { method.as_str() } else { "other" };
+        if let Some(metrics) = &self.metrics {
+            metrics.request_started(label);
+        }
+
+        let start = Instant::now();
+        let response = trace_context::scope(trace_parent, self.run_request(request, peer))
+            .instrument(span.clone())
+            .await;
+        let duration = start.elapsed();
+        let error_code = response.error.as_ref().map(|error| error.code);
+
+        span.record("duration_ms", duration.as_secs_f64() * 1000.0);
+        if let Some(code) = error_code {
+            span.record("error.code", code);
+        }
+        if let Some(metrics) = &self.metrics {
+            let tool = tool.as_deref().filter(|_| known);
+            metrics.request_finished(label, tool, duration, error_code);
+        }
+        response
+    }
+
+    /// Returns the metrics of the protocol, if any.
+    pub fn metrics(&self) -> Option<&Arc<dyn Metrics>> {
+        self.metrics.as_ref()
+    }
+
+    /// Runs the middleware and the handler of a request.
+    async fn run_request(&self, mut request: JsonRpcRequest, peer: Peer) -> JsonRpcResponse {
+        let key = (peer.session_id.clone(), request.id);
+        let cancellation = CancellationToken::new();
+        let mut context = RequestContext {
+            request_id: Some(request.id),
+            method: request.method.clone(),
+            meta: extract_meta(request.params.as_deref()),
+            peer,
+            cancellation: cancellation.clone(),
+        };
+
+        let mut entered = 0;
+        let mut rejection = None;
+        for middleware in self.middlewares.iter() {
+            if let Err(e) = middleware.before_request(&mut request, &context).await {
+                rejection = Some(e);
+                break;
             }
-        } else {
-            JsonRpcResponse {
-                id: request.id,
-                error: Some(JsonRpcError {
-                    code: ErrorCode::MethodNotFound as i32,
-                    message: format!("Method not found: {}", request.method),
-                    data: None,
-                }),
+            entered += 1;
+        }
+        // Middleware may have rewritten the params
+        context.meta = extract_meta(request.params.as_deref());
+
+        let id = request.id;
+        let mut response = match rejection {
+            Some(e) => JsonRpcResponse {
+                id,
+                error: Some(e.into()),
                 ..Default::default()
+            },
+            None => {
+                if let Ok(mut in_flight) = self.in_flight.write() {
+                    in_flight.insert(key.clone(), cancellation);
+                }
+                let response = self.dispatch_request(request, context.clone()).await;
+                if let Ok(mut in_flight) = self.in_flight.write() {
+                    in_flight.remove(&key);
+                }
+                response
             }
+        };
+
+        for middleware in self.middlewares[..entered].iter().rev() {
+            middleware.after_request(&context, &mut response).await;
+        }
+        response
+    }
+
+    /// Creates the span of an incoming request, continuing the trace of its
+    /// `traceparent`, and the trace context for requests sent while handling it.
+    fn request_span(
+        request: &JsonRpcRequest,
+        tool_name: Option<&str>,
+        peer: &Peer,
+    ) -> (tracing::Span, TraceParent) {
+        let trace_parent = TraceParent::from_meta(extract_meta(request.params.as_deref()).as_ref());
+        let current = trace_parent
+            .as_ref()
+            .map(TraceParent::child)
+            .unwrap_or_else(TraceParent::new_root);
+        let span = tracing::info_span!(
+            "mcp.request",
+            otel.kind = "server",
+            rpc.method = %request.method,
+            rpc.request_id = request.id,
+            session.id = peer.session_id.as_deref(),
+            mcp.tool.name = tool_name,
+            trace_id = %current.trace_id,
+            span_id = %current.span_id,
+            parent_span_id = trace_parent.as_ref().map(|parent| parent.span_id.as_str()),
+            error.code = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        );
+        (span, current)
+    }
+
+    /// Passes a request to its handler.
+    async fn dispatch_request(
+        &self,
+        request: JsonRpcRequest,
+        context: RequestContext,
+    ) -> JsonRpcResponse {
+        let handler = self
+            .request_handlers
+            .read()
+            .ok()
+            .and_then(|handlers| handlers.get(&request.method).cloned());
+        let Some(handler) = handler else {
+            return JsonRpcResponse {
+                id: request.id,
+                error: Some(McpError::method_not_found(&request.method).into()),
+                ..Default::default()
+            };
+        };
+
+        let id = request.id;
+        match handler.handle(request, context).await {
+            Ok(response) => response,
+            Err(e) => JsonRpcResponse {
+                id,
+                result: None,
+                error: Some(e.into()),
+                ..Default::default()
+            },
         }
     }
 
@@ -100,10 +523,64 @@ impl Protocol {
     ///
     /// * `request` - The incoming JSON-RPC notification
     pub async fn handle_notification(&self, request: JsonRpcNotification) {
-        let handlers = self.notification_handlers.lock().await;
-        if let Some(handler) = handlers.get(&request.method) {
-            match handler.handle(request.clone()).await {
-                Ok(_) => tracing::info!("Received notification: {:?}", request.method),
+        self.handle_notification_from(request, Peer::detached(self.clone(), None))
+            .await
+    }
+
+    /// Handles an incoming JSON-RPC notification received from a peer.
+    ///
+    /// `notifications/cancelled` is handled here by cancelling the token of the
+    /// referenced request, before being passed on to any registered handler.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The incoming JSON-RPC notification
+    /// * `peer` - The peer that sent the notification
+    pub async fn handle_notification_from(&self, mut request: JsonRpcNotification, peer: Peer) {
+        let mut context = RequestContext {
+            request_id: None,
+            method: request.method.clone(),
+            meta: extract_meta(request.params.as_deref()),
+            peer,
+            cancellation: CancellationToken::new(),
+        };
+        for middleware in self.middlewares.iter() {
+            if let Err(e) = middleware.before_notification(&mut request, &context).await {
+                tracing::debug!("Notification {} rejected: {}", request.method, e);
+                return;
+            }
+        }
+        context.meta = extract_meta(request.params.as_deref());
+
+        match request.method.as_str() {
+            "notifications/cancelled" => self.cancel_in_flight(&request, &context.peer),
+            "notifications/progress" => self.notify_progress(&request),
+            "notifications/roots/list_changed" => context.peer.update_info(|info| {
+                info.roots = None;
+                info.roots_changes += 1;
+            }),
+            "notifications/initialized" => {
+                context
+                    .peer
+                    .update_info(|info| info.state = LifecycleState::Initialized);
+                if let Some(transport) = context.peer.transport.clone() {
+                    if let Ok(mut sessions) = self.sessions.write() {
+                        sessions.insert(context.peer.session_id.clone(), transport);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let handler = self
+            .notification_handlers
+            .read()
+            .ok()
+            .and_then(|handlers| handlers.get(&request.method).cloned());
+        if let Some(handler) = handler {
+            let method = request.method.clone();
+            match handler.handle(request, context).await {
+                Ok(_) => tracing::info!("Received notification: {:?}", method),
                 Err(e) => tracing::error!("Error handling notification: {}", e),
             }
         } else {
@@ -111,98 +588,1091 @@ impl Protocol {
         }
     }
 
-    /// Generates a new unique message ID for requests.
+    /// Wakes up the request waiting for a `notifications/progress` notification, if any.
+    fn notify_progress(&self, notification: &JsonRpcNotification) {
+        #[derive(serde::Deserialize)]
+        struct Progress {
+            #[serde(rename = "progressToken")]
+            progress_token: serde_json::Value,
+        }
+
+        let Some(Ok(progress)) = notification
+            .params
+            .as_deref()
+            .map(from_raw_value::<Progress>)
+        else {
+            return;
+        };
+        let token = progress.progress_token.to_string();
+        if let Some(waiter) = self
+            .progress_waiters
+            .read()
+            .ok()
+            .and_then(|waiters| waiters.get(&token).cloned())
+        {
+            waiter.notify_one();
+        }
+    }
+
+    /// Cancels the token of the request referenced by a `notifications/cancelled` notification.
+    fn cancel_in_flight(&self, notification: &JsonRpcNotification, peer: &Peer) {
+        let Some(Ok(cancelled)) = notification
+            .params
+            .as_deref()
+            .map(from_raw_value::<CancelledNotification>)
+        else {
+            tracing::debug!("Invalid cancellation notification: {:?}", notification);
+            return;
+        };
+
+        let key = (peer.session_id.clone(), cancelled.request_id);
+        if let Some(token) = self
+            .in_flight
+            .read()
+            .ok()
+            .and_then(|in_flight| in_flight.get(&key).cloned())
+        {
+            tracing::debug!(
+                "Request {} cancelled: {}",
+                cancelled.request_id,
+                cancelled.reason.unwrap_or_default()
+            );
+            token.cancel();
+        }
+    }
+
+    /// Generates a new unique message ID for requests.
+    ///
+    /// # Returns
+    ///
+    /// A unique message ID
+    pub fn new_message_id(&self) -> u64 {
+        self.request_id.fetch_add(1, Ordering::SeqCst)
+    }
+
+    /// Creates a new request ID and channel for receiving the response.
+    ///
+    /// Once the connection is closed, the receiver immediately yields a
+    /// `ConnectionClosed` error response.
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing the request ID and a receiver for the response
+    pub async fn create_request(&self) -> (u64, oneshot::Receiver<JsonRpcResponse>) {
+        self.create_request_for(None).await
+    }
+
+    /// Creates a new request ID and channel for a request sent to a session.
+    ///
+    /// Request IDs are shared by all sessions of the protocol, so the request
+    /// only accepts a response received from the session it was sent to, see
+    /// `handle_response_from`.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The ID of the session the request is sent to
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing the request ID and a receiver for the response
+    pub async fn create_request_for(
+        &self,
+        session_id: Option<&str>,
+    ) -> (u64, oneshot::Receiver<JsonRpcResponse>) {
+        let id = self.new_message_id();
+        let (tx, rx) = oneshot::channel();
+
+        {
+            let mut pending = self.pending_requests();
+            if self.is_closed() {
+                let _ = tx.send(connection_closed_response(id));
+            } else {
+                pending.insert(
+                    id,
+                    PendingRequest {
+                        session_id: session_id.map(str::to_string),
+                        tx,
+                    },
+                );
+            }
+        }
+
+        (id, rx)
+    }
+
+    fn pending_requests(&self) -> std::sync::MutexGuard<'_, HashMap<RequestId, PendingRequest>> {
+        self.pending_requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the request options the timeout policy gives a method.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method name
+    ///
+    /// # Returns
+    ///
+    /// The `RequestOptions` to use when the caller does not pass any
+    pub fn request_options(&self, method: &str) -> RequestOptions {
+        self.timeout_policy.options_for(method)
+    }
+
+    /// Waits for the response to a request created with `create_request`.
+    ///
+    /// The request fails with a `RequestTimeout` error once `options.timeout`
+    /// has elapsed. When `options.reset_timeout_on_progress` is set and the
+    /// request params carry a `_meta.progressToken`, each matching progress
+    /// notification restarts the timeout, but never beyond
+    /// `options.max_total_timeout`.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the request
+    /// * `rx` - The receiver returned by `create_request`
+    /// * `params` - The params the request was sent with
+    /// * `options` - The request options
+    ///
+    /// # Returns
+    ///
+    /// The response, or an error response if the request timed out or was cancelled
+    pub async fn wait_for_response(
+        &self,
+        id: RequestId,
+        mut rx: oneshot::Receiver<JsonRpcResponse>,
+        params: Option<&RawValue>,
+        options: &RequestOptions,
+    ) -> JsonRpcResponse {
+        let progress_token = extract_meta(params)
+            .and_then(|meta| meta.get("progressToken").map(|token| token.to_string()))
+            .filter(|_| options.reset_timeout_on_progress);
+        let progress = progress_token.as_ref().map(|token| {
+            let waiter = Arc::new(Notify::new());
+            if let Ok(mut waiters) = self.progress_waiters.write() {
+                waiters.insert(token.clone(), waiter.clone());
+            }
+            waiter
+        });
+
+        let start = Instant::now();
+        let hard_deadline = options.max_total_timeout.map(|max| start + max);
+        let next_deadline = |now: Instant| {
+            let deadline = now + options.timeout;
+            hard_deadline.map_or(deadline, |hard| deadline.min(hard))
+        };
+        let mut deadline = next_deadline(start);
+
+        let response = loop {
+            let progressed = async {
+                match &progress {
+                    Some(progress) => progress.notified().await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                response = &mut rx => {
+                    break response.unwrap_or_else(|_| JsonRpcResponse {
+                        id,
+                        error: Some(JsonRpcError {
+                            code: ErrorCode::RequestTimeout as i32,
+                            message: "Request cancelled".to_string(),
+                            data: None,
+                        }),
+                        ..Default::default()
+                    });
+                }
+                _ = tokio::time::sleep_until(deadline) => {
+                    self.cancel_response(id).await;
+                    break JsonRpcResponse {
+                        id,
+                        error: Some(JsonRpcError {
+                            code: ErrorCode::RequestTimeout as i32,
+                            message: "Request timed out".to_string(),
+                            data: None,
+                        }),
+                        ..Default::default()
+                    };
+                }
+                _ = progressed => {
+                    deadline = next_deadline(Instant::now());
+                }
+            }
+        };
+
+        if let Some(token) = progress_token {
+            if let Ok(mut waiters) = self.progress_waiters.write() {
+                waiters.remove(&token);
+            }
+        }
+        response
+    }
+
+    /// Marks the connection as open, e.g. when a transport is (re)opened.
+    pub fn mark_open(&self) {
+        self.closed.store(false, Ordering::SeqCst);
+    }
+
+    /// Returns whether the connection has been closed.
+    ///
+    /// # Returns
+    ///
+    /// `true` once `close` has been called and until `mark_open` is called
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Marks the connection as closed and fails all pending requests.
+    ///
+    /// Every request still waiting for a response receives a
+    /// `ConnectionClosed` error, and requests created afterwards fail
+    /// immediately. Transports call this when their reader loop ends or
+    /// when they are closed.
+    pub async fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        let pending: Vec<_> = self.pending_requests().drain().collect();
+        if !pending.is_empty() {
+            tracing::debug!(
+                "Connection closed, failing {} pending requests",
+                pending.len()
+            );
+        }
+        for (id, request) in pending {
+            let _ = request.tx.send(connection_closed_response(id));
+        }
+    }
+
+    /// Handles an incoming JSON-RPC response.
+    ///
+    /// This method delivers the response to the appropriate waiting request,
+    /// if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `response` - The incoming JSON-RPC response
+    pub async fn handle_response(&self, response: JsonRpcResponse) {
+        self.handle_response_from(response, None).await
+    }
+
+    /// Handles an incoming JSON-RPC response received from a session.
+    ///
+    /// The response is only delivered if the request with its ID was sent to
+    /// the same session, so that a session cannot answer the requests sent to
+    /// another one. Other responses are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `response` - The incoming JSON-RPC response
+    /// * `session_id` - The ID of the session the response was received from
+    pub async fn handle_response_from(&self, response: JsonRpcResponse, session_id: Option<&str>) {
+        let request = {
+            let mut pending = self.pending_requests();
+            match pending.get(&response.id) {
+                Some(request) if request.session_id.as_deref() == session_id => {
+                    pending.remove(&response.id)
+                }
+                Some(_) => {
+                    tracing::warn!(
+                        "Dropping response {} from session {:?}: the request was sent to another session",
+                        response.id,
+                        session_id
+                    );
+                    None
+                }
+                None => None,
+            }
+        };
+        if let Some(request) = request {
+            let _ = request.tx.send(response);
+        }
+    }
+
+    /// Cancels a pending request and sends an error response.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the request to cancel
+    pub async fn cancel_response(&self, id: u64) {
+        let request = self.pending_requests().remove(&id);
+        if let Some(request) = request {
+            let _ = request.tx.send(JsonRpcResponse {
+                id,
+                result: None,
+                error: Some(JsonRpcError {
+                    code: ErrorCode::RequestTimeout as i32,
+                    message: "Request cancelled".to_string(),
+                    data: None,
+                }),
+                ..Default::default()
+            });
+        }
+    }
+}
+
+/// A request waiting for its response.
+struct PendingRequest {
+    /// The session the request was sent to
+    session_id: Option<String>,
+    tx: oneshot::Sender<JsonRpcResponse>,
+}
+
+/// Builds the error response for a request that cannot complete because the
+/// connection is closed.
+fn connection_closed_response(id: RequestId) -> JsonRpcResponse {
+    JsonRpcResponse {
+        id,
+        result: None,
+        error: Some(JsonRpcError {
+            code: ErrorCode::ConnectionClosed as i32,
+            message: "Connection closed".to_string(),
+            data: None,
+        }),
+        ..Default::default()
+    }
+}
+
+/// The fields of incoming params read by the protocol itself. Other fields are
+/// skipped without being parsed, and left to the handler.
+#[derive(serde::Deserialize)]
+struct ParamsHead {
+    #[serde(rename = "_meta")]
+    meta: Option<serde_json::Value>,
+    name: Option<serde_json::Value>,
+}
+
+fn params_head(params: Option<&RawValue>) -> Option<ParamsHead> {
+    from_raw_value(params?).ok()
+}
+
+/// Returns the `_meta` field of request or notification params, if any.
+fn extract_meta(params: Option<&RawValue>) -> Option<serde_json::Value> {
+    params_head(params)?.meta
+}
+
+/// What is known about the peer of a session.
+///
+/// The server fills in the client fields when it receives the `initialize`
+/// request, and the client fills in the server fields from the response.
+/// Every session has its own `PeerInfo`, so clients connected to the same
+/// server do not see each other's identity or capabilities.
+#[derive(Debug, Clone, Default)]
+pub struct PeerInfo {
+    /// The lifecycle state of the session
+    pub state: LifecycleState,
+    /// The protocol version negotiated with the peer
+    pub protocol_version: Option<ProtocolVersion>,
+    /// Information about the client implementation
+    pub client_info: Option<Implementation>,
+    /// The capabilities reported by the client
+    pub client_capabilities: Option<ClientCapabilities>,
+    /// Information about the server implementation
+    pub server_info: Option<Implementation>,
+    /// The capabilities reported by the server
+    pub server_capabilities: Option<ServerCapabilities>,
+    /// The URIs of the resources the client subscribed to
+    pub resource_subscriptions: HashSet<String>,
+    /// The minimum level of the log messages the client asked for with
+    /// `logging/setLevel`, `None` until it does
+    pub logging_level: Option<LoggingLevel>,
+    /// The roots of the client, cached by `Peer::list_roots` until the client
+    /// sends `notifications/roots/list_changed`
+    pub roots: Option<Vec<Root>>,
+    /// The number of `notifications/roots/list_changed` received from the client
+    pub roots_changes: u64,
+}
+
+impl PeerInfo {
+    /// Returns `true` once the peer has sent `notifications/initialized`.
+    pub fn is_initialized(&self) -> bool {
+        self.state == LifecycleState::Initialized
+    }
+}
+
+/// The lifecycle state of a session.
+///
+/// A session moves through the states in order: the client sends `initialize`,
+/// then `notifications/initialized`. When the transport closes the session,
+/// `Protocol::close_session` moves it to `Closed` and cancels its in-flight
+/// requests. The server enforces the lifecycle with the `Lifecycle` middleware,
+/// so that only `initialize` and `ping` are accepted before the session is
+/// initialized, and nothing is accepted once it is closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LifecycleState {
+    /// The peer has not sent `initialize` yet
+    #[default]
+    Uninitialized,
+    /// The peer has sent `initialize`, but not `notifications/initialized`
+    Initializing,
+    /// The peer has completed initialization
+    Initialized,
+    /// The transport closed the session
+    Closed,
+}
+
+/// A view of the sessions of a protocol, e.g. of the clients connected to a
+/// server.
+///
+/// A session is listed once its peer has started initialization, and until
+/// its transport removes it.
+///
+/// # Examples
+///
+/// ```
+/// use mcp_core::protocol::Protocol;
+///
+/// let protocol = Protocol::builder().build();
+/// for session_id in protocol.sessions().ids() {
+///     let info = protocol.sessions().get(session_id.as_deref()).unwrap();
+///     println!("{:?}: {:?}", session_id, info.client_info);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct SessionRegistry {
+    protocol: Protocol,
+}
+
+impl SessionRegistry {
+    /// Returns the IDs of the sessions.
+    ///
+    /// Transports with a single session, like stdio, use `None` as its ID.
+    pub fn ids(&self) -> Vec<Option<String>> {
+        self.protocol
+            .peer_infos
+            .read()
+            .map(|infos| infos.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns what is known about the peer of a session.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The ID of the session
+    ///
+    /// # Returns
+    ///
+    /// The `PeerInfo` of the session, or `None` if there is no such session
+    pub fn get(&self, session_id: Option<&str>) -> Option<PeerInfo> {
+        self.protocol
+            .peer_infos
+            .read()
+            .ok()?
+            .get(&session_id.map(str::to_string))
+            .cloned()
+    }
+
+    /// Returns a handle to the peer of an initialized session.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The ID of the session
+    ///
+    /// # Returns
+    ///
+    /// A `Peer` for sending requests and notifications to the session, or
+    /// `None` if there is no such initialized session
+    pub fn peer(&self, session_id: Option<&str>) -> Option<Peer> {
+        let session_id = session_id.map(str::to_string);
+        let transport = self
+            .protocol
+            .sessions
+            .read()
+            .ok()?
+            .get(&session_id)
+            .cloned()?;
+        Some(self.protocol.peer(session_id, transport))
+    }
+
+    /// Returns the number of sessions.
+    pub fn len(&self) -> usize {
+        self.protocol
+            .peer_infos
+            .read()
+            .map(|infos| infos.len())
+            .unwrap_or_default()
+    }
+
+    /// Returns `true` if there are no sessions.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A handle to the peer on the other end of a connection.
+///
+/// A `Peer` can send notifications and requests back to the peer that sent
+/// the message being handled.
+#[derive(Clone)]
+pub struct Peer {
+    session_id: Option<String>,
+    transport: Option<Arc<dyn Transport>>,
+    protocol: Protocol,
+}
+
+impl Peer {
+    /// Creates a peer that is not connected to any transport.
+    pub(crate) fn detached(protocol: Protocol, session_id: Option<String>) -> Self {
+        Self {
+            session_id,
+            transport: None,
+            protocol,
+        }
+    }
+
+    /// Returns the ID of the session the peer belongs to, if any.
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    /// Returns the protocol the peer is reached through.
+    pub(crate) fn protocol(&self) -> &Protocol {
+        &self.protocol
+    }
+
+    /// Returns what is known about the peer, such as its negotiated capabilities.
+    pub fn info(&self) -> PeerInfo {
+        self.protocol.peer_info(self.session_id())
+    }
+
+    /// Updates what is known about the peer.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - A function that modifies the `PeerInfo` of the peer
+    pub fn update_info(&self, f: impl FnOnce(&mut PeerInfo)) {
+        self.protocol.update_peer_info(self.session_id(), f)
+    }
+
+    fn transport(&self) -> Result<&Arc<dyn Transport>> {
+        self.transport.as_ref().ok_or_else(|| {
+            McpError::new(ErrorCode::ConnectionClosed, "Peer is not connected").into()
+        })
+    }
+
+    /// Sends a notification to the peer.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method name for the notification
+    /// * `params` - Optional parameters for the notification
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or failure
+    pub async fn send_notification(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> Result<()> {
+        self.transport()?.send_notification(method, params).await
+    }
+
+    /// Sends a request to the peer and waits for the response.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method name for the request
+    /// * `params` - Optional parameters for the request
+    /// * `options` - Request options, defaulting to the protocol's timeout policy
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the response. If the peer did not advertise the
+    /// capability the method requires, e.g. `sampling` for
+    /// `sampling/createMessage`, the request is not sent and a
+    /// `McpError::capability_not_supported` error is returned instead.
+    pub async fn request(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+        options: Option<RequestOptions>,
+    ) -> Result<JsonRpcResponse> {
+        // Capabilities are only known once the session is initialized
+        let info = self.info();
+        let version = info
+            .protocol_version
+            .clone()
+            .unwrap_or(LATEST_PROTOCOL_VERSION);
+        if let Some(capabilities) = &info.client_capabilities {
+            capabilities.check_request(method, &version)?;
+        }
+        if let Some(capabilities) = &info.server_capabilities {
+            capabilities.check_request(method, &version)?;
+        }
+
+        let options = options.unwrap_or_else(|| self.protocol.timeout_policy.options_for(method));
+        traced_request(
+            self.transport()?.as_ref(),
+            self.session_id.as_deref(),
+            method,
+            params,
+            options,
+        )
+        .await
+    }
+
+    /// Sends a request to the peer and deserializes the result.
+    async fn request_as<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+        options: Option<RequestOptions>,
+    ) -> Result<R> {
+        let response = self.request(method, params, options).await?;
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(McpError::from(error).into()),
+            (Some(result), None) => from_raw_value(&result)
+                .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e)),
+            (None, None) => Err(McpError::internal("Response has neither result nor error").into()),
+        }
+    }
+
+    /// Returns the roots of the client, asking for them with `roots/list`.
+    ///
+    /// The roots are cached in the `PeerInfo` of the session until the client
+    /// announces a change with `notifications/roots/list_changed`.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Request options, defaulting to the protocol's timeout policy
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the roots, or an error if the client does not
+    /// support roots
+    pub async fn list_roots(&self, options: Option<RequestOptions>) -> Result<Vec<Root>> {
+        let info = self.info();
+        if let Some(roots) = info.roots {
+            return Ok(roots);
+        }
+
+        let result: ListRootsResult = self.request_as("roots/list", None, options).await?;
+        self.update_info(|updated| {
+            // Roots that changed while the request was in flight are stale
+            if updated.roots_changes == info.roots_changes {
+                updated.roots = Some(result.roots.clone());
+            }
+        });
+        Ok(result.roots)
+    }
+
+    /// Asks the user for information through the client with `elicitation/create`.
+    ///
+    /// When the user accepts, the submitted information is validated against
+    /// the requested schema.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The message to present to the user
+    /// * `requested_schema` - The schema of the requested information
+    /// * `options` - Request options, defaulting to the protocol's timeout policy
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the action of the user and the submitted
+    /// information, or an error if the client does not support elicitation or
+    /// the information does not match the schema
+    pub async fn elicit(
+        &self,
+        message: impl Into<String>,
+        requested_schema: ElicitationSchema,
+        options: Option<RequestOptions>,
+    ) -> Result<ElicitResult> {
+        let version = self.info().protocol_version;
+        if version.is_some_and(|version| !version.supports_elicitation()) {
+            return Err(
+                McpError::capability_not_supported("elicitation/create", "elicitation").into(),
+            );
+        }
+
+        let request = ElicitRequest {
+            message: message.into(),
+            requested_schema,
+        };
+        let result: ElicitResult = self
+            .request_as(
+                "elicitation/create",
+                Some(serde_json::to_value(&request)?),
+                options,
+            )
+            .await?;
+        if result.action == ElicitAction::Accept {
+            let content = result.content.clone().unwrap_or_default();
+            request.requested_schema.validate(&content)?;
+        }
+        Ok(result)
+    }
+
+    /// Asks the client to sample its LLM with `sampling/createMessage`.
+    ///
+    /// This is typically called from a tool handler, through the peer of its
+    /// `RequestContext`, to let the host's model work on intermediate results.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The messages to sample from and the sampling parameters
+    /// * `options` - Request options, defaulting to the protocol's timeout policy
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the sampled message, or an error if the client
+    /// does not support sampling, or declined the request
+    pub async fn create_message(
+        &self,
+        request: CreateMessageRequest,
+        options: Option<RequestOptions>,
+    ) -> Result<CreateMessageResult> {
+        self.request_as(
+            "sampling/createMessage",
+            Some(serde_json::to_value(request)?),
+            options,
+        )
+        .await
+    }
+}
+
+/// Returns the name of the called tool for `tools/call` requests.
+fn tool_name(method: &str, params: Option<&RawValue>) -> Option<String> {
+    match method {
+        "tools/call" => match params_head(params)?.name? {
+            serde_json::Value::String(name) => Some(name),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Sends a request within a client span, propagating the trace context
+/// through `_meta.traceparent`.
+pub(crate) async fn traced_request(
+    transport: &dyn Transport,
+    session_id: Option<&str>,
+    method: &str,
+    params: Option<serde_json::Value>,
+    options: RequestOptions,
+) -> Result<JsonRpcResponse> {
+    let (params, trace_parent) = trace_context::inject(params);
+    let tool_name = match method {
+        "tools/call" => params
+            .as_ref()
+            .and_then(|params| params.get("name"))
+            .and_then(serde_json::Value::as_str),
+        _ => None,
+    };
+    let span = tracing::info_span!(
+        "mcp.client.request",
+        otel.kind = "client",
+        rpc.method = method,
+        session.id = session_id,
+        mcp.tool.name = tool_name,
+        trace_id = trace_parent.as_ref().map(|tp| tp.trace_id.as_str()),
+        span_id = trace_parent.as_ref().map(|tp| tp.span_id.as_str()),
+        error.code = tracing::field::Empty,
+        duration_ms = tracing::field::Empty,
+    );
+    let start = Instant::now();
+    let result = transport
+        .request(method, params, options)
+        .instrument(span.clone())
+        .await;
+    span.record("duration_ms", start.elapsed().as_secs_f64() * 1000.0);
+    match &result {
+        Ok(JsonRpcResponse {
+            error: Some(error), ..
+        }) => {
+            span.record("error.code", error.code);
+        }
+        Err(e) => {
+            span.record(
+                "error.code",
+                e.downcast_ref::<McpError>()
+                    .map(|e| e.code)
+                    .unwrap_or(ErrorCode::InternalError as i32),
+            );
+        }
+        _ => {}
+    }
+    result
+}
+
+/// Context passed to handlers that opt into it.
+///
+/// The context identifies the message being handled and the peer that sent
+/// it, and lets the handler notice when the peer cancels the request.
+#[derive(Clone)]
+pub struct RequestContext {
+    /// The ID of the request, `None` for notifications
+    pub request_id: Option<RequestId>,
+    /// The method of the request or notification
+    pub method: String,
+    /// The `_meta` field of the params, if any
+    pub meta: Option<serde_json::Value>,
+    /// A handle for sending notifications and requests back to the peer
+    pub peer: Peer,
+    /// Cancelled when the peer sends `notifications/cancelled` for this request
+    pub cancellation: CancellationToken,
+}
+
+impl RequestContext {
+    /// Returns the ID of the session the message was received on, if any.
+    pub fn session_id(&self) -> Option<&str> {
+        self.peer.session_id()
+    }
+
+    /// Returns the protocol version negotiated during initialization.
+    pub fn protocol_version(&self) -> Option<ProtocolVersion> {
+        self.peer.info().protocol_version
+    }
+
+    /// Returns the capabilities the client reported during initialization.
+    pub fn client_capabilities(&self) -> Option<ClientCapabilities> {
+        self.peer.info().client_capabilities
+    }
+
+    /// Returns the capabilities the server reported during initialization.
+    pub fn server_capabilities(&self) -> Option<ServerCapabilities> {
+        self.peer.info().server_capabilities
+    }
+}
+
+/// The default request timeout, in milliseconds
+pub const DEFAULT_REQUEST_TIMEOUT_MSEC: u64 = 60000;
+
+/// Options for customizing requests.
+///
+/// This struct allows configuring various aspects of request handling,
+/// such as timeouts.
+#[derive(Debug, Clone)]
+pub struct RequestOptions {
+    /// The timeout duration for the request
+    pub timeout: Duration,
+    /// Whether a progress notification for the request restarts the timeout
+    pub reset_timeout_on_progress: bool,
+    /// The maximum total time to wait, which progress notifications cannot extend
+    pub max_total_timeout: Option<Duration>,
+}
+
+impl RequestOptions {
+    /// Sets the timeout for the request.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The timeout duration
+    ///
+    /// # Returns
+    ///
+    /// The modified options instance
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// Restarts the timeout whenever a progress notification for the request arrives.
+    ///
+    /// Progress notifications are only matched to requests whose params carry
+    /// a `_meta.progressToken`.
+    ///
+    /// # Arguments
+    ///
+    /// * `reset` - Whether progress restarts the timeout
+    ///
+    /// # Returns
+    ///
+    /// The modified options instance
+    pub fn reset_timeout_on_progress(self, reset: bool) -> Self {
+        Self {
+            reset_timeout_on_progress: reset,
+            ..self
+        }
+    }
+
+    /// Sets the maximum total time to wait for the response.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_total_timeout` - The time after which the request fails, whatever its progress
+    ///
+    /// # Returns
+    ///
+    /// The modified options instance
+    pub fn max_total_timeout(self, max_total_timeout: Duration) -> Self {
+        Self {
+            max_total_timeout: Some(max_total_timeout),
+            ..self
+        }
+    }
+}
+
+impl Default for RequestOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_millis(DEFAULT_REQUEST_TIMEOUT_MSEC),
+            reset_timeout_on_progress: false,
+            max_total_timeout: None,
+        }
+    }
+}
+
+/// Default request options by method.
+///
+/// The policy provides the `RequestOptions` of requests for which the caller
+/// does not pass any, so that e.g. `tools/call` can wait much longer than
+/// `initialize`.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use mcp_core::protocol::{RequestOptions, TimeoutPolicy};
+///
+/// let policy = TimeoutPolicy::default()
+///     .with_default(RequestOptions::default().timeout(Duration::from_secs(10)))
+///     .with_method(
+///         "tools/call",
+///         RequestOptions::default()
+///             .timeout(Duration::from_secs(60))
+///             .reset_timeout_on_progress(true)
+///             .max_total_timeout(Duration::from_secs(600)),
+///     );
+/// assert_eq!(policy.options_for("initialize").timeout, Duration::from_secs(10));
+/// assert_eq!(policy.options_for("tools/call").timeout, Duration::from_secs(60));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TimeoutPolicy {
+    default: RequestOptions,
+    methods: HashMap<String, RequestOptions>,
+}
+
+impl TimeoutPolicy {
+    /// Sets the options of methods without an override.
     ///
-    /// # Returns
+    /// # Arguments
     ///
-    /// A unique message ID
-    pub fn new_message_id(&self) -> u64 {
-        self.request_id.fetch_add(1, Ordering::SeqCst)
-    }
-
-    /// Creates a new request ID and channel for receiving the response.
+    /// * `options` - The default request options
     ///
     /// # Returns
     ///
-    /// A tuple containing the request ID and a receiver for the response
-    pub async fn create_request(&self) -> (u64, oneshot::Receiver<JsonRpcResponse>) {
-        let id = self.new_message_id();
-        let (tx, rx) = oneshot::channel();
-
-        {
-            let mut pending = self.pending_requests.lock().await;
-            pending.insert(id, tx);
-        }
-
-        (id, rx)
+    /// The modified policy instance
+    pub fn with_default(mut self, options: RequestOptions) -> Self {
+        self.default = options;
+        self
     }
 
-    /// Handles an incoming JSON-RPC response.
-    ///
-    /// This method delivers the response to the appropriate waiting request,
-    /// if any.
+    /// Overrides the options of a method.
     ///
     /// # Arguments
     ///
-    /// * `response` - The incoming JSON-RPC response
-    pub async fn handle_response(&self, response: JsonRpcResponse) {
-        if let Some(tx) = self.pending_requests.lock().await.remove(&response.id) {
-            let _ = tx.send(response);
-        }
+    /// * `method` - The method name, e.g. `tools/call`
+    /// * `options` - The request options for the method
+    ///
+    /// # Returns
+    ///
+    /// The modified policy instance
+    pub fn with_method(mut self, method: impl Into<String>, options: RequestOptions) -> Self {
+        self.methods.insert(method.into(), options);
+        self
     }
 
-    /// Cancels a pending request and sends an error response.
+    /// Returns the options to use for a method.
     ///
     /// # Arguments
     ///
-    /// * `id` - The ID of the request to cancel
-    pub async fn cancel_response(&self, id: u64) {
-        if let Some(tx) = self.pending_requests.lock().await.remove(&id) {
-            let _ = tx.send(JsonRpcResponse {
-                id,
-                result: None,
-                error: Some(JsonRpcError {
-                    code: ErrorCode::RequestTimeout as i32,
-                    message: "Request cancelled".to_string(),
-                    data: None,
-                }),
-                ..Default::default()
-            });
-        }
+    /// * `method` - The method name
+    ///
+    /// # Returns
+    ///
+    /// The method's override if any, otherwise the default options
+    pub fn options_for(&self, method: &str) -> RequestOptions {
+        self.methods.get(method).unwrap_or(&self.default).clone()
     }
 }
 
-/// The default request timeout, in milliseconds
-pub const DEFAULT_REQUEST_TIMEOUT_MSEC: u64 = 60000;
+/// The default interval between liveness pings, in milliseconds
+pub const DEFAULT_PING_INTERVAL_MSEC: u64 = 15000;
 
-/// Options for customizing requests.
+/// The default time to wait for a ping response, in milliseconds
+pub const DEFAULT_PING_TIMEOUT_MSEC: u64 = 10000;
+
+/// The default number of consecutive missed pings before a peer is considered dead
+pub const DEFAULT_MAX_MISSED_PINGS: u32 = 3;
+
+/// Configuration for periodic liveness checks.
 ///
-/// This struct allows configuring various aspects of request handling,
-/// such as timeouts.
-pub struct RequestOptions {
-    /// The timeout duration for the request
+/// A liveness check sends a `ping` request to the peer every `interval` and
+/// counts consecutive pings that are not answered within `timeout`. Once
+/// `max_missed` pings in a row have gone unanswered, the peer is considered dead.
+#[derive(Debug, Clone)]
+pub struct LivenessCheck {
+    /// The time between two pings
+    pub interval: Duration,
+    /// The time to wait for a ping response
     pub timeout: Duration,
+    /// The number of consecutive missed pings after which the peer is considered dead
+    pub max_missed: u32,
 }
 
-impl RequestOptions {
-    /// Sets the timeout for the request.
+impl LivenessCheck {
+    /// Creates a new liveness check configuration.
     ///
     /// # Arguments
     ///
-    /// * `timeout` - The timeout duration
+    /// * `interval` - The time between two pings
+    /// * `timeout` - The time to wait for a ping response
+    /// * `max_missed` - The number of consecutive missed pings tolerated
     ///
     /// # Returns
     ///
-    /// The modified options instance
-    pub fn timeout(self, timeout: Duration) -> Self {
-        Self { timeout }
+    /// A new `LivenessCheck` instance
+    pub fn new(interval: Duration, timeout: Duration, max_missed: u32) -> Self {
+        Self {
+            interval,
+            timeout,
+            max_missed,
+        }
+    }
+
+    /// Sends a single `ping` request to the peer.
+    ///
+    /// Any reply of the peer, including an error response, proves that it is
+    /// alive. Timeouts, transport failures and the errors generated locally
+    /// when the connection is closed count as a missed ping.
+    ///
+    /// # Arguments
+    ///
+    /// * `transport` - The transport connected to the peer
+    ///
+    /// # Returns
+    ///
+    /// `true` if the peer answered in time, `false` otherwise
+    pub async fn ping<T: Transport + ?Sized>(&self, transport: &T) -> bool {
+        let request = transport.request(
+            "ping",
+            None,
+            RequestOptions::default().timeout(self.timeout),
+        );
+        match tokio::time::timeout(self.timeout, request).await {
+            // Timeouts and closed connections are reported locally, with codes peers never send
+            Ok(Ok(response)) => !matches!(
+                response
+                    .error
+                    .as_ref()
+                    .and_then(|error| ErrorCode::from_code(error.code)),
+                Some(ErrorCode::RequestTimeout | ErrorCode::ConnectionClosed)
+            ),
+            Ok(Err(e)) => {
+                tracing::debug!("Failed to send ping: {}", e);
+                false
+            }
+            Err(_) => false,
+        }
     }
 }
 
-impl Default for RequestOptions {
+impl Default for LivenessCheck {
     fn default() -> Self {
         Self {
-            timeout: Duration::from_millis(DEFAULT_REQUEST_TIMEOUT_MSEC),
+            interval: Duration::from_millis(DEFAULT_PING_INTERVAL_MSEC),
+            timeout: Duration::from_millis(DEFAULT_PING_TIMEOUT_MSEC),
+            max_missed: DEFAULT_MAX_MISSED_PINGS,
         }
     }
 }
@@ -213,21 +1683,34 @@ impl Default for RequestOptions {
 /// protocols with specific request and notification handlers.
 #[derive(Clone)]
 pub struct ProtocolBuilder {
-    request_handlers: Arc<Mutex<HashMap<String, Box<dyn RequestHandler>>>>,
-    notification_handlers: Arc<Mutex<HashMap<String, Box<dyn NotificationHandler>>>>,
+    request_handlers: RequestHandlers,
+    notification_handlers: NotificationHandlers,
+    middlewares: Vec<Arc<dyn Middleware>>,
+    timeout_policy: TimeoutPolicy,
+    metrics: Option<Arc<dyn Metrics>>,
 }
 
 impl ProtocolBuilder {
     /// Creates a new protocol builder.
     ///
+    /// The builder comes with a handler for `ping` requests, which every MCP
+    /// peer must answer with an empty result. It can be replaced by registering
+    /// another handler for the `ping` method.
+    ///
     /// # Returns
     ///
     /// A new `ProtocolBuilder` instance
     pub fn new() -> Self {
         Self {
-            request_handlers: Arc::new(Mutex::new(HashMap::new())),
-            notification_handlers: Arc::new(Mutex::new(HashMap::new())),
+            request_handlers: Arc::new(RwLock::new(HashMap::new())),
+            notification_handlers: Arc::new(RwLock::new(HashMap::new())),
+            middlewares: Vec::new(),
+            timeout_policy: TimeoutPolicy::default(),
+            metrics: None,
         }
+        .request_handler("ping", |_: serde_json::Value| {
+            Box::pin(async move { Ok(json!({})) })
+        })
     }
 
     /// Registers a typed request handler.
@@ -248,6 +1731,34 @@ impl ProtocolBuilder {
             + Sync
             + 'static,
     ) -> Self
+    where
+        Req: DeserializeOwned + Send + Sync + 'static,
+        Resp: Serialize + Send + Sync + 'static,
+    {
+        self.request_handler_with_context(method, move |req: Req, _| handler(req))
+    }
+
+    /// Registers a typed request handler that receives the `RequestContext`.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method name to handle
+    /// * `handler` - The handler function, called with the params and the context
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn request_handler_with_context<Req, Resp>(
+        self,
+        method: &str,
+        handler: impl Fn(
+                Req,
+                RequestContext,
+            ) -> Pin<Box<dyn std::future::Future<Output = Result<Resp>> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self
     where
         Req: DeserializeOwned + Send + Sync + 'static,
         Resp: Serialize + Send + Sync + 'static,
@@ -257,9 +1768,10 @@ impl ProtocolBuilder {
             _phantom: std::marker::PhantomData,
         };
 
-        if let Ok(mut handlers) = self.request_handlers.try_lock() {
-            handlers.insert(method.to_string(), Box::new(handler));
-        }
+        self.request_handlers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(method.to_string(), Arc::new(handler));
         self
     }
 
@@ -274,7 +1786,7 @@ impl ProtocolBuilder {
     /// `true` if a handler exists, `false` otherwise
     pub fn has_request_handler(&self, method: &str) -> bool {
         self.request_handlers
-            .try_lock()
+            .read()
             .map(|handlers| handlers.contains_key(method))
             .unwrap_or(false)
     }
@@ -297,6 +1809,32 @@ impl ProtocolBuilder {
             + Sync
             + 'static,
     ) -> Self
+    where
+        N: DeserializeOwned + Send + Sync + 'static,
+    {
+        self.notification_handler_with_context(method, move |notification: N, _| {
+            handler(notification)
+        })
+    }
+
+    /// Registers a typed notification handler that receives the `RequestContext`.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method name to handle
+    /// * `handler` - The handler function, called with the params and the context
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn notification_handler_with_context<N>(
+        self,
+        method: &str,
+        handler: impl Fn(N, RequestContext) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self
     where
         N: DeserializeOwned + Send + Sync + 'static,
     {
@@ -305,9 +1843,10 @@ impl ProtocolBuilder {
             _phantom: std::marker::PhantomData,
         };
 
-        if let Ok(mut handlers) = self.notification_handlers.try_lock() {
-            handlers.insert(method.to_string(), Box::new(handler));
-        }
+        self.notification_handlers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(method.to_string(), Arc::new(handler));
         self
     }
 
@@ -322,11 +1861,59 @@ impl ProtocolBuilder {
     /// `true` if a handler exists, `false` otherwise
     pub fn has_notification_handler(&self, method: &str) -> bool {
         self.notification_handlers
-            .try_lock()
+            .read()
             .map(|handlers| handlers.contains_key(method))
             .unwrap_or(false)
     }
 
+    /// Adds a middleware wrapping the dispatch of every request and notification.
+    ///
+    /// Middleware runs in the order it is added, and in reverse order once the
+    /// response of a request is known.
+    ///
+    /// # Arguments
+    ///
+    /// * `middleware` - The middleware to add
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// Sets the timeout policy for requests sent without explicit options.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout_policy` - The timeout policy
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn timeout_policy(mut self, timeout_policy: TimeoutPolicy) -> Self {
+        self.timeout_policy = timeout_policy;
+        self
+    }
+
+    /// Sets the metrics that requests are recorded in.
+    ///
+    /// The transports using the protocol record their sessions and queues in
+    /// the same metrics.
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics` - The metrics implementation
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
     /// Builds the protocol with the configured handlers.
     ///
     /// # Returns
@@ -336,12 +1923,26 @@ impl ProtocolBuilder {
         Protocol {
             request_id: Arc::new(AtomicU64::new(0)),
             pending_requests: Arc::new(Mutex::new(HashMap::new())),
+            closed: Arc::new(AtomicBool::new(false)),
             request_handlers: self.request_handlers,
             notification_handlers: self.notification_handlers,
+            in_flight: Arc::new(RwLock::new(HashMap::new())),
+            peer_infos: Arc::new(RwLock::new(HashMap::new())),
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+            progress_waiters: Arc::new(RwLock::new(HashMap::new())),
+            middlewares: Arc::new(self.middlewares),
+            timeout_policy: Arc::new(self.timeout_policy),
+            metrics: self.metrics,
         }
     }
 }
 
+impl Default for ProtocolBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Trait for handling JSON-RPC requests.
 ///
 /// Implementors of this trait can handle incoming JSON-RPC requests
@@ -353,11 +1954,16 @@ trait RequestHandler: Send + Sync {
     /// # Arguments
     ///
     /// * `request` - The incoming JSON-RPC request
+    /// * `context` - The context of the request
     ///
     /// # Returns
     ///
-    /// A `Result` containing the response or an error
-    async fn handle(&self, request: JsonRpcRequest) -> Result<JsonRpcResponse>;
+    /// A `Result` containing the response or an `McpError`
+    async fn handle(
+        &self,
+        request: JsonRpcRequest,
+        context: RequestContext,
+    ) -> Result<JsonRpcResponse, McpError>;
 }
 
 /// Trait for handling JSON-RPC notifications.
@@ -370,13 +1976,37 @@ trait NotificationHandler: Send + Sync {
     /// # Arguments
     ///
     /// * `notification` - The incoming JSON-RPC notification
+    /// * `context` - The context of the notification
     ///
     /// # Returns
     ///
     /// A `Result` indicating success or failure
-    async fn handle(&self, notification: JsonRpcNotification) -> Result<()>;
+    async fn handle(
+        &self,
+        notification: JsonRpcNotification,
+        context: RequestContext,
+    ) -> Result<()>;
 }
 
+/// The transport used to reach an initialized session
+type SessionTransport = Arc<dyn Transport>;
+
+type RequestHandlers = Arc<RwLock<HashMap<String, Arc<dyn RequestHandler>>>>;
+
+type NotificationHandlers = Arc<RwLock<HashMap<String, Arc<dyn NotificationHandler>>>>;
+
+/// In-flight requests are identified by the session they arrived on and their ID
+type InFlightKey = (Option<String>, RequestId);
+
+/// A boxed future returned by request and notification handlers.
+pub(crate) type HandlerFuture<T> = Pin<Box<dyn std::future::Future<Output = Result<T>> + Send>>;
+
+/// The function type wrapped by a `TypedRequestHandler`.
+type RequestHandlerFn<Req, Resp> = dyn Fn(Req, RequestContext) -> HandlerFuture<Resp> + Send + Sync;
+
+/// The function type wrapped by a `TypedNotificationHandler`.
+type NotificationHandlerFn<N> = dyn Fn(N, RequestContext) -> HandlerFuture<()> + Send + Sync;
+
 /// A typed request handler.
 ///
 /// This struct adapts a typed handler function to the `RequestHandler` trait,
@@ -386,11 +2016,7 @@ where
     Req: DeserializeOwned + Send + Sync + 'static,
     Resp: Serialize + Send + Sync + 'static,
 {
-    handler: Box<
-        dyn Fn(Req) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Resp>> + Send>>
-            + Send
-            + Sync,
-    >,
+    handler: Box<RequestHandlerFn<Req, Resp>>,
     _phantom: std::marker::PhantomData<(Req, Resp)>,
 }
 
@@ -400,17 +2026,22 @@ where
     Req: DeserializeOwned + Send + Sync + 'static,
     Resp: Serialize + Send + Sync + 'static,
 {
-    async fn handle(&self, request: JsonRpcRequest) -> Result<JsonRpcResponse> {
-        let params: Req = if request.params.is_none() || request.params.as_ref().unwrap().is_null()
-        {
-            serde_json::from_value(json!({}))?
-        } else {
-            serde_json::from_value(request.params.unwrap())?
-        };
-        let result = (self.handler)(params).await?;
+    async fn handle(
+        &self,
+        request: JsonRpcRequest,
+        context: RequestContext,
+    ) -> Result<JsonRpcResponse, McpError> {
+        let params: Req = match request.params.as_deref() {
+            Some(params) if params.get() != "null" => from_raw_value(params),
+            _ => serde_json::from_str("{}"),
+        }
+        .map_err(|e| McpError::invalid_params(format!("Invalid params: {}", e)))?;
+        let result = (self.handler)(params, context).await?;
+        let result = to_raw_value(&result)
+            .map_err(|e| McpError::internal(format!("Failed to serialize result: {}", e)))?;
         Ok(JsonRpcResponse {
             id: request.id,
-            result: Some(serde_json::to_value(result)?),
+            result: Some(result),
             error: None,
             ..Default::default()
         })
@@ -425,11 +2056,7 @@ struct TypedNotificationHandler<N>
 where
     N: DeserializeOwned + Send + Sync + 'static,
 {
-    handler: Box<
-        dyn Fn(N) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send>>
-            + Send
-            + Sync,
-    >,
+    handler: Box<NotificationHandlerFn<N>>,
     _phantom: std::marker::PhantomData<N>,
 }
 
@@ -438,13 +2065,431 @@ impl<N> NotificationHandler for TypedNotificationHandler<N>
 where
     N: DeserializeOwned + Send + Sync + 'static,
 {
-    async fn handle(&self, notification: JsonRpcNotification) -> Result<()> {
-        let params: N =
-            if notification.params.is_none() || notification.params.as_ref().unwrap().is_null() {
-                serde_json::from_value(serde_json::Value::Null)?
-            } else {
-                serde_json::from_value(notification.params.unwrap())?
-            };
-        (self.handler)(params).await
+    async fn handle(
+        &self,
+        notification: JsonRpcNotification,
+        context: RequestContext,
+    ) -> Result<()> {
+        let params: N = match notification.params.as_deref() {
+            Some(params) if params.get() != "null" => from_raw_value(params)?,
+            _ => serde_json::from_str("null")?,
+        };
+        (self.handler)(params, context).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(value: serde_json::Value) -> Box<RawValue> {
+        to_raw_value(&value).unwrap()
+    }
+
+    fn request(method: &str, params: Option<serde_json::Value>) -> JsonRpcRequest {
+        JsonRpcRequest {
+            id: 1,
+            method: method.to_string(),
+            params: params.map(raw),
+            jsonrpc: Default::default(),
+        }
+    }
+
+    fn notification(method: &str, params: Option<serde_json::Value>) -> JsonRpcNotification {
+        JsonRpcNotification {
+            method: method.to_string(),
+            params: params.map(raw),
+            jsonrpc: Default::default(),
+        }
+    }
+
+    fn result_of(response: &JsonRpcResponse) -> Option<serde_json::Value> {
+        response
+            .result
+            .as_deref()
+            .map(|result| from_raw_value(result).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_ping_request() {
+        let protocol = Protocol::builder().build();
+        let response = protocol.handle_request(request("ping", None)).await;
+
+        assert_eq!(response.id, 1);
+        assert!(response.error.is_none());
+        assert_eq!(result_of(&response), Some(json!({})));
+    }
+
+    #[tokio::test]
+    async fn test_request_error_codes() {
+        let protocol = Protocol::builder()
+            .request_handler("echo", |req: HashMap<String, String>| {
+                Box::pin(async move { Ok(req) })
+            })
+            .request_handler("missing", |_: serde_json::Value| {
+                Box::pin(async move {
+                    Err::<(), _>(McpError::resource_not_found("file:///missing").into())
+                })
+            })
+            .request_handler("backend", |_: serde_json::Value| {
+                Box::pin(async move {
+                    // The handler fails to parse its own data, not the request
+                    let data: serde_json::Value = serde_json::from_str("{")?;
+                    Ok(data)
+                })
+            })
+            .build();
+
+        let response = protocol
+            .handle_request(request("echo", Some(json!([1]))))
+            .await;
+        assert_eq!(
+            response.error.map(|e| e.code),
+            Some(ErrorCode::InvalidParams as i32)
+        );
+
+        let response = protocol
+            .handle_request(request("missing", Some(json!({}))))
+            .await;
+        let error = response.error.unwrap();
+        assert_eq!(error.code, ErrorCode::ResourceNotFound as i32);
+        assert_eq!(error.data, Some(json!({ "uri": "file:///missing" })));
+
+        let response = protocol
+            .handle_request(request("backend", Some(json!({}))))
+            .await;
+        assert_eq!(
+            response.error.map(|e| e.code),
+            Some(ErrorCode::InternalError as i32)
+        );
+
+        let response = protocol
+            .handle_request(request("unknown", Some(json!({}))))
+            .await;
+        assert_eq!(
+            response.error.map(|e| e.code),
+            Some(ErrorCode::MethodNotFound as i32)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_context() {
+        let protocol = Protocol::builder()
+            .request_handler_with_context("context", |_: serde_json::Value, context| {
+                Box::pin(async move {
+                    Ok(json!({
+                        "requestId": context.request_id,
+                        "method": context.method,
+                        "meta": context.meta,
+                    }))
+                })
+            })
+            .request_handler_with_context("wait", |_: serde_json::Value, context| {
+                Box::pin(async move {
+                    context.cancellation.cancelled().await;
+                    Ok(json!({}))
+                })
+            })
+            .build();
+
+        let response = protocol
+            .handle_request(JsonRpcRequest {
+                id: 7,
+                ..request("context", Some(json!({ "_meta": { "progressToken": 1 } })))
+            })
+            .await;
+        assert_eq!(
+            result_of(&response),
+            Some(json!({
+                "requestId": 7,
+                "method": "context",
+                "meta": { "progressToken": 1 },
+            }))
+        );
+
+        let waiting = tokio::spawn({
+            let protocol = protocol.clone();
+            async move {
+                protocol
+                    .handle_request(JsonRpcRequest {
+                        id: 8,
+                        ..request("wait", None)
+                    })
+                    .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        protocol
+            .handle_notification(notification(
+                "notifications/cancelled",
+                Some(json!({ "requestId": 8 })),
+            ))
+            .await;
+
+        let response = tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .expect("cancelled request did not finish")
+            .unwrap();
+        assert_eq!(response.id, 8);
+    }
+
+    #[tokio::test]
+    async fn test_middleware() {
+        struct Deny;
+
+        #[async_trait]
+        impl Middleware for Deny {
+            async fn before_request(
+                &self,
+                _request: &mut JsonRpcRequest,
+                context: &RequestContext,
+            ) -> Result<(), McpError> {
+                if context.method == "secret" {
+                    return Err(McpError::invalid_request("Denied"));
+                }
+                Ok(())
+            }
+        }
+
+        struct Tag;
+
+        #[async_trait]
+        impl Middleware for Tag {
+            async fn after_request(
+                &self,
+                context: &RequestContext,
+                response: &mut JsonRpcResponse,
+            ) {
+                if let Some(mut result) = result_of(response) {
+                    result["method"] = json!(context.method);
+                    response.result = Some(raw(result));
+                }
+            }
+        }
+
+        let protocol = Protocol::builder()
+            .middleware(Deny)
+            .middleware(Tag)
+            .request_handler("secret", |_: serde_json::Value| {
+                Box::pin(async move { Ok(json!({})) })
+            })
+            .build();
+
+        let response = protocol.handle_request(request("ping", None)).await;
+        assert_eq!(result_of(&response), Some(json!({ "method": "ping" })));
+
+        let response = protocol.handle_request(request("secret", None)).await;
+        assert!(response.result.is_none());
+        assert_eq!(
+            response.error.map(|e| e.code),
+            Some(ErrorCode::InvalidRequest as i32)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_runtime_handlers() {
+        let protocol = Protocol::builder().build();
+        let echo = || request("echo", Some(json!("hello")));
+
+        protocol.set_request_handler("echo", |req: String, _| Box::pin(async move { Ok(req) }));
+        let response = protocol.handle_request(echo()).await;
+        assert_eq!(result_of(&response), Some(json!("hello")));
+
+        assert!(protocol.remove_request_handler("echo"));
+        let response = protocol.handle_request(echo()).await;
+        assert_eq!(
+            response.error.map(|e| e.code),
+            Some(ErrorCode::MethodNotFound as i32)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_traceparent_round_trip() {
+        let protocol = Protocol::builder()
+            .request_handler("trace", |_: serde_json::Value| {
+                Box::pin(async move {
+                    // Requests sent while handling continue the trace of the caller
+                    let (outgoing, _) = trace_context::inject(None);
+                    Ok(json!({
+                        "current": trace_context::current().map(|current| current.to_string()),
+                        "outgoing": outgoing,
+                    }))
+                })
+            })
+            .build();
+
+        let (params, caller) = trace_context::inject(Some(json!({})));
+        let caller = caller.unwrap();
+        let response = protocol.handle_request(request("trace", params)).await;
+        let result = result_of(&response).unwrap();
+
+        let current = TraceParent::parse(result["current"].as_str().unwrap()).unwrap();
+        assert_eq!(current.trace_id, caller.trace_id);
+        assert_ne!(current.span_id, caller.span_id);
+        let outgoing = TraceParent::from_params(Some(&result["outgoing"])).unwrap();
+        assert_eq!(outgoing.trace_id, caller.trace_id);
+        assert_ne!(outgoing.span_id, current.span_id);
+    }
+
+    #[tokio::test]
+    async fn test_request_requires_capability() {
+        let protocol = Protocol::builder().build();
+        let peer = Peer::detached(protocol.clone(), Some("session".to_string()));
+        peer.update_info(|info| info.client_capabilities = Some(ClientCapabilities::default()));
+
+        let error = peer
+            .request("sampling/createMessage", None, None)
+            .await
+            .unwrap_err();
+        let error = error.downcast_ref::<McpError>().unwrap();
+        assert_eq!(error.error_code(), Some(ErrorCode::MethodNotFound));
+        assert_eq!(error.data, Some(json!({ "capability": "sampling" })));
+    }
+
+    #[tokio::test]
+    async fn test_roots_cache() {
+        let protocol = Protocol::builder().build();
+        let peer = Peer::detached(protocol.clone(), Some("a".to_string()));
+        let root = Root {
+            uri: "file:///workspace".parse().unwrap(),
+            name: Some("workspace".to_string()),
+        };
+        peer.update_info(|info| info.roots = Some(vec![root.clone()]));
+        assert_eq!(peer.list_roots(None).await.unwrap(), vec![root]);
+
+        protocol
+            .handle_notification_from(
+                notification("notifications/roots/list_changed", None),
+                peer.clone(),
+            )
+            .await;
+        let info = peer.info();
+        assert_eq!(info.roots, None);
+        assert_eq!(info.roots_changes, 1);
+        // The peer is not connected, so the roots cannot be fetched again
+        assert!(peer.list_roots(None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_close_fails_pending_requests() {
+        let protocol = Protocol::builder().build();
+        let (_, pending) = protocol.create_request().await;
+
+        protocol.close().await;
+        let response = pending.await.unwrap();
+        assert_eq!(
+            response.error.map(|e| e.code),
+            Some(ErrorCode::ConnectionClosed as i32)
+        );
+
+        let (_, rejected) = protocol.create_request().await;
+        assert!(rejected.await.unwrap().error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_remove_session_fails_pending_requests() {
+        let protocol = Protocol::builder().build();
+        let (_, removed) = protocol.create_request_for(Some("a")).await;
+        let (_, mut other) = protocol.create_request_for(Some("b")).await;
+
+        protocol.remove_session(Some("a"));
+        assert_eq!(
+            removed.await.unwrap().error.map(|e| e.code),
+            Some(ErrorCode::ConnectionClosed as i32)
+        );
+        assert!(other.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_response_from_other_session() {
+        let protocol = Protocol::builder().build();
+        let (id, mut rx) = protocol.create_request_for(Some("a")).await;
+        let response = || JsonRpcResponse {
+            id,
+            result: Some(raw(json!({}))),
+            ..Default::default()
+        };
+
+        protocol.handle_response_from(response(), Some("b")).await;
+        protocol.handle_response(response()).await;
+        assert!(rx.try_recv().is_err());
+
+        protocol.handle_response_from(response(), Some("a")).await;
+        assert!(rx.await.unwrap().error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ping_closed_protocol() {
+        let protocol = Protocol::builder().build();
+        let transport = crate::transport::Recorder {
+            protocol: Some(protocol.clone()),
+            ..Default::default()
+        };
+        let liveness = LivenessCheck::new(Duration::from_millis(10), Duration::from_millis(50), 1);
+        assert!(!liveness.ping(&transport).await);
+
+        protocol.close().await;
+        let started = std::time::Instant::now();
+        assert!(!liveness.ping(&transport).await);
+        // The closed connection fails the ping without waiting for the timeout
+        assert!(started.elapsed() < Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_progress_resets_timeout() {
+        let protocol = Protocol::builder().build();
+        let params = raw(json!({ "_meta": { "progressToken": "build" } }));
+        let options = RequestOptions::default()
+            .timeout(Duration::from_millis(100))
+            .reset_timeout_on_progress(true)
+            .max_total_timeout(Duration::from_millis(1000));
+
+        let (id, rx) = protocol.create_request().await;
+        let server = protocol.clone();
+        tokio::spawn(async move {
+            for _ in 0..4 {
+                tokio::time::sleep(Duration::from_millis(60)).await;
+                server
+                    .handle_notification(notification(
+                        "notifications/progress",
+                        Some(json!({ "progressToken": "build", "progress": 1 })),
+                    ))
+                    .await;
+            }
+            server
+                .handle_response(JsonRpcResponse {
+                    id,
+                    result: Some(raw(json!({}))),
+                    ..Default::default()
+                })
+                .await;
+        });
+        let response = protocol
+            .wait_for_response(id, rx, Some(&*params), &options)
+            .await;
+        assert!(response.error.is_none());
+
+        // Progress cannot extend the request beyond the maximum total time
+        let options = options.max_total_timeout(Duration::from_millis(150));
+        let (id, rx) = protocol.create_request().await;
+        let server = protocol.clone();
+        tokio::spawn(async move {
+            for _ in 0..10 {
+                tokio::time::sleep(Duration::from_millis(60)).await;
+                server
+                    .handle_notification(notification(
+                        "notifications/progress",
+                        Some(json!({ "progressToken": "build", "progress": 1 })),
+                    ))
+                    .await;
+            }
+        });
+        let response = protocol
+            .wait_for_response(id, rx, Some(&*params), &options)
+            .await;
+        assert_eq!(
+            response.error.map(|e| e.code),
+            Some(ErrorCode::RequestTimeout as i32)
+        );
     }
 }
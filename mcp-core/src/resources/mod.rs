@@ -0,0 +1,468 @@
+//! # MCP Resources Management
+//!
+//! This module provides the infrastructure for exposing resources from a server.
+//! Resources are data, such as files or database records, that clients can list
+//! and read by URI.
+//!
+//! A resource is either static, with contents fixed at registration, or backed
+//! by an async provider that produces its contents on each read, as text or as
+//! a binary blob. Like the tool registry, the resource registry can be changed
+//! while the server is running.
+//!
+//! Resource sets that are too large to list, such as the rows of a database,
+//! are exposed through resource templates: a provider registered with an
+//! RFC 6570 URI template like `db://{table}/{id}` reads every URI matching it.
+//!
+//! Clients can subscribe to resources, and the `ResourcePublisher` notifies
+//! them when a resource changes.
+
+use crate::change_notifier::ChangeNotifier;
+use crate::error::McpError;
+use crate::protocol::RequestContext;
+use crate::types::{ReadResourceResponse, Resource, ResourceContents, ResourceTemplate};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, PoisonError, RwLock};
+use url::Url;
+
+mod publisher;
+mod template;
+pub use publisher::ResourcePublisher;
+pub use template::UriTemplate;
+
+/// Registry of the resources of a server.
+///
+/// The `Resources` struct manages a collection of resources and their
+/// associated content providers, providing methods to list and read them.
+pub struct Resources {
+    resource_handlers: RwLock<HashMap<String, Arc<ResourceHandler>>>,
+    template_handlers: RwLock<Vec<Arc<TemplateHandler>>>,
+}
+
+impl Resources {
+    /// Creates a new, empty resource registry.
+    pub(crate) fn new() -> Self {
+        Self {
+            resource_handlers: RwLock::new(HashMap::new()),
+            template_handlers: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Retrieves a resource definition by URI.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The URI of the resource to retrieve
+    ///
+    /// # Returns
+    ///
+    /// An `Option` containing the resource if found, or `None` if not found.
+    pub fn get_resource(&self, uri: &str) -> Option<Resource> {
+        self.resource_handlers
+            .read()
+            .ok()?
+            .get(uri)
+            .map(|resource_handler| resource_handler.resource.clone())
+    }
+
+    /// Checks whether a URI is a registered resource or matches a resource template.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The URI to check
+    ///
+    /// # Returns
+    ///
+    /// `true` if the URI can be read
+    pub fn has_resource(&self, uri: &str) -> bool {
+        self.get_resource(uri).is_some() || self.match_template(uri).is_some()
+    }
+
+    /// Reads the contents of a resource.
+    ///
+    /// A URI that is not a registered resource is read by the provider of the
+    /// first resource template matching it. Contents without a MIME type get
+    /// the MIME type of the resource or template definition.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The URI of the resource to read
+    /// * `context` - The context of the `resources/read` request
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the contents of the resource, or a resource not
+    /// found error if there is no resource with this URI.
+    pub async fn read_resource(
+        &self,
+        uri: Url,
+        context: RequestContext,
+    ) -> Result<ReadResourceResponse> {
+        let handler = self
+            .resource_handlers
+            .read()
+            .ok()
+            .and_then(|resource_handlers| resource_handlers.get(uri.as_str()).cloned());
+
+        let (mut contents, mime_type) = match handler {
+            Some(handler) => (
+                (handler.f)(uri, context).await?,
+                handler.resource.mime_type.clone(),
+            ),
+            None => {
+                let (handler, vars) = self
+                    .match_template(uri.as_str())
+                    .ok_or_else(|| McpError::resource_not_found(uri.as_str()))?;
+                (
+                    (handler.f)(uri, vars, context).await?,
+                    handler.template.mime_type.clone(),
+                )
+            }
+        };
+        for content in contents.iter_mut() {
+            if content.mime_type.is_none() {
+                content.mime_type = mime_type.clone();
+            }
+        }
+        Ok(ReadResourceResponse {
+            contents,
+            meta: None,
+        })
+    }
+
+    /// Lists all registered resources.
+    ///
+    /// # Returns
+    ///
+    /// A vector containing all registered resources.
+    pub fn list_resources(&self) -> Vec<Resource> {
+        self.resource_handlers
+            .read()
+            .map(|resource_handlers| {
+                resource_handlers
+                    .values()
+                    .map(|resource_handler| resource_handler.resource.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Lists all registered resource templates, in registration order.
+    ///
+    /// # Returns
+    ///
+    /// A vector containing all registered resource templates.
+    pub fn list_resource_templates(&self) -> Vec<ResourceTemplate> {
+        self.template_handlers
+            .read()
+            .map(|template_handlers| {
+                template_handlers
+                    .iter()
+                    .map(|template_handler| template_handler.template.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Finds the first resource template matching a URI.
+    fn match_template(&self, uri: &str) -> Option<(Arc<TemplateHandler>, HashMap<String, String>)> {
+        self.template_handlers
+            .read()
+            .ok()?
+            .iter()
+            .find_map(|template_handler| {
+                template_handler
+                    .uri_template
+                    .match_uri(uri)
+                    .map(|vars| (template_handler.clone(), vars))
+            })
+    }
+}
+
+/// A handle for changing the resources of a server while it is running.
+///
+/// Clones of a `ResourceRegistry` share the same resources. After each change,
+/// the registered change listeners are called; the server uses this to send
+/// `notifications/resources/list_changed` when its
+/// `ResourceCapabilities.list_changed` is set.
+///
+/// # Examples
+///
+/// ```
+/// use mcp_core::server::Server;
+/// use mcp_core::types::{Resource, ResourceContents};
+///
+/// let builder = Server::builder(
+///     "files".to_string(),
+///     "1.0".to_string(),
+///     mcp_core::types::ProtocolVersion::V2025_03_26,
+/// );
+/// let readme = Resource {
+///     uri: "file:///readme.md".parse().unwrap(),
+///     name: "README".to_string(),
+///     description: None,
+///     mime_type: Some("text/markdown".to_string()),
+///     annotations: None,
+///     size: None,
+/// };
+/// let contents = ResourceContents::text(readme.uri.clone(), "# Hello");
+/// let builder = builder.register_static_resource(readme, contents);
+///
+/// // Resources can also be added once the server is running
+/// let registry = builder.resource_registry();
+/// let protocol = builder.build();
+/// assert!(registry.remove_resource("file:///readme.md"));
+/// ```
+#[derive(Clone)]
+pub struct ResourceRegistry {
+    resources: Arc<Resources>,
+    changes: ChangeNotifier,
+}
+
+impl ResourceRegistry {
+    /// Creates a new, empty resource registry.
+    ///
+    /// # Returns
+    ///
+    /// A new `ResourceRegistry` instance
+    pub fn new() -> Self {
+        Self {
+            resources: Arc::new(Resources::new()),
+            changes: ChangeNotifier::default(),
+        }
+    }
+
+    /// Returns the resources of the registry, for listing and reading them.
+    pub fn resources(&self) -> Arc<Resources> {
+        self.resources.clone()
+    }
+
+    /// Adds a resource whose contents are produced on each read, replacing any
+    /// resource with the same URI.
+    ///
+    /// # Arguments
+    ///
+    /// * `resource` - The resource definition
+    /// * `f` - The content provider, called with the URI of the resource and
+    ///   the context of the `resources/read` request
+    pub fn add_resource(
+        &self,
+        resource: Resource,
+        f: impl Fn(
+                Url,
+                RequestContext,
+            ) -> Pin<Box<dyn Future<Output = Result<Vec<ResourceContents>>> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.insert(resource, Box::new(f));
+    }
+
+    /// Adds a resource with fixed contents, replacing any resource with the same URI.
+    ///
+    /// # Arguments
+    ///
+    /// * `resource` - The resource definition
+    /// * `contents` - The contents returned on each read
+    pub fn add_static_resource(&self, resource: Resource, contents: ResourceContents) {
+        self.insert(
+            resource,
+            Box::new(move |_uri, _context| {
+                let contents = contents.clone();
+                Box::pin(async move { Ok(vec![contents]) })
+            }),
+        );
+    }
+
+    /// Adds a resource template, replacing any template with the same URI template.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - The resource template definition
+    /// * `f` - The content provider, called with the URI to read, the values of
+    ///   the template variables and the context of the `resources/read` request
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success, or an error if the URI template is invalid
+    pub fn add_resource_template(
+        &self,
+        template: ResourceTemplate,
+        f: impl Fn(
+                Url,
+                HashMap<String, String>,
+                RequestContext,
+            ) -> Pin<Box<dyn Future<Output = Result<Vec<ResourceContents>>> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Result<()> {
+        let uri_template = UriTemplate::parse(&template.uri_template)?;
+        {
+            let mut template_handlers = self
+                .resources
+                .template_handlers
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+            let handler = Arc::new(TemplateHandler {
+                template,
+                uri_template,
+                f: Box::new(f),
+            });
+            match template_handlers
+                .iter_mut()
+                .find(|existing| existing.uri_template == handler.uri_template)
+            {
+                Some(existing) => *existing = handler,
+                None => template_handlers.push(handler),
+            }
+        }
+        self.changes.notify();
+        Ok(())
+    }
+
+    /// Removes a resource template.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri_template` - The URI template of the template to remove
+    ///
+    /// # Returns
+    ///
+    /// `true` if the template was removed, `false` if there was no such template
+    pub fn remove_resource_template(&self, uri_template: &str) -> bool {
+        // Compared like in `add_resource_template`
+        let Ok(uri_template) = UriTemplate::parse(uri_template) else {
+            return false;
+        };
+        let removed = {
+            let mut template_handlers = self
+                .resources
+                .template_handlers
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+            let before = template_handlers.len();
+            template_handlers.retain(|handler| handler.uri_template != uri_template);
+            before != template_handlers.len()
+        };
+        if removed {
+            self.changes.notify();
+        }
+        removed
+    }
+
+    /// Removes a resource.
+    ///
+    /// The URI is normalized like the URIs of added resources, so e.g.
+    /// `FILE:///a.txt` removes the resource `file:///a.txt`.
+    /// Sessions of a server subscribed to the resource are unsubscribed,
+    /// unless a resource template still matches its URI.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The URI of the resource to remove
+    ///
+    /// # Returns
+    ///
+    /// `true` if the resource was removed, `false` if there was no such resource
+    pub fn remove_resource(&self, uri: &str) -> bool {
+        let Ok(uri) = Url::parse(uri) else {
+            return false;
+        };
+        let removed = self
+            .resources
+            .resource_handlers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(uri.as_str())
+            .is_some();
+        if removed {
+            self.changes.notify();
+        }
+        removed
+    }
+
+    /// Retrieves a resource definition by URI.
+    pub fn get_resource(&self, uri: &str) -> Option<Resource> {
+        self.resources.get_resource(uri)
+    }
+
+    /// Lists all registered resources.
+    pub fn list_resources(&self) -> Vec<Resource> {
+        self.resources.list_resources()
+    }
+
+    /// Lists all registered resource templates.
+    pub fn list_resource_templates(&self) -> Vec<ResourceTemplate> {
+        self.resources.list_resource_templates()
+    }
+
+    /// Registers a function called after every change to the resources.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The function to call
+    pub fn on_change(&self, f: impl Fn() + Send + Sync + 'static) {
+        self.changes.on_change(f);
+    }
+
+    fn insert(&self, resource: Resource, f: Box<ResourceHandlerFn>) {
+        self.resources
+            .resource_handlers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(
+                resource.uri.to_string(),
+                Arc::new(ResourceHandler { resource, f }),
+            );
+        self.changes.notify();
+    }
+}
+
+impl Default for ResourceRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Type alias for a resource content provider.
+///
+/// A provider is called with the URI of the resource and the `RequestContext`
+/// of the `resources/read` request, and returns a future that resolves to the
+/// contents of the resource.
+pub type ResourceHandlerFn = dyn Fn(Url, RequestContext) -> Pin<Box<dyn Future<Output = Result<Vec<ResourceContents>>> + Send>>
+    + Send
+    + Sync;
+
+/// Type alias for a resource template content provider.
+///
+/// A provider is called with the URI to read, the values of the variables of
+/// the URI template, and the `RequestContext` of the `resources/read` request.
+pub type ResourceTemplateHandlerFn = dyn Fn(
+        Url,
+        HashMap<String, String>,
+        RequestContext,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<ResourceContents>>> + Send>>
+    + Send
+    + Sync;
+
+/// Container for a resource definition and its content provider.
+pub(crate) struct ResourceHandler {
+    /// The resource definition (URI, name, MIME type, etc.)
+    pub resource: Resource,
+    /// The provider of the resource contents
+    pub f: Box<ResourceHandlerFn>,
+}
+
+/// Container for a resource template definition and its content provider.
+pub(crate) struct TemplateHandler {
+    /// The resource template definition
+    pub template: ResourceTemplate,
+    /// The parsed URI template
+    pub uri_template: UriTemplate,
+    /// The provider of the contents of matching resources
+    pub f: Box<ResourceTemplateHandlerFn>,
+}
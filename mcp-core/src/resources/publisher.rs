@@ -0,0 +1,282 @@
+use crate::protocol::{PeerInfo, Protocol};
+use crate::types::ResourceUpdatedNotification;
+use anyhow::Result;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use url::Url;
+
+/// The default time over which updates are coalesced
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Publishes changes to the resources of a server.
+///
+/// `resource_updated` sends `notifications/resources/updated` to the sessions
+/// that subscribed to the resource with `resources/subscribe`, and
+/// `list_changed` sends `notifications/resources/list_changed` to the
+/// initialized sessions the server advertised `resources.listChanged` to.
+///
+/// Notifications are debounced: the first change schedules a notification
+/// after the debounce delay, and further changes until it is sent are
+/// coalesced into it. Clients therefore see every change, at most once per
+/// delay, which keeps rapidly changing resources such as log files from
+/// flooding them.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mcp_core::resources::ResourcePublisher;
+/// use mcp_core::server::Server;
+/// use std::time::Duration;
+///
+/// # async fn example() -> anyhow::Result<()> {
+/// let protocol = Server::builder(
+///     "builds".to_string(),
+///     "1.0".to_string(),
+///     mcp_core::types::ProtocolVersion::V2025_03_26,
+/// )
+/// .build();
+/// let publisher = ResourcePublisher::new(protocol).with_debounce(Duration::from_millis(500));
+///
+/// // Whenever the build log grows
+/// publisher.resource_updated("file:///build.log")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ResourcePublisher {
+    protocol: Protocol,
+    debounce: Duration,
+    pending: Arc<Mutex<HashSet<Change>>>,
+}
+
+/// A change waiting to be published
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Change {
+    Updated(String),
+    ListChanged,
+}
+
+impl ResourcePublisher {
+    /// Creates a publisher for the resources of a server.
+    ///
+    /// # Arguments
+    ///
+    /// * `protocol` - The protocol of the server
+    ///
+    /// # Returns
+    ///
+    /// A new `ResourcePublisher`, debouncing changes over 100 milliseconds
+    pub fn new(protocol: Protocol) -> Self {
+        Self {
+            protocol,
+            debounce: DEFAULT_DEBOUNCE,
+            pending: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Sets the time over which changes are coalesced.
+    ///
+    /// # Arguments
+    ///
+    /// * `debounce` - The debounce delay, zero to send every change at once
+    ///
+    /// # Returns
+    ///
+    /// The modified publisher
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Announces that a resource has changed to the sessions subscribed to it.
+    ///
+    /// The URI is normalized the same way as the URIs of `resources/subscribe`
+    /// requests, so e.g. `https://example.com` reaches the sessions subscribed
+    /// to `https://example.com/`. Must be called from within a Tokio runtime.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The URI of the resource
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` once the notification is scheduled, or an error if the URI is invalid
+    pub fn resource_updated(&self, uri: &str) -> Result<()> {
+        let uri =
+            Url::parse(uri).map_err(|e| anyhow::anyhow!("Invalid resource URI {}: {}", uri, e))?;
+        self.schedule(Change::Updated(uri.to_string()));
+        Ok(())
+    }
+
+    /// Announces that the list of resources has changed.
+    ///
+    /// Only the sessions the server advertised the `resources.listChanged`
+    /// capability to are notified. Must be called from within a Tokio runtime.
+    pub fn list_changed(&self) {
+        self.schedule(Change::ListChanged);
+    }
+
+    fn schedule(&self, change: Change) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!("Cannot publish {:?} outside of a Tokio runtime", change);
+            return;
+        };
+        let scheduled = self
+            .pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(change.clone());
+        if !scheduled {
+            // Coalesced into the pending notification
+            return;
+        }
+
+        let publisher = self.clone();
+        runtime.spawn(async move {
+            tokio::time::sleep(publisher.debounce).await;
+            // Changes made from now on need another notification
+            publisher
+                .pending
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(&change);
+            match change {
+                Change::Updated(uri) => publisher.send_updated(uri).await,
+                Change::ListChanged => publisher.send_list_changed().await,
+            }
+        });
+    }
+
+    async fn send_updated(&self, uri: String) {
+        let params = match serde_json::to_value(ResourceUpdatedNotification { uri: uri.clone() }) {
+            Ok(params) => params,
+            Err(e) => {
+                tracing::error!("Failed to serialize resource update: {}", e);
+                return;
+            }
+        };
+        self.send_to("notifications/resources/updated", Some(params), |info| {
+            info.resource_subscriptions.contains(&uri)
+        })
+        .await;
+    }
+
+    async fn send_list_changed(&self) {
+        self.send_to("notifications/resources/list_changed", None, |info| {
+            info.server_capabilities
+                .as_ref()
+                .and_then(|capabilities| capabilities.resources.as_ref())
+                .and_then(|resources| resources.list_changed)
+                .unwrap_or(false)
+        })
+        .await;
+    }
+
+    /// Sends a notification to the initialized sessions whose `PeerInfo` passes a filter.
+    async fn send_to(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+        filter: impl Fn(&PeerInfo) -> bool,
+    ) {
+        let sessions = self.protocol.sessions();
+        for session_id in sessions.ids() {
+            let selected = sessions
+                .get(session_id.as_deref())
+                .is_some_and(|info| filter(&info));
+            let Some(peer) = sessions.peer(session_id.as_deref()).filter(|_| selected) else {
+                continue;
+            };
+            if let Err(e) = peer.send_notification(method, params.clone()).await {
+                tracing::debug!(
+                    "Failed to send {} to session {:?}: {}",
+                    method,
+                    session_id,
+                    e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{JsonRpcNotification, Recorder};
+    use crate::types::{ResourceCapabilities, ServerCapabilities};
+
+    /// Connects an initialized session to the protocol.
+    async fn connect(
+        protocol: &Protocol,
+        session_id: &str,
+        f: impl FnOnce(&mut PeerInfo),
+    ) -> Arc<Recorder> {
+        let recorder = Arc::new(Recorder::default());
+        let peer = protocol.peer(Some(session_id.to_string()), recorder.clone());
+        peer.update_info(f);
+        protocol
+            .handle_notification_from(
+                JsonRpcNotification {
+                    method: "notifications/initialized".to_string(),
+                    params: None,
+                    jsonrpc: Default::default(),
+                },
+                peer,
+            )
+            .await;
+        recorder
+    }
+
+    #[tokio::test]
+    async fn test_publish_to_sessions() {
+        let protocol = Protocol::builder().build();
+        let subscribed = connect(&protocol, "a", |info| {
+            info.resource_subscriptions
+                .insert("https://example.com/".to_string());
+            info.server_capabilities = Some(ServerCapabilities {
+                resources: Some(ResourceCapabilities {
+                    subscribe: Some(true),
+                    list_changed: Some(true),
+                }),
+                ..Default::default()
+            });
+        })
+        .await;
+        let other = connect(&protocol, "b", |_| {}).await;
+        let publisher = ResourcePublisher::new(protocol).with_debounce(Duration::from_millis(50));
+
+        // Rapid changes are coalesced into one notification each
+        for _ in 0..3 {
+            publisher.resource_updated("https://example.com").unwrap();
+            publisher.list_changed();
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let mut notifications = subscribed.notifications.lock().unwrap().clone();
+        notifications.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            notifications,
+            vec![
+                ("notifications/resources/list_changed".to_string(), None),
+                (
+                    "notifications/resources/updated".to_string(),
+                    Some(serde_json::json!({ "uri": "https://example.com/" })),
+                ),
+            ]
+        );
+        assert!(other.notifications.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resource_updated_rejects_invalid_uri() {
+        let publisher = ResourcePublisher::new(Protocol::builder().build());
+        assert!(publisher.resource_updated("not a uri").is_err());
+        assert!(publisher.resource_updated("https://example.com").is_ok());
+        assert!(publisher
+            .pending
+            .lock()
+            .unwrap()
+            .contains(&Change::Updated("https://example.com/".to_string())));
+    }
+}
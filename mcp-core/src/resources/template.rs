@@ -0,0 +1,368 @@
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// The work, roughly in characters scanned, a match may take before the URI
+/// is considered not to match.
+const MATCH_BUDGET: usize = 1_000_000;
+
+/// An RFC 6570 URI template, matched against the URIs of `resources/read` requests.
+///
+/// Matching reverses the expansion of the template: it extracts the value of
+/// each variable from a URI. The operators behave as follows:
+/// - `{var}`: one or more characters other than `/`, `?`, `#` and `,`
+/// - `{+var}` and `{#var}`: any characters, including `/`
+/// - `{/var}`, `{.var}`: a path segment or a dot-prefixed label
+/// - `{?var}`, `{&var}`: query parameters, which may be missing
+///
+/// An expression may list several variables, e.g. `{x,y}` or `{?page,limit}`.
+/// Prefix (`:n`) and explode (`*`) modifiers are accepted and ignored.
+/// Values are percent-decoded. Adjacent expressions, like `{a}{b}`, are
+/// ambiguous and are matched by backtracking, which gives up after a bounded
+/// amount of work, so that a long URI cannot stall the server.
+///
+/// # Examples
+///
+/// ```
+/// use mcp_core::resources::UriTemplate;
+///
+/// let template = UriTemplate::parse("db://{table}/{id}{?fields}").unwrap();
+/// let vars = template.match_uri("db://users/42?fields=name").unwrap();
+/// assert_eq!(vars["table"], "users");
+/// assert_eq!(vars["id"], "42");
+/// assert_eq!(vars["fields"], "name");
+/// assert!(template.match_uri("db://users").is_none());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UriTemplate {
+    template: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Expression {
+        operator: Operator,
+        vars: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Simple,
+    Reserved,
+    Fragment,
+    Label,
+    Path,
+    Query,
+    QueryContinuation,
+}
+
+impl Operator {
+    /// The character preceding the expansion, and the separator between values.
+    fn prefix_and_separator(self) -> (&'static str, char) {
+        match self {
+            Operator::Simple | Operator::Reserved => ("", ','),
+            Operator::Fragment => ("#", ','),
+            Operator::Label => (".", '.'),
+            Operator::Path => ("/", '/'),
+            Operator::Query => ("?", '&'),
+            Operator::QueryContinuation => ("&", '&'),
+        }
+    }
+
+    /// Whether a character can be part of a value.
+    fn allows(self, c: char) -> bool {
+        match self {
+            Operator::Reserved | Operator::Fragment => true,
+            Operator::Label => !matches!(c, '/' | '?' | '#' | ',' | '.'),
+            _ => !matches!(c, '/' | '?' | '#' | ','),
+        }
+    }
+}
+
+impl UriTemplate {
+    /// Parses a URI template.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - The template, e.g. `file:///{+path}`
+    ///
+    /// # Returns
+    ///
+    /// The parsed template, or an error if an expression is unterminated or empty
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..].find('}').ok_or_else(|| {
+                anyhow::anyhow!("Unterminated expression in URI template: {}", template)
+            })? + start;
+            let expression = &rest[start + 1..end];
+            let (operator, vars) = match expression.chars().next() {
+                Some('+') => (Operator::Reserved, &expression[1..]),
+                Some('#') => (Operator::Fragment, &expression[1..]),
+                Some('.') => (Operator::Label, &expression[1..]),
+                Some('/') => (Operator::Path, &expression[1..]),
+                Some('?') => (Operator::Query, &expression[1..]),
+                Some('&') => (Operator::QueryContinuation, &expression[1..]),
+                _ => (Operator::Simple, expression),
+            };
+            let vars = vars
+                .split(',')
+                .map(|var| {
+                    let name = var.split(':').next().unwrap_or_default();
+                    let name = name.trim_end_matches('*');
+                    if name.is_empty()
+                        || !name
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '%'))
+                    {
+                        return Err(anyhow::anyhow!(
+                            "Invalid variable in URI template: {}",
+                            template
+                        ));
+                    }
+                    Ok(name.to_string())
+                })
+                .collect::<Result<Vec<_>>>()?;
+            parts.push(Part::Expression { operator, vars });
+            rest = &rest[end + 1..];
+        }
+        if rest.contains('}') {
+            return Err(anyhow::anyhow!(
+                "Unexpected '}}' in URI template: {}",
+                template
+            ));
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+        Ok(Self {
+            template: template.to_string(),
+            parts,
+        })
+    }
+
+    /// Returns the template as it was parsed.
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// Returns the names of the variables of the template.
+    pub fn variables(&self) -> impl Iterator<Item = &str> {
+        self.parts
+            .iter()
+            .flat_map(|part| match part {
+                Part::Expression { vars, .. } => vars.as_slice(),
+                Part::Literal(_) => &[],
+            })
+            .map(String::as_str)
+    }
+
+    /// Matches a URI against the template.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The URI to match
+    ///
+    /// # Returns
+    ///
+    /// The values of the variables, or `None` if the URI does not match or
+    /// matching it takes too long. Missing query parameters are left out.
+    pub fn match_uri(&self, uri: &str) -> Option<HashMap<String, String>> {
+        let mut vars = HashMap::new();
+        let mut budget = MATCH_BUDGET;
+        match_parts(&self.parts, uri, &mut vars, &mut budget).then_some(vars)
+    }
+}
+
+impl std::fmt::Display for UriTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+/// Takes some work from the budget of a match, returning `false` once it is spent.
+fn spend(budget: &mut usize, cost: usize) -> bool {
+    *budget = budget.saturating_sub(cost);
+    *budget > 0
+}
+
+/// Matches the remaining parts of a template against the remaining input,
+/// backtracking over the possible lengths of each value.
+fn match_parts(
+    parts: &[Part],
+    input: &str,
+    vars: &mut HashMap<String, String>,
+    budget: &mut usize,
+) -> bool {
+    if !spend(budget, 1) {
+        return false;
+    }
+    let Some((part, rest)) = parts.split_first() else {
+        return input.is_empty();
+    };
+    match part {
+        Part::Literal(literal) => input
+            .strip_prefix(literal.as_str())
+            .is_some_and(|input| match_parts(rest, input, vars, budget)),
+        Part::Expression {
+            operator,
+            vars: names,
+        } => match operator {
+            Operator::Query | Operator::QueryContinuation => {
+                match_query(*operator, names, rest, input, vars, budget)
+            }
+            _ => {
+                let (prefix, _) = operator.prefix_and_separator();
+                let Some(input) = input.strip_prefix(prefix) else {
+                    return false;
+                };
+                match_values(*operator, names, rest, input, vars, budget)
+            }
+        },
+    }
+}
+
+/// Matches the values of an expression, one per variable.
+fn match_values(
+    operator: Operator,
+    names: &[String],
+    rest: &[Part],
+    input: &str,
+    vars: &mut HashMap<String, String>,
+    budget: &mut usize,
+) -> bool {
+    let Some((name, names)) = names.split_first() else {
+        return match_parts(rest, input, vars, budget);
+    };
+    let (_, separator) = operator.prefix_and_separator();
+    let len = input
+        .char_indices()
+        .find(|(_, c)| !operator.allows(*c))
+        .map_or(input.len(), |(i, _)| i);
+    if !spend(budget, len) {
+        return false;
+    }
+    // Try the longest value first
+    for end in (1..=len).rev().filter(|end| input.is_char_boundary(*end)) {
+        if !spend(budget, 1) {
+            return false;
+        }
+        let remaining = &input[end..];
+        let remaining = match names.is_empty() {
+            true => Some(remaining),
+            false => remaining.strip_prefix(separator),
+        };
+        let Some(remaining) = remaining else {
+            continue;
+        };
+        if match_values(operator, names, rest, remaining, vars, budget) {
+            // Decode only the value that matched
+            vars.insert(name.clone(), percent_decode(&input[..end]));
+            return true;
+        }
+    }
+    false
+}
+
+/// Matches query parameters, which end at a `#` or at the next literal of the template.
+fn match_query(
+    operator: Operator,
+    names: &[String],
+    rest: &[Part],
+    input: &str,
+    vars: &mut HashMap<String, String>,
+    budget: &mut usize,
+) -> bool {
+    let (prefix, separator) = operator.prefix_and_separator();
+    let Some(query) = input.strip_prefix(prefix) else {
+        // All parameters are missing
+        return match_parts(rest, input, vars, budget);
+    };
+    let end = query.find('#').unwrap_or(query.len());
+    for end in (0..=end).rev().filter(|end| query.is_char_boundary(*end)) {
+        if !match_parts(rest, &query[end..], vars, budget) {
+            continue;
+        }
+        for pair in query[..end]
+            .split(separator)
+            .filter(|pair| !pair.is_empty())
+        {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            if names.iter().any(|name| name == key) {
+                vars.insert(key.to_string(), percent_decode(value));
+            }
+        }
+        return true;
+    }
+    false
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = value
+            .get(i + 1..i + 3)
+            .filter(|hex| bytes[i] == b'%' && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_uri() {
+        let template = UriTemplate::parse("file:///{+path}").unwrap();
+        let vars = template.match_uri("file:///src/my%20file.rs").unwrap();
+        assert_eq!(vars["path"], "src/my file.rs");
+
+        let template = UriTemplate::parse("file:///{name}.{ext}").unwrap();
+        let vars = template.match_uri("file:///archive.tar.gz").unwrap();
+        assert_eq!(vars["name"], "archive.tar");
+        assert_eq!(vars["ext"], "gz");
+        assert!(template.match_uri("file:///dir/archive.gz").is_none());
+
+        let template = UriTemplate::parse("repo://{owner}{/repo,branch}{?page,limit}").unwrap();
+        let vars = template.match_uri("repo://me/mcp/main?limit=5").unwrap();
+        assert_eq!(vars["repo"], "mcp");
+        assert_eq!(vars["branch"], "main");
+        assert_eq!(vars["limit"], "5");
+        assert!(!vars.contains_key("page"));
+
+        assert!(UriTemplate::parse("file:///{path").is_err());
+        assert!(UriTemplate::parse("file:///{}").is_err());
+    }
+
+    #[test]
+    fn test_adjacent_expressions_on_long_uri() {
+        let template = UriTemplate::parse("x://{a}{b}{c}/end").unwrap();
+        let vars = template.match_uri("x://abc/end").unwrap();
+        assert_eq!((&*vars["a"], &*vars["b"], &*vars["c"]), ("a", "b", "c"));
+
+        // Every split of the value is tried before the literal fails to match
+        let uri = format!("x://{}/other", "a".repeat(100_000));
+        let started = std::time::Instant::now();
+        assert!(template.match_uri(&uri).is_none());
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,147 @@
+//! # MCP Sampling
+//!
+//! Sampling lets a server borrow the LLM of the client: the server sends
+//! `sampling/createMessage`, usually from within a tool handler with
+//! `Peer::create_message`, and the client answers with the sampled message.
+//!
+//! Clients handle these requests with a `SamplingHandler`, registered with
+//! `ClientBuilder::with_sampling_handler`, which also advertises the
+//! `sampling` capability.
+
+use crate::protocol::{Protocol, RequestContext};
+use crate::types::{CreateMessageRequest, CreateMessageResult};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Handles the `sampling/createMessage` requests of a server.
+///
+/// Implementations should let the user review the request before sampling,
+/// and return an error to decline it.
+///
+/// # Examples
+///
+/// ```
+/// use async_trait::async_trait;
+/// use mcp_core::protocol::RequestContext;
+/// use mcp_core::sampling::SamplingHandler;
+/// use mcp_core::types::{
+///     CreateMessageRequest, CreateMessageResult, Role, SamplingContent, TextContent,
+/// };
+///
+/// struct Echo;
+///
+/// #[async_trait]
+/// impl SamplingHandler for Echo {
+///     async fn create_message(
+///         &self,
+///         request: CreateMessageRequest,
+///         _context: RequestContext,
+///     ) -> anyhow::Result<CreateMessageResult> {
+///         let content = request
+///             .messages
+///             .last()
+///             .map(|message| message.content.clone())
+///             .ok_or_else(|| anyhow::anyhow!("No messages"))?;
+///         Ok(CreateMessageResult {
+///             role: Role::Assistant,
+///             content,
+///             model: "echo".to_string(),
+///             stop_reason: Some("endTurn".to_string()),
+///             meta: None,
+///         })
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait SamplingHandler: Send + Sync {
+    /// Samples the LLM of the client.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The messages to sample from and the sampling parameters
+    /// * `context` - The context of the `sampling/createMessage` request
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the sampled message
+    async fn create_message(
+        &self,
+        request: CreateMessageRequest,
+        context: RequestContext,
+    ) -> Result<CreateMessageResult>;
+}
+
+/// Registers a sampling handler for the `sampling/createMessage` requests
+/// received by a protocol.
+pub(crate) fn register_handler(protocol: &Protocol, handler: Arc<dyn SamplingHandler>) {
+    protocol.set_request_handler(
+        "sampling/createMessage",
+        move |request: CreateMessageRequest, context| {
+            let handler = handler.clone();
+            Box::pin(async move { handler.create_message(request, context).await })
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{from_raw_value, to_raw_value, JsonRpcRequest};
+    use crate::types::{Role, SamplingContent, TextContent};
+    use serde_json::json;
+
+    struct Summarizer;
+
+    #[async_trait]
+    impl SamplingHandler for Summarizer {
+        async fn create_message(
+            &self,
+            request: CreateMessageRequest,
+            _context: RequestContext,
+        ) -> Result<CreateMessageResult> {
+            Ok(CreateMessageResult {
+                role: Role::Assistant,
+                content: SamplingContent::Text(TextContent {
+                    content_type: "text".to_string(),
+                    text: format!("{} messages", request.messages.len()),
+                    annotations: None,
+                }),
+                model: "summarizer".to_string(),
+                stop_reason: Some("endTurn".to_string()),
+                meta: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sampling_handler() {
+        let protocol = Protocol::builder().build();
+        register_handler(&protocol, Arc::new(Summarizer));
+
+        let params = json!({
+            "messages": [
+                { "role": "user", "content": { "type": "text", "text": "Summarize this" } },
+            ],
+            "maxTokens": 100,
+        });
+        let response = protocol
+            .handle_request(JsonRpcRequest {
+                id: 1,
+                method: "sampling/createMessage".to_string(),
+                params: Some(to_raw_value(&params).unwrap()),
+                jsonrpc: Default::default(),
+            })
+            .await;
+        let result: serde_json::Value = from_raw_value(&response.result.unwrap()).unwrap();
+        assert_eq!(
+            result,
+            json!({
+                "role": "assistant",
+                "content": { "type": "text", "text": "1 messages" },
+                "model": "summarizer",
+                "stopReason": "endTurn",
+            })
+        );
+    }
+}
@@ -0,0 +1,274 @@
+//! Validation of JSON values against JSON Schemas.
+//!
+//! Only the keywords commonly found in tool output schemas are checked:
+//! `type`, `enum`, `const`, `properties`, `required`, `additionalProperties`,
+//! `items`, `minItems`, `maxItems`, `minLength`, `maxLength`, `minimum`,
+//! `maximum`, `allOf`, `anyOf`, `oneOf` and local `$ref`s, e.g. to
+//! `#/definitions/...` as generated by schemars. Other keywords are ignored.
+
+use serde_json::Value;
+
+/// Validates a value against a schema.
+///
+/// # Arguments
+///
+/// * `schema` - The schema
+/// * `value` - The value to validate
+///
+/// # Returns
+///
+/// `Ok(())` if the value matches the schema, or a description of the first
+/// mismatch, with the path of the offending value. A schema whose `$ref`s
+/// loop back to themselves without descending into the value is an error.
+pub(crate) fn validate(schema: &Value, value: &Value) -> Result<(), String> {
+    validate_at(schema, schema, value, "$", &[])
+}
+
+/// Validates a value against a schema, `followed` being the `$ref`s already
+/// resolved for this value.
+fn validate_at(
+    root: &Value,
+    schema: &Value,
+    value: &Value,
+    path: &str,
+    followed: &[&str],
+) -> Result<(), String> {
+    let schema = match schema {
+        Value::Bool(true) => return Ok(()),
+        Value::Bool(false) => return Err(format!("{}: no value is allowed", path)),
+        Value::Object(schema) => schema,
+        _ => return Ok(()),
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let target = reference
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer))
+            .ok_or_else(|| format!("{}: unresolved reference {}", path, reference))?;
+        if followed.contains(&reference) {
+            return Err(format!("{}: circular reference {}", path, reference));
+        }
+        let followed = [followed, &[reference]].concat();
+        validate_at(root, target, value, path, &followed)?;
+    }
+
+    if let Some(types) = schema.get("type") {
+        let matches = match types {
+            Value::String(name) => has_type(value, name),
+            Value::Array(names) => names
+                .iter()
+                .filter_map(Value::as_str)
+                .any(|name| has_type(value, name)),
+            _ => true,
+        };
+        if !matches {
+            return Err(format!("{}: expected {}", path, types));
+        }
+    }
+    if let Some(Value::Array(values)) = schema.get("enum") {
+        if !values.contains(value) {
+            return Err(format!("{}: not one of the allowed values", path));
+        }
+    }
+    if let Some(constant) = schema.get("const") {
+        if constant != value {
+            return Err(format!("{}: expected {}", path, constant));
+        }
+    }
+
+    match value {
+        Value::Object(object) => {
+            for name in schema
+                .get("required")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+            {
+                if !object.contains_key(name) {
+                    return Err(format!("{}: missing property {}", path, name));
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (name, property) in object {
+                let property_path = format!("{}.{}", path, name);
+                let property_schema = properties
+                    .and_then(|properties| properties.get(name))
+                    .or_else(|| schema.get("additionalProperties"));
+                if let Some(property_schema) = property_schema {
+                    validate_at(root, property_schema, property, &property_path, &[])?;
+                }
+            }
+        }
+        Value::Array(items) => {
+            let len = items.len() as u64;
+            if schema
+                .get("minItems")
+                .and_then(Value::as_u64)
+                .is_some_and(|min| len < min)
+            {
+                return Err(format!("{}: too few items", path));
+            }
+            if schema
+                .get("maxItems")
+                .and_then(Value::as_u64)
+                .is_some_and(|max| len > max)
+            {
+                return Err(format!("{}: too many items", path));
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(root, item_schema, item, &format!("{}[{}]", path, i), &[])?;
+                }
+            }
+        }
+        Value::String(string) => {
+            let len = string.chars().count() as u64;
+            if schema
+                .get("minLength")
+                .and_then(Value::as_u64)
+                .is_some_and(|min| len < min)
+            {
+                return Err(format!("{}: too short", path));
+            }
+            if schema
+                .get("maxLength")
+                .and_then(Value::as_u64)
+                .is_some_and(|max| len > max)
+            {
+                return Err(format!("{}: too long", path));
+            }
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            if schema
+                .get("minimum")
+                .and_then(Value::as_f64)
+                .is_some_and(|min| number < min)
+            {
+                return Err(format!("{}: below the minimum", path));
+            }
+            if schema
+                .get("maximum")
+                .and_then(Value::as_f64)
+                .is_some_and(|max| number > max)
+            {
+                return Err(format!("{}: above the maximum", path));
+            }
+        }
+        _ => {}
+    }
+
+    let subschemas = |keyword: &str| {
+        schema
+            .get(keyword)
+            .and_then(Value::as_array)
+            .map(|schemas| {
+                schemas
+                    .iter()
+                    .map(|schema| validate_at(root, schema, value, path, followed))
+                    .collect::<Vec<_>>()
+            })
+    };
+    if let Some(results) = subschemas("allOf") {
+        results.into_iter().collect::<Result<Vec<_>, _>>()?;
+    }
+    if let Some(results) = subschemas("anyOf") {
+        if !results.iter().any(Result::is_ok) {
+            return Err(format!("{}: matches none of the allowed schemas", path));
+        }
+    }
+    if let Some(results) = subschemas("oneOf") {
+        if results.iter().filter(|result| result.is_ok()).count() != 1 {
+            return Err(format!("{}: does not match exactly one schema", path));
+        }
+    }
+    Ok(())
+}
+
+/// Checks a value against a JSON Schema type name.
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.as_f64().is_some_and(|number| number.fract() == 0.0),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "forecast": { "type": "array", "items": { "$ref": "#/definitions/Day" } },
+                "unit": { "enum": ["celsius", "fahrenheit"] },
+            },
+            "required": ["forecast"],
+            "additionalProperties": false,
+            "definitions": {
+                "Day": {
+                    "type": "object",
+                    "properties": {
+                        "temperature": { "type": "number", "minimum": -100 },
+                        "summary": { "type": ["string", "null"] },
+                    },
+                    "required": ["temperature"],
+                },
+            },
+        });
+        let valid = json!({
+            "forecast": [{ "temperature": 21.5, "summary": null }],
+            "unit": "celsius",
+        });
+        assert_eq!(validate(&schema, &valid), Ok(()));
+
+        let missing = json!({ "unit": "celsius" });
+        assert_eq!(
+            validate(&schema, &missing),
+            Err("$: missing property forecast".to_string())
+        );
+        let nested = json!({ "forecast": [{ "temperature": "warm" }] });
+        assert_eq!(
+            validate(&schema, &nested),
+            Err("$.forecast[0].temperature: expected \"number\"".to_string())
+        );
+        let extra = json!({ "forecast": [], "wind": 3 });
+        assert!(validate(&schema, &extra).is_err());
+        let unit = json!({ "forecast": [], "unit": "kelvin" });
+        assert!(validate(&schema, &unit).is_err());
+    }
+
+    #[test]
+    fn test_circular_references() {
+        assert_eq!(
+            validate(&json!({ "$ref": "#" }), &json!({})),
+            Err("$: circular reference #".to_string())
+        );
+        let schema = json!({
+            "allOf": [{ "$ref": "#/definitions/A" }],
+            "definitions": {
+                "A": { "anyOf": [{ "$ref": "#/definitions/B" }] },
+                "B": { "$ref": "#/definitions/A" },
+            },
+        });
+        assert!(validate(&schema, &json!(1)).is_err());
+
+        // A recursive schema is fine as long as it descends into the value
+        let tree = json!({
+            "type": "object",
+            "properties": { "children": { "type": "array", "items": { "$ref": "#" } } },
+        });
+        let value = json!({ "children": [{ "children": [] }] });
+        assert_eq!(validate(&tree, &value), Ok(()));
+    }
+}
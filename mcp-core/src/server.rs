@@ -6,50 +6,44 @@
 //! The core components include:
 //! - The `Server` for managing server lifetime
 //! - The `ServerProtocolBuilder` for configuring servers
-//! - Client connection tracking
+//! - Resources and resource templates that clients can list, read and subscribe to
+//! - Prompts that clients can list and fill in
+//! - Completions of prompt arguments and resource template variables
+//! - The minimum level of the log messages each client receives, see `logging::LoggingLayer`
+//! - Per-session client state, available through `Protocol::sessions`
 //!
-//! Servers expose tools that can be discovered and called by clients, with
-//! customizable capabilities and metadata.
+//! Servers expose tools that can be discovered and called by clients, and
+//! resources that can be read by them, with customizable capabilities and metadata.
 
-use std::{
-    collections::HashMap,
-    sync::{Arc, RwLock},
-};
+use std::sync::Arc;
 
 use crate::{
+    completions::CompletionRegistry,
+    error::McpError,
+    metrics::Metrics,
+    middleware::{Lifecycle, Middleware},
+    prompts::PromptRegistry,
     protocol::Protocol,
-    tools::{ToolHandler, ToolHandlerFn, Tools},
+    protocol::{RequestContext, TimeoutPolicy},
+    resources::ResourceRegistry,
+    tools::{ToolHandlerFn, ToolRegistry, Tools},
     types::{
-        CallToolRequest, ListRequest, ProtocolVersion, Tool, ToolsListResponse,
-        LATEST_PROTOCOL_VERSION,
+        CallToolRequest, CallToolResponse, CompleteRequest, Completion, GetPromptRequest,
+        ListRequest, Prompt, PromptCapabilities, PromptMessage, PromptMessageContent,
+        PromptsListResponse, ProtocolVersion, ReadResourceRequest, Resource, ResourceCapabilities,
+        ResourceContents, ResourceTemplate, ResourceTemplatesListResponse, ResourcesListResponse,
+        SubscribeRequest, Tool, ToolResponseContent, ToolsListResponse, LATEST_PROTOCOL_VERSION,
     },
 };
 
 use super::{
     protocol::ProtocolBuilder,
     transport::Transport,
-    types::{
-        ClientCapabilities, Implementation, InitializeRequest, InitializeResponse,
-        ServerCapabilities,
-    },
+    types::{Implementation, InitializeRequest, InitializeResponse, ServerCapabilities},
 };
 use anyhow::Result;
 use std::pin::Pin;
 
-/// Represents a connected MCP client.
-///
-/// Tracks information about a client that has connected to the server,
-/// including its capabilities, info, and initialization state.
-#[derive(Clone)]
-pub struct ClientConnection {
-    /// The capabilities reported by the client
-    pub client_capabilities: Option<ClientCapabilities>,
-    /// Information about the client implementation
-    pub client_info: Option<Implementation>,
-    /// Whether the client has completed initialization
-    pub initialized: bool,
-}
-
 /// The main MCP server type.
 ///
 /// Provides static methods for creating and starting MCP servers.
@@ -100,8 +94,10 @@ pub struct ServerProtocolBuilder {
     server_info: Implementation,
     capabilities: ServerCapabilities,
     instructions: Option<String>,
-    tools: HashMap<String, ToolHandler>,
-    client_connection: Arc<RwLock<ClientConnection>>,
+    tools: ToolRegistry,
+    resources: ResourceRegistry,
+    prompts: PromptRegistry,
+    completions: CompletionRegistry,
 }
 
 impl ServerProtocolBuilder {
@@ -118,16 +114,14 @@ impl ServerProtocolBuilder {
     pub fn new(name: String, version: String) -> Self {
         ServerProtocolBuilder {
             protocol_version: LATEST_PROTOCOL_VERSION,
-            protocol_builder: ProtocolBuilder::new(),
+            protocol_builder: ProtocolBuilder::new().middleware(Lifecycle),
             server_info: Implementation { name, version },
             capabilities: ServerCapabilities::default(),
             instructions: None,
-            tools: HashMap::new(),
-            client_connection: Arc::new(RwLock::new(ClientConnection {
-                client_capabilities: None,
-                client_info: None,
-                initialized: false,
-            })),
+            tools: ToolRegistry::new(),
+            resources: ResourceRegistry::new(),
+            prompts: PromptRegistry::new(),
+            completions: CompletionRegistry::new(),
         }
     }
 
@@ -195,23 +189,343 @@ impl ServerProtocolBuilder {
     /// # Returns
     ///
     /// The modified builder instance
-    pub fn register_tool(mut self, tool: Tool, f: ToolHandlerFn) -> Self {
-        self.tools.insert(
-            tool.name.clone(),
-            ToolHandler {
-                tool,
-                f: Box::new(f),
-            },
-        );
+    pub fn register_tool(self, tool: Tool, f: ToolHandlerFn) -> Self {
+        self.tools.add_tool(tool, f);
+        self
+    }
+
+    /// Registers a tool whose handler receives the request context.
+    ///
+    /// # Arguments
+    ///
+    /// * `tool` - The tool definition
+    /// * `f` - The handler function for the tool
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn register_tool_with_context(
+        self,
+        tool: Tool,
+        f: impl Fn(
+                CallToolRequest,
+                RequestContext,
+            ) -> Pin<Box<dyn std::future::Future<Output = CallToolResponse> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.tools.add_tool_with_context(tool, f);
+        self
+    }
+
+    /// Returns a handle for adding and removing tools after the server is built.
+    ///
+    /// Tools registered on the builder are part of the same registry. When the
+    /// server capabilities enable `tools.list_changed`, every change made
+    /// through the handle after `build` is announced to initialized sessions
+    /// with a `notifications/tools/list_changed` notification.
+    ///
+    /// # Returns
+    ///
+    /// A `ToolRegistry` handle sharing the server's tools
+    pub fn tool_registry(&self) -> ToolRegistry {
+        self.tools.clone()
+    }
+
+    /// Registers a resource whose contents are produced on each read.
+    ///
+    /// Registering a resource enables the `resources` server capability.
+    ///
+    /// # Arguments
+    ///
+    /// * `resource` - The resource definition
+    /// * `f` - The content provider for the resource
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn register_resource(
+        self,
+        resource: Resource,
+        f: impl Fn(
+                url::Url,
+                RequestContext,
+            )
+                -> Pin<Box<dyn std::future::Future<Output = Result<Vec<ResourceContents>>> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.resources.add_resource(resource, f);
+        self
+    }
+
+    /// Registers a resource with fixed contents.
+    ///
+    /// Registering a resource enables the `resources` server capability.
+    ///
+    /// # Arguments
+    ///
+    /// * `resource` - The resource definition
+    /// * `contents` - The contents of the resource
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn register_static_resource(self, resource: Resource, contents: ResourceContents) -> Self {
+        self.resources.add_static_resource(resource, contents);
+        self
+    }
+
+    /// Registers a resource template, whose provider reads every resource with
+    /// a URI matching the template.
+    ///
+    /// Registering a resource template enables the `resources` server capability.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - The resource template definition, e.g. with the URI template `db://{table}/{id}`
+    /// * `f` - The content provider, called with the URI and the values of the template variables
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance, or an error if the URI template is invalid
+    pub fn register_resource_template(
+        self,
+        template: ResourceTemplate,
+        f: impl Fn(
+                url::Url,
+                std::collections::HashMap<String, String>,
+                RequestContext,
+            )
+                -> Pin<Box<dyn std::future::Future<Output = Result<Vec<ResourceContents>>> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Result<Self> {
+        self.resources.add_resource_template(template, f)?;
+        Ok(self)
+    }
+
+    /// Returns a handle for adding and removing resources after the server is built.
+    ///
+    /// Changes to the contents of resources are announced to subscribed
+    /// clients with a `ResourcePublisher`, when the server capabilities enable
+    /// `resources.subscribe`.
+    ///
+    /// Resources registered on the builder are part of the same registry. A
+    /// server without resources at `build` only serves resources added later
+    /// if the `resources` capability is set with `set_capabilities`. When it
+    /// enables `resources.list_changed`, every change made through the handle
+    /// after `build` is announced to initialized sessions with a
+    /// `notifications/resources/list_changed` notification.
+    ///
+    /// # Returns
+    ///
+    /// A `ResourceRegistry` handle sharing the server's resources
+    pub fn resource_registry(&self) -> ResourceRegistry {
+        self.resources.clone()
+    }
+
+    /// Registers a prompt with the server.
+    ///
+    /// Registering a prompt enables the `prompts` server capability, with
+    /// `list_changed` set.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The prompt definition
+    /// * `f` - The handler, called with the arguments of `prompts/get` requests
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn register_prompt(
+        self,
+        prompt: Prompt,
+        f: impl Fn(
+                std::collections::HashMap<String, String>,
+                RequestContext,
+            )
+                -> Pin<Box<dyn std::future::Future<Output = Result<Vec<PromptMessage>>> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.prompts.add_prompt(prompt, f);
+        self
+    }
+
+    /// Returns a handle for adding and removing prompts after the server is built.
+    ///
+    /// Prompts registered on the builder are part of the same registry. A
+    /// server without prompts at `build` only serves prompts added later if
+    /// the `prompts` capability is set with `set_capabilities`. When it enables
+    /// `prompts.list_changed`, every change made through the handle sends a
+    /// `notifications/prompts/list_changed` notification.
+    ///
+    /// # Returns
+    ///
+    /// A `PromptRegistry` handle sharing the server's prompts
+    pub fn prompt_registry(&self) -> PromptRegistry {
+        self.prompts.clone()
+    }
+
+    /// Registers a completion provider for an argument of a prompt.
+    ///
+    /// Registering a completion provider enables the `completions` server capability.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The name of the prompt
+    /// * `argument` - The name of the argument
+    /// * `f` - The provider, called with the value typed so far
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn register_prompt_completion(
+        self,
+        prompt: &str,
+        argument: &str,
+        f: impl Fn(
+                String,
+                RequestContext,
+            ) -> Pin<Box<dyn std::future::Future<Output = Result<Completion>> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.completions.add_prompt_completion(prompt, argument, f);
+        self
+    }
+
+    /// Registers a completion provider for a variable of a resource template.
+    ///
+    /// Registering a completion provider enables the `completions` server capability.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri_template` - The URI template of the resource template
+    /// * `variable` - The name of the template variable
+    /// * `f` - The provider, called with the value typed so far
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance, or an error if the URI template is
+    /// invalid or has no such variable
+    pub fn register_resource_completion(
+        self,
+        uri_template: &str,
+        variable: &str,
+        f: impl Fn(
+                String,
+                RequestContext,
+            ) -> Pin<Box<dyn std::future::Future<Output = Result<Completion>> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Result<Self> {
+        self.completions
+            .add_resource_completion(uri_template, variable, f)?;
+        Ok(self)
+    }
+
+    /// Returns a handle for adding and removing completion providers after the
+    /// server is built.
+    ///
+    /// A server without completion providers at `build` only serves providers
+    /// added later if the `completions` capability is set with `set_capabilities`.
+    ///
+    /// # Returns
+    ///
+    /// A `CompletionRegistry` handle sharing the server's completion providers
+    pub fn completion_registry(&self) -> CompletionRegistry {
+        self.completions.clone()
+    }
+
+    /// Adds a middleware wrapping the dispatch of every request and notification.
+    ///
+    /// # Arguments
+    ///
+    /// * `middleware` - The middleware to add
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.protocol_builder = self.protocol_builder.middleware(middleware);
+        self
+    }
+
+    /// Negotiates the protocol version requested by a client.
+    ///
+    /// The server answers with the requested version if it supports it, that
+    /// is if it is known and not newer than the server's configured version.
+    /// Otherwise it answers with its configured version, and the client
+    /// decides whether it can work with that.
+    ///
+    /// # Arguments
+    ///
+    /// * `server_version` - The latest protocol version the server supports
+    /// * `requested` - The protocol version requested by the client
+    ///
+    /// # Returns
+    ///
+    /// The protocol version to use with the client
+    pub fn negotiate_protocol_version(
+        server_version: &ProtocolVersion,
+        requested: &str,
+    ) -> ProtocolVersion {
+        match ProtocolVersion::parse(requested) {
+            Some(version) if version <= *server_version => version,
+            _ => server_version.clone(),
+        }
+    }
+
+    /// Sets the timeout policy for requests the server sends to clients.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout_policy` - The default and per-method request options
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn timeout_policy(mut self, timeout_policy: TimeoutPolicy) -> Self {
+        self.protocol_builder = self.protocol_builder.timeout_policy(timeout_policy);
+        self
+    }
+
+    /// Sets the metrics that requests, sessions and queues are recorded in.
+    ///
+    /// Calls to tools that are not registered are recorded with the tool `other`.
+    ///
+    /// # Arguments
+    ///
+    /// * `metrics` - The metrics implementation
+    ///
+    /// # Returns
+    ///
+    /// The modified builder instance
+    pub fn metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        let metrics = RegisteredToolMetrics {
+            metrics,
+            tools: self.tools.tools(),
+        };
+        self.protocol_builder = self.protocol_builder.metrics(Arc::new(metrics));
         self
     }
 
     /// Helper function for creating an initialize request handler.
     ///
+    /// The negotiated version and the client's identity are recorded in the
+    /// `PeerInfo` of the session the request was received on.
+    ///
     /// # Arguments
     ///
-    /// * `protocol_version` - The protocol version to use
-    /// * `state` - The client connection state
+    /// * `protocol_version` - The latest protocol version to use
     /// * `server_info` - The server information
     /// * `capabilities` - The server capabilities
     /// * `instructions` - Optional server instructions
@@ -221,27 +535,43 @@ impl ServerProtocolBuilder {
     /// A handler function for initialize requests
     fn handle_init(
         protocol_version: ProtocolVersion,
-        state: Arc<RwLock<ClientConnection>>,
         server_info: Implementation,
         capabilities: ServerCapabilities,
         instructions: Option<String>,
     ) -> impl Fn(
         InitializeRequest,
+        RequestContext,
     )
         -> Pin<Box<dyn std::future::Future<Output = Result<InitializeResponse>> + Send>> {
-        move |req| {
-            let state = state.clone();
+        move |req, context| {
             let server_info = server_info.clone();
-            let capabilities = capabilities.clone();
+            let mut capabilities = capabilities.clone();
+            // A `LoggingLayer` installed after the server was built answers `logging/setLevel`
+            if capabilities.logging.is_none()
+                && context
+                    .peer
+                    .protocol()
+                    .has_request_handler("logging/setLevel")
+            {
+                capabilities.logging = Some(serde_json::json!({}));
+            }
             let instructions = instructions.clone();
-            let protocol_version = protocol_version.clone();
+            let protocol_version =
+                Self::negotiate_protocol_version(&protocol_version, &req.protocol_version);
 
             Box::pin(async move {
-                let mut state = state
-                    .write()
-                    .map_err(|_| anyhow::anyhow!("Lock poisoned"))?;
-                state.client_capabilities = Some(req.capabilities);
-                state.client_info = Some(req.client_info);
+                tracing::debug!(
+                    "Client requested protocol version {}, using {}",
+                    req.protocol_version,
+                    protocol_version
+                );
+                context.peer.update_info(|info| {
+                    info.protocol_version = Some(protocol_version.clone());
+                    info.client_info = Some(req.client_info);
+                    info.client_capabilities = Some(req.capabilities);
+                    info.server_info = Some(server_info.clone());
+                    info.server_capabilities = Some(capabilities.clone());
+                });
 
                 Ok(InitializeResponse {
                     protocol_version: protocol_version.as_str().to_string(),
@@ -253,109 +583,194 @@ impl ServerProtocolBuilder {
         }
     }
 
-    /// Helper function for creating an initialized notification handler.
-    ///
-    /// # Arguments
-    ///
-    /// * `state` - The client connection state
+    /// Builds the server protocol.
     ///
     /// # Returns
     ///
-    /// A handler function for initialized notifications
-    fn handle_initialized(
-        state: Arc<RwLock<ClientConnection>>,
-    ) -> impl Fn(()) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send>> {
-        move |_| {
-            let state = state.clone();
-            Box::pin(async move {
-                let mut state = state
-                    .write()
-                    .map_err(|_| anyhow::anyhow!("Lock poisoned"))?;
-                state.initialized = true;
-                Ok(())
-            })
-        }
-    }
+    /// A `Protocol` instance configured with the server's settings
+    pub fn build(mut self) -> Protocol {
+        let registry = self.tools;
+        let tools_list = registry.tools();
+        let tools_call = registry.tools();
+        let list_changed = self
+            .capabilities
+            .tools
+            .as_ref()
+            .and_then(|tools| tools.list_changed)
+            .unwrap_or(false);
 
-    /// Gets the client capabilities, if available.
-    ///
-    /// # Returns
-    ///
-    /// An `Option` containing the client capabilities if available
-    pub fn get_client_capabilities(&self) -> Option<ClientCapabilities> {
-        self.client_connection
-            .read()
-            .ok()?
-            .client_capabilities
-            .clone()
-    }
+        let has_resources = !self.resources.list_resources().is_empty()
+            || !self.resources.list_resource_templates().is_empty();
+        if self.capabilities.resources.is_none() && has_resources {
+            self.capabilities.resources = Some(ResourceCapabilities::default());
+        }
+        let resources_list_changed = self
+            .capabilities
+            .resources
+            .as_ref()
+            .and_then(|resources| resources.list_changed)
+            .unwrap_or(false);
+        let resources_subscribe = self
+            .capabilities
+            .resources
+            .as_ref()
+            .and_then(|resources| resources.subscribe)
+            .unwrap_or(false);
 
-    /// Gets the client information, if available.
-    ///
-    /// # Returns
-    ///
-    /// An `Option` containing the client information if available
-    pub fn get_client_info(&self) -> Option<Implementation> {
-        self.client_connection.read().ok()?.client_info.clone()
-    }
+        if self.capabilities.prompts.is_none() && !self.prompts.list_prompts().is_empty() {
+            self.capabilities.prompts = Some(PromptCapabilities {
+                list_changed: Some(true),
+            });
+        }
+        let prompts_list_changed = self
+            .capabilities
+            .prompts
+            .as_ref()
+            .and_then(|prompts| prompts.list_changed)
+            .unwrap_or(false);
 
-    /// Checks if the client has completed initialization.
-    ///
-    /// # Returns
-    ///
-    /// `true` if the client is initialized, `false` otherwise
-    pub fn is_initialized(&self) -> bool {
-        self.client_connection
-            .read()
-            .ok()
-            .map(|client_connection| client_connection.initialized)
-            .unwrap_or(false)
-    }
+        if self.capabilities.completions.is_none() && !self.completions.completions().is_empty() {
+            self.capabilities.completions = Some(serde_json::json!({}));
+        }
 
-    /// Builds the server protocol.
-    ///
-    /// # Returns
-    ///
-    /// A `Protocol` instance configured with the server's settings
-    pub fn build(self) -> Protocol {
-        let tools = Arc::new(Tools::new(self.tools));
-        let tools_clone = tools.clone();
-        let tools_list = tools.clone();
-        let tools_call = tools_clone.clone();
+        let mut protocol_builder = self.protocol_builder;
+        if self.capabilities.resources.is_some() {
+            let resources_list = self.resources.resources();
+            let resources_read = self.resources.resources();
+            let templates_list = self.resources.resources();
+            protocol_builder = protocol_builder
+                .request_handler("resources/list", move |_req: ListRequest| {
+                    let resources = resources_list.list_resources();
+                    Box::pin(async move {
+                        Ok(ResourcesListResponse {
+                            resources,
+                            next_cursor: None,
+                            meta: None,
+                        })
+                    })
+                })
+                .request_handler("resources/templates/list", move |_req: ListRequest| {
+                    let resource_templates = templates_list.list_resource_templates();
+                    Box::pin(async move {
+                        Ok(ResourceTemplatesListResponse {
+                            resource_templates,
+                            next_cursor: None,
+                            meta: None,
+                        })
+                    })
+                })
+                .request_handler_with_context(
+                    "resources/read",
+                    move |req: ReadResourceRequest, context| {
+                        let resources_read = resources_read.clone();
+                        Box::pin(
+                            async move { resources_read.read_resource(req.uri, context).await },
+                        )
+                    },
+                );
+        }
+        if resources_subscribe {
+            let resources = self.resources.resources();
+            protocol_builder = protocol_builder
+                .request_handler_with_context(
+                    "resources/subscribe",
+                    move |req: SubscribeRequest, context| {
+                        let known = resources.has_resource(req.uri.as_str());
+                        Box::pin(async move {
+                            if !known {
+                                return Err(McpError::resource_not_found(req.uri.as_str()).into());
+                            }
+                            context.peer.update_info(|info| {
+                                info.resource_subscriptions.insert(req.uri.to_string());
+                            });
+                            Ok(serde_json::json!({}))
+                        })
+                    },
+                )
+                .request_handler_with_context(
+                    "resources/unsubscribe",
+                    |req: SubscribeRequest, context| {
+                        Box::pin(async move {
+                            context.peer.update_info(|info| {
+                                info.resource_subscriptions.remove(req.uri.as_str());
+                            });
+                            Ok(serde_json::json!({}))
+                        })
+                    },
+                );
+        }
 
-        let conn_for_list = self.client_connection.clone();
-        let conn_for_call = self.client_connection.clone();
+        if self.capabilities.completions.is_some() {
+            let completions = self.completions.completions();
+            protocol_builder = protocol_builder.request_handler_with_context(
+                "completion/complete",
+                move |req: CompleteRequest, context| {
+                    let completions = completions.clone();
+                    Box::pin(async move { completions.complete(req, context).await })
+                },
+            );
+        }
+        if self.capabilities.logging.is_some() {
+            protocol_builder = protocol_builder
+                .request_handler_with_context("logging/setLevel", crate::logging::set_level);
+        }
+        if self.capabilities.prompts.is_some() {
+            let prompts_list = self.prompts.prompts();
+            let prompts_get = self.prompts.prompts();
+            protocol_builder = protocol_builder
+                .request_handler("prompts/list", move |_req: ListRequest| {
+                    let prompts = prompts_list.list_prompts();
+                    Box::pin(async move {
+                        Ok(PromptsListResponse {
+                            prompts,
+                            next_cursor: None,
+                            meta: None,
+                        })
+                    })
+                })
+                .request_handler_with_context(
+                    "prompts/get",
+                    move |req: GetPromptRequest, context| {
+                        let prompts_get = prompts_get.clone();
+                        Box::pin(async move {
+                            let version = context.protocol_version();
+                            let mut resp = prompts_get.render_prompt(req, context).await?;
+                            if let Some(version) = version {
+                                if !version.supports_audio_content() {
+                                    resp.messages.retain(|message| {
+                                        !matches!(message.content, PromptMessageContent::Audio(_))
+                                    });
+                                }
+                            }
+                            Ok(resp)
+                        })
+                    },
+                );
+        }
 
-        self.protocol_builder
-            .request_handler(
+        let protocol = protocol_builder
+            .request_handler_with_context(
                 "initialize",
                 Self::handle_init(
                     self.protocol_version.clone(),
-                    self.client_connection.clone(),
                     self.server_info,
                     self.capabilities,
                     self.instructions,
                 ),
             )
-            .notification_handler(
-                "notifications/initialized",
-                Self::handle_initialized(self.client_connection),
-            )
-            .request_handler("tools/list", move |_req: ListRequest| {
+            .request_handler_with_context("tools/list", move |_req: ListRequest, context| {
                 let tools_list = tools_list.clone();
-                let conn = conn_for_list.clone();
                 Box::pin(async move {
-                    match conn.read() {
-                        Ok(conn) => {
-                            if !conn.initialized {
-                                return Err(anyhow::anyhow!("Client not initialized"));
-                            }
+                    let mut tools = tools_list.list_tools();
+                    if let Some(version) = context.protocol_version() {
+                        if !version.supports_tool_annotations() {
+                            tools.iter_mut().for_each(|tool| tool.annotations = None);
+                        }
+                        if !version.supports_structured_output() {
+                            tools.iter_mut().for_each(|tool| tool.output_schema = None);
                         }
-                        Err(_) => return Err(anyhow::anyhow!("Lock poisoned")),
                     }
 
-                    let tools = tools_list.list_tools();
-
                     Ok(ToolsListResponse {
                         tools,
                         next_cursor: None,
@@ -363,25 +778,650 @@ impl ServerProtocolBuilder {
                     })
                 })
             })
-            .request_handler("tools/call", move |req: CallToolRequest| {
+            .request_handler_with_context("tools/call", move |req: CallToolRequest, context| {
                 let tools_call = tools_call.clone();
-                let conn = conn_for_call.clone();
                 Box::pin(async move {
-                    match conn.read() {
-                        Ok(conn) => {
-                            if !conn.initialized {
-                                return Err(anyhow::anyhow!("Client not initialized"));
-                            }
+                    let version = context.protocol_version();
+                    let mut resp = tools_call.call_tool(req, context).await?;
+                    if let Some(version) = version {
+                        if !version.supports_audio_content() {
+                            resp.content.retain(|content| {
+                                !matches!(content, ToolResponseContent::Audio(_))
+                            });
+                        }
+                        // Older clients get the text fallback only
+                        if !version.supports_structured_output() {
+                            resp.structured_content = None;
                         }
-                        Err(_) => return Err(anyhow::anyhow!("Lock poisoned")),
                     }
+                    Ok(resp)
+                })
+            })
+            .build();
+
+        if list_changed {
+            registry.on_change(Self::notify_list_changed(
+                &protocol,
+                "notifications/tools/list_changed",
+            ));
+        }
+        if resources_list_changed {
+            self.resources.on_change(Self::notify_list_changed(
+                &protocol,
+                "notifications/resources/list_changed",
+            ));
+        }
+        if prompts_list_changed {
+            self.prompts.on_change(Self::notify_list_changed(
+                &protocol,
+                "notifications/prompts/list_changed",
+            ));
+        }
+        if resources_subscribe {
+            self.resources
+                .on_change(Self::prune_subscriptions(&protocol, &self.resources));
+        }
+
+        protocol
+    }
+
+    /// Creates a change listener dropping the subscriptions of every session
+    /// to resources that can no longer be read.
+    fn prune_subscriptions(
+        protocol: &Protocol,
+        registry: &ResourceRegistry,
+    ) -> impl Fn() + Send + Sync + 'static {
+        let protocol = protocol.clone();
+        let resources = registry.resources();
+        move || {
+            protocol.update_peer_infos(|info| {
+                info.resource_subscriptions
+                    .retain(|uri| resources.has_resource(uri));
+            });
+        }
+    }
+
+    /// Creates a change listener broadcasting a list changed notification to
+    /// initialized sessions.
+    fn notify_list_changed(
+        protocol: &Protocol,
+        method: &'static str,
+    ) -> impl Fn() + Send + Sync + 'static {
+        let notifier = protocol.clone();
+        move || {
+            let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+                return;
+            };
+            let protocol = notifier.clone();
+            runtime.spawn(async move {
+                protocol.broadcast_notification(method, None).await;
+            });
+        }
+    }
+}
+
+/// Metrics that record calls to unregistered tools with the tool `other`.
+struct RegisteredToolMetrics {
+    metrics: Arc<dyn Metrics>,
+    tools: Arc<Tools>,
+}
+
+impl Metrics for RegisteredToolMetrics {
+    fn request_started(&self, method: &str) {
+        self.metrics.request_started(method);
+    }
+
+    fn request_finished(
+        &self,
+        method: &str,
+        tool: Option<&str>,
+        duration: std::time::Duration,
+        error_code: Option<i32>,
+    ) {
+        let tool = tool.map(|tool| {
+            if self.tools.has_tool(tool) {
+                tool
+            } else {
+                "other"
+            }
+        });
+        self.metrics
+            .request_finished(method, tool, duration, error_code);
+    }
+
+    fn session_opened(&self) {
+        self.metrics.session_opened();
+    }
+
+    fn session_closed(&self) {
+        self.metrics.session_closed();
+    }
+
+    fn sse_message_queued(&self) {
+        self.metrics.sse_message_queued();
+    }
+
+    fn sse_messages_dequeued(&self, count: usize) {
+        self.metrics.sse_messages_dequeued(count);
+    }
+
+    fn render(&self) -> Option<String> {
+        self.metrics.render()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{LifecycleState, Peer};
+    use crate::transport::{to_raw_value, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse};
+    use crate::types::ErrorCode;
+    use serde_json::json;
+
+    fn builder() -> ServerProtocolBuilder {
+        Server::builder(
+            "server".to_string(),
+            "1.0".to_string(),
+            ProtocolVersion::V2025_03_26,
+        )
+    }
+
+    fn request(method: &str, params: serde_json::Value) -> JsonRpcRequest {
+        JsonRpcRequest {
+            id: 1,
+            method: method.to_string(),
+            params: Some(to_raw_value(&params).unwrap()),
+            jsonrpc: Default::default(),
+        }
+    }
+
+    fn notification(method: &str) -> JsonRpcNotification {
+        JsonRpcNotification {
+            method: method.to_string(),
+            params: None,
+            jsonrpc: Default::default(),
+        }
+    }
+
+    fn initialize(name: &str) -> JsonRpcRequest {
+        request(
+            "initialize",
+            json!({
+                "protocolVersion": "2025-03-26",
+                "capabilities": {},
+                "clientInfo": { "name": name, "version": "1.0" },
+            }),
+        )
+    }
+
+    /// Returns a peer whose session went through initialization.
+    fn initialized_peer(protocol: &Protocol, session_id: Option<&str>) -> Peer {
+        let peer = Peer::detached(protocol.clone(), session_id.map(str::to_string));
+        peer.update_info(|info| info.state = LifecycleState::Initialized);
+        peer
+    }
+
+    fn result<T: serde::de::DeserializeOwned>(response: JsonRpcResponse) -> T {
+        serde_json::from_str(response.result.unwrap().get()).unwrap()
+    }
+
+    fn error_code(response: JsonRpcResponse) -> Option<i32> {
+        response.error.map(|e| e.code)
+    }
+
+    #[tokio::test]
+    async fn test_session_lifecycle() {
+        let protocol = builder().build();
+        let peer = |id: &str| Peer::detached(protocol.clone(), Some(id.to_string()));
+
+        for name in ["a", "b"] {
+            let response = protocol
+                .handle_request_from(initialize(name), peer(name))
+                .await;
+            assert!(response.error.is_none());
+        }
+        protocol
+            .handle_notification_from(notification("notifications/initialized"), peer("a"))
+            .await;
+
+        let response = protocol
+            .handle_request_from(request("tools/list", json!({})), peer("a"))
+            .await;
+        assert!(response.error.is_none());
+        let response = protocol
+            .handle_request_from(request("tools/list", json!({})), peer("b"))
+            .await;
+        assert!(response.error.is_some());
 
-                    match tools_call.call_tool(req).await {
-                        Ok(resp) => Ok(resp),
-                        Err(e) => Err(e),
+        // Only ping is accepted before initialize, and initialize only once
+        let response = protocol
+            .handle_request_from(request("ping", json!({})), peer("c"))
+            .await;
+        assert!(response.error.is_none());
+        let response = protocol
+            .handle_request_from(request("tools/list", json!({})), peer("c"))
+            .await;
+        assert!(response.error.is_some());
+        let response = protocol
+            .handle_request_from(initialize("a"), peer("a"))
+            .await;
+        assert_eq!(error_code(response), Some(ErrorCode::InvalidRequest as i32));
+
+        let sessions = protocol.sessions();
+        assert_eq!(sessions.len(), 2);
+        let info = sessions.get(Some("b")).unwrap();
+        assert_eq!(info.state, LifecycleState::Initializing);
+        assert_eq!(info.client_info.unwrap().name, "b");
+    }
+
+    #[tokio::test]
+    async fn test_closed_session() {
+        let protocol = builder().build();
+        let peer = initialized_peer(&protocol, Some("a"));
+        protocol.close_session(Some("a"));
+        assert_eq!(peer.info().state, LifecycleState::Closed);
+
+        for method in ["ping", "initialize", "tools/list"] {
+            let response = protocol
+                .handle_request_from(request(method, json!({})), peer.clone())
+                .await;
+            assert_eq!(error_code(response), Some(ErrorCode::InvalidRequest as i32));
+        }
+        protocol
+            .handle_notification_from(
+                notification("notifications/roots/list_changed"),
+                peer.clone(),
+            )
+            .await;
+        assert_eq!(peer.info().roots_changes, 0);
+    }
+
+    #[tokio::test]
+    async fn test_resources() {
+        let resource = |uri: &str| Resource {
+            uri: uri.parse().unwrap(),
+            name: uri.to_string(),
+            description: None,
+            mime_type: Some("text/plain".to_string()),
+            annotations: None,
+            size: None,
+        };
+        let builder = builder()
+            .register_static_resource(
+                resource("file:///a.txt"),
+                ResourceContents::text("file:///a.txt".parse().unwrap(), "a"),
+            )
+            .register_resource(resource("file:///b.bin"), |uri, _| {
+                Box::pin(async move { Ok(vec![ResourceContents::blob(uri, [0xff, 0x00])]) })
+            })
+            .register_resource_template(
+                ResourceTemplate {
+                    uri_template: "db://{table}/{id}".to_string(),
+                    name: "row".to_string(),
+                    description: None,
+                    mime_type: None,
+                    annotations: None,
+                },
+                |uri, vars, _| {
+                    let text = format!("{}#{}", vars["table"], vars["id"]);
+                    Box::pin(async move { Ok(vec![ResourceContents::text(uri, text)]) })
+                },
+            )
+            .unwrap();
+        let registry = builder.resource_registry();
+        let protocol = builder.build();
+        let response = protocol
+            .handle_request_from(initialize("client"), Peer::detached(protocol.clone(), None))
+            .await;
+        let initialized: InitializeResponse = result(response);
+        assert!(initialized.capabilities.resources.is_some());
+        let peer = initialized_peer(&protocol, None);
+
+        let response = protocol
+            .handle_request_from(request("resources/list", json!({})), peer.clone())
+            .await;
+        let listed: ResourcesListResponse = result(response);
+        assert_eq!(listed.resources.len(), 2);
+
+        let read = |uri: &str| {
+            let request = request("resources/read", json!({ "uri": uri }));
+            protocol.handle_request_from(request, peer.clone())
+        };
+        let contents: crate::types::ReadResourceResponse = result(read("file:///b.bin").await);
+        assert_eq!(contents.contents[0].blob.as_deref(), Some("/wA="));
+        assert_eq!(
+            contents.contents[0].mime_type.as_deref(),
+            Some("text/plain")
+        );
+
+        let contents: crate::types::ReadResourceResponse = result(read("db://users/42").await);
+        assert_eq!(contents.contents[0].text.as_deref(), Some("users#42"));
+
+        assert_eq!(
+            error_code(read("file:///missing").await),
+            Some(ErrorCode::ResourceNotFound as i32)
+        );
+
+        // Removal normalizes the URI like registration does
+        assert!(!registry.remove_resource("not a uri"));
+        assert!(registry.remove_resource("FILE:///a.txt"));
+        assert_eq!(
+            error_code(read("file:///a.txt").await),
+            Some(ErrorCode::ResourceNotFound as i32)
+        );
+
+        assert!(!registry.remove_resource_template("db://{table"));
+        assert!(registry.remove_resource_template("db://{table}/{id}"));
+        assert!(registry.list_resource_templates().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prompts() {
+        let prompt = Prompt {
+            name: "greet".to_string(),
+            description: Some("Greet someone".to_string()),
+            arguments: Some(vec![crate::types::PromptArgument {
+                name: "name".to_string(),
+                description: None,
+                required: Some(true),
+            }]),
+        };
+        let protocol = builder()
+            .register_prompt(prompt, |arguments, _| {
+                Box::pin(async move {
+                    Ok(vec![PromptMessage {
+                        role: crate::types::Role::User,
+                        content: PromptMessageContent::Text(crate::types::TextContent {
+                            content_type: "text".to_string(),
+                            text: format!("Hello, {}!", arguments["name"]),
+                            annotations: None,
+                        }),
+                    }])
+                })
+            })
+            .build();
+        let peer = initialized_peer(&protocol, None);
+
+        let response = protocol
+            .handle_request_from(request("prompts/list", json!({})), peer.clone())
+            .await;
+        let listed: PromptsListResponse = result(response);
+        assert_eq!(listed.prompts[0].name, "greet");
+
+        let params = json!({ "name": "greet", "arguments": { "name": "Ada" } });
+        let response = protocol
+            .handle_request_from(request("prompts/get", params), peer.clone())
+            .await;
+        let prompt: crate::types::GetPromptResponse = result(response);
+        assert!(matches!(
+            &prompt.messages[0].content,
+            PromptMessageContent::Text(text) if text.text == "Hello, Ada!"
+        ));
+
+        let response = protocol
+            .handle_request_from(request("prompts/get", json!({ "name": "greet" })), peer)
+            .await;
+        assert_eq!(error_code(response), Some(ErrorCode::InvalidParams as i32));
+    }
+
+    #[tokio::test]
+    async fn test_completions() {
+        let protocol = builder()
+            .register_prompt_completion("review", "branch", |value, _| {
+                Box::pin(async move {
+                    let values = (0..150)
+                        .map(|i| format!("feature-{}", i))
+                        .filter(|branch| branch.starts_with(&value))
+                        .collect();
+                    Ok(Completion {
+                        values,
+                        ..Default::default()
+                    })
+                })
+            })
+            .register_resource_completion("db://{table}/{id}", "table", |_, _| {
+                Box::pin(async move {
+                    Ok(Completion {
+                        values: vec!["users".to_string()],
+                        total: Some(1),
+                        has_more: Some(false),
+                    })
+                })
+            })
+            .unwrap()
+            .build();
+        let peer = initialized_peer(&protocol, None);
+        let complete = |reference: serde_json::Value, name: &str, value: &str| {
+            let request = request(
+                "completion/complete",
+                json!({
+                    "ref": reference,
+                    "argument": { "name": name, "value": value },
+                }),
+            );
+            let response = protocol.handle_request_from(request, peer.clone());
+            async move { result::<crate::types::CompleteResponse>(response.await).completion }
+        };
+
+        let prompt = json!({ "type": "ref/prompt", "name": "review" });
+        let completion = complete(prompt.clone(), "branch", "feature-1").await;
+        assert_eq!(completion.values.len(), 61);
+        assert_eq!(completion.has_more, None);
+
+        let completion = complete(prompt, "branch", "").await;
+        assert_eq!(completion.values.len(), 100);
+        assert_eq!(completion.total, Some(150));
+        assert_eq!(completion.has_more, Some(true));
+
+        let resource = json!({ "type": "ref/resource", "uri": "db://{table}/{id}" });
+        let completion = complete(resource.clone(), "table", "u").await;
+        assert_eq!(completion.values, vec!["users"]);
+
+        let completion = complete(resource, "id", "").await;
+        assert!(completion.values.is_empty());
+
+        assert!(builder()
+            .register_resource_completion("db://{table}", "id", |_, _| {
+                Box::pin(async move { Ok(Completion::default()) })
+            })
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_structured_tool_output() {
+        let tool = |name: &str| Tool {
+            name: name.to_string(),
+            description: None,
+            input_schema: json!({ "type": "object" }),
+            output_schema: Some(json!({
+                "type": "object",
+                "properties": { "temperature": { "type": "number" } },
+                "required": ["temperature"],
+            })),
+            annotations: None,
+        };
+        let protocol = builder()
+            .register_tool(tool("weather"), |_| {
+                Box::pin(async move {
+                    CallToolResponse {
+                        content: vec![],
+                        structured_content: Some(json!({ "temperature": 21.5 })),
+                        is_error: None,
+                        meta: None,
                     }
                 })
             })
-            .build()
+            .register_tool(tool("broken"), |_| {
+                Box::pin(async move {
+                    crate::tool_structured_response!(json!({ "temperature": "warm" })).unwrap()
+                })
+            })
+            .build();
+        let call = |name: &str, version: ProtocolVersion| {
+            let peer = initialized_peer(&protocol, None);
+            peer.update_info(|info| info.protocol_version = Some(version));
+            protocol.handle_request_from(request("tools/call", json!({ "name": name })), peer)
+        };
+
+        let called: CallToolResponse = result(call("weather", ProtocolVersion::V2025_06_18).await);
+        assert_eq!(
+            called.structured_content,
+            Some(json!({ "temperature": 21.5 }))
+        );
+        assert!(matches!(
+            &called.content[..],
+            [ToolResponseContent::Text(text)] if text.text == r#"{"temperature":21.5}"#
+        ));
+
+        let called: CallToolResponse = result(call("weather", ProtocolVersion::V2025_03_26).await);
+        assert_eq!(called.structured_content, None);
+        assert_eq!(called.content.len(), 1);
+
+        assert_eq!(
+            error_code(call("broken", ProtocolVersion::V2025_06_18).await),
+            Some(ErrorCode::InternalError as i32)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_metric_labels() {
+        #[derive(Default)]
+        struct Labels(std::sync::Mutex<Vec<(String, Option<String>)>>);
+
+        impl Metrics for Labels {
+            fn request_finished(
+                &self,
+                method: &str,
+                tool: Option<&str>,
+                _duration: std::time::Duration,
+                _error_code: Option<i32>,
+            ) {
+                let label = (method.to_string(), tool.map(str::to_string));
+                self.0.lock().unwrap().push(label);
+            }
+        }
+
+        let labels = Arc::new(Labels::default());
+        let tool = Tool {
+            name: "echo".to_string(),
+            description: None,
+            input_schema: json!({ "type": "object" }),
+            output_schema: None,
+            annotations: None,
+        };
+        let protocol = builder()
+            .metrics(labels.clone())
+            .register_tool(tool, |_| {
+                Box::pin(async move { crate::tool_text_response!("echo") })
+            })
+            .build();
+        for request in [
+            request("tools/call", json!({ "name": "echo" })),
+            request("tools/call", json!({ "name": "missing" })),
+            request("made/up", json!({ "name": "echo" })),
+        ] {
+            let peer = initialized_peer(&protocol, None);
+            protocol.handle_request_from(request, peer).await;
+        }
+
+        let label = |method: &str, tool: &str| (method.to_string(), Some(tool.to_string()));
+        assert_eq!(
+            *labels.0.lock().unwrap(),
+            vec![
+                label("tools/call", "echo"),
+                label("tools/call", "other"),
+                ("other".to_string(), None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_logging_level() {
+        let protocol = builder()
+            .set_capabilities(ServerCapabilities {
+                logging: Some(json!({})),
+                ..Default::default()
+            })
+            .build();
+        let peer = initialized_peer(&protocol, None);
+        assert_eq!(peer.info().logging_level, None);
+
+        let response = protocol
+            .handle_request_from(
+                request("logging/setLevel", json!({ "level": "warning" })),
+                peer.clone(),
+            )
+            .await;
+        assert!(response.error.is_none());
+        assert_eq!(
+            peer.info().logging_level,
+            Some(crate::types::LoggingLevel::Warning)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resource_subscriptions() {
+        let builder = builder()
+            .set_capabilities(ServerCapabilities {
+                resources: Some(ResourceCapabilities {
+                    subscribe: Some(true),
+                    list_changed: None,
+                }),
+                ..Default::default()
+            })
+            .register_static_resource(
+                Resource {
+                    uri: "file:///build.log".parse().unwrap(),
+                    name: "build log".to_string(),
+                    description: None,
+                    mime_type: None,
+                    annotations: None,
+                    size: None,
+                },
+                ResourceContents::text("file:///build.log".parse().unwrap(), ""),
+            );
+        let registry = builder.resource_registry();
+        let protocol = builder.build();
+        let peer = initialized_peer(&protocol, Some("a"));
+        let send = |method: &str, uri: &str| {
+            protocol.handle_request_from(request(method, json!({ "uri": uri })), peer.clone())
+        };
+
+        let response = send("resources/subscribe", "file:///build.log").await;
+        assert!(response.error.is_none());
+        assert!(peer
+            .info()
+            .resource_subscriptions
+            .contains("file:///build.log"));
+
+        assert_eq!(
+            error_code(send("resources/subscribe", "file:///missing").await),
+            Some(ErrorCode::ResourceNotFound as i32)
+        );
+
+        send("resources/unsubscribe", "file:///build.log").await;
+        assert!(peer.info().resource_subscriptions.is_empty());
+
+        // Subscriptions do not outlive the resource
+        send("resources/subscribe", "file:///build.log").await;
+        assert!(registry.remove_resource("file:///build.log"));
+        assert!(peer.info().resource_subscriptions.is_empty());
+    }
+
+    #[test]
+    fn test_negotiate_protocol_version() {
+        let negotiate = ServerProtocolBuilder::negotiate_protocol_version;
+        assert_eq!(
+            negotiate(&ProtocolVersion::V2025_03_26, "2024-11-05"),
+            ProtocolVersion::V2024_11_05
+        );
+        assert_eq!(
+            negotiate(&ProtocolVersion::V2025_03_26, "1999-01-01"),
+            ProtocolVersion::V2025_03_26
+        );
+        assert_eq!(
+            negotiate(&ProtocolVersion::V2024_11_05, "2025-03-26"),
+            ProtocolVersion::V2024_11_05
+        );
     }
 }
@@ -4,25 +4,34 @@
 //! MCP tools. Tools are the primary way for clients to interact with server capabilities.
 //!
 //! The module implements a registry for tools and handlers that process tool invocations.
+//! The registry can be changed while the server is running, e.g. when tools are
+//! provided by plugins that are loaded and unloaded at runtime.
 
-use crate::types::{CallToolRequest, CallToolResponse, Tool};
+use crate::change_notifier::ChangeNotifier;
+use crate::error::McpError;
+use crate::protocol::RequestContext;
+use crate::schema;
+use crate::types::{CallToolRequest, CallToolResponse, TextContent, Tool, ToolResponseContent};
 use anyhow::Result;
 use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::{Arc, PoisonError, RwLock};
 
 /// Registry and dispatcher for MCP tools.
 ///
 /// The `Tools` struct manages a collection of tools and their associated handlers,
 /// providing methods to register, list, and invoke tools.
 pub struct Tools {
-    tool_handlers: HashMap<String, ToolHandler>,
+    tool_handlers: RwLock<HashMap<String, Arc<ToolHandler>>>,
 }
 
 impl Tools {
-    /// Creates a new tool registry with the given tool handlers.
-    pub(crate) fn new(map: HashMap<String, ToolHandler>) -> Self {
-        Self { tool_handlers: map }
+    /// Creates a new, empty tool registry.
+    pub(crate) fn new() -> Self {
+        Self {
+            tool_handlers: RwLock::new(HashMap::new()),
+        }
     }
 
     /// Retrieves a tool definition by name.
@@ -36,27 +45,72 @@ impl Tools {
     /// An `Option` containing the tool if found, or `None` if not found.
     pub fn get_tool(&self, name: &str) -> Option<Tool> {
         self.tool_handlers
+            .read()
+            .ok()?
             .get(name)
             .map(|tool_handler| tool_handler.tool.clone())
     }
 
+    /// Returns whether a tool is registered under a name.
+    pub(crate) fn has_tool(&self, name: &str) -> bool {
+        self.tool_handlers
+            .read()
+            .is_ok_and(|tool_handlers| tool_handlers.contains_key(name))
+    }
+
     /// Invokes a tool with the given request.
     ///
     /// # Arguments
     ///
     /// * `req` - The request containing the tool name and arguments
+    /// * `context` - The context of the `tools/call` request
     ///
     /// # Returns
     ///
-    /// A `Result` containing the tool response if successful, or an error if
-    /// the tool is not found or the invocation fails.
-    pub async fn call_tool(&self, req: CallToolRequest) -> Result<CallToolResponse> {
+    /// A `Result` containing the tool response if successful, or an invalid
+    /// params error if the tool is not found. When the tool declares an output
+    /// schema, a successful response without matching structured content is
+    /// an internal error. Structured content is repeated as text content when
+    /// the tool returned no content.
+    pub async fn call_tool(
+        &self,
+        req: CallToolRequest,
+        context: RequestContext,
+    ) -> Result<CallToolResponse> {
         let handler = self
             .tool_handlers
-            .get(&req.name)
-            .ok_or_else(|| anyhow::anyhow!("Tool not found: {}", req.name))?;
+            .read()
+            .ok()
+            .and_then(|tool_handlers| tool_handlers.get(&req.name).cloned())
+            .ok_or_else(|| McpError::invalid_params(format!("Tool not found: {}", req.name)))?;
 
-        Ok((handler.f)(req).await)
+        let mut response = (handler.f)(req, context).await;
+        if let Some(output_schema) = &handler.tool.output_schema {
+            if response.is_error != Some(true) {
+                let content = response.structured_content.as_ref().ok_or_else(|| {
+                    McpError::internal(format!(
+                        "Tool {} returned no structured content",
+                        handler.tool.name
+                    ))
+                })?;
+                schema::validate(output_schema, content).map_err(|e| {
+                    McpError::internal(format!(
+                        "Structured content of tool {} does not match its output schema: {}",
+                        handler.tool.name, e
+                    ))
+                })?;
+            }
+        }
+        if let (Some(content), true) = (&response.structured_content, response.content.is_empty()) {
+            response
+                .content
+                .push(ToolResponseContent::Text(TextContent {
+                    content_type: "text".to_string(),
+                    text: content.to_string(),
+                    annotations: None,
+                }));
+        }
+        Ok(response)
     }
 
     /// Lists all registered tools.
@@ -66,9 +120,190 @@ impl Tools {
     /// A vector containing all registered tools.
     pub fn list_tools(&self) -> Vec<Tool> {
         self.tool_handlers
-            .values()
-            .map(|tool_handler| tool_handler.tool.clone())
-            .collect()
+            .read()
+            .map(|tool_handlers| {
+                tool_handlers
+                    .values()
+                    .map(|tool_handler| tool_handler.tool.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// A handle for changing the tools of a server while it is running.
+///
+/// Clones of a `ToolRegistry` share the same tools. Every change is applied
+/// atomically: a concurrent `tools/list` sees the tools either before or after
+/// the change. After each change, the registered change listeners are called;
+/// the server uses this to send `notifications/tools/list_changed` when its
+/// `ToolCapabilities.list_changed` is set.
+///
+/// # Examples
+///
+/// ```
+/// use mcp_core::server::Server;
+/// use mcp_core::tool_text_response;
+/// use mcp_core::types::Tool;
+/// use serde_json::json;
+///
+/// let builder = Server::builder(
+///     "plugins".to_string(),
+///     "1.0".to_string(),
+///     mcp_core::types::ProtocolVersion::V2025_03_26,
+/// );
+/// let registry = builder.tool_registry();
+/// let protocol = builder.build();
+///
+/// // Later, once a plugin is loaded
+/// let tool = Tool {
+///     name: "hello".to_string(),
+///     description: None,
+///     input_schema: json!({ "type": "object" }),
+///     output_schema: None,
+///     annotations: None,
+/// };
+/// registry.add_tool(tool, |_| Box::pin(async move { tool_text_response!("Hello") }));
+/// assert!(registry.remove_tool("hello"));
+/// ```
+#[derive(Clone)]
+pub struct ToolRegistry {
+    tools: Arc<Tools>,
+    changes: ChangeNotifier,
+}
+
+impl ToolRegistry {
+    /// Creates a new, empty tool registry.
+    ///
+    /// # Returns
+    ///
+    /// A new `ToolRegistry` instance
+    pub fn new() -> Self {
+        Self {
+            tools: Arc::new(Tools::new()),
+            changes: ChangeNotifier::default(),
+        }
+    }
+
+    /// Returns the tools of the registry, for listing and invoking them.
+    pub fn tools(&self) -> Arc<Tools> {
+        self.tools.clone()
+    }
+
+    /// Adds a tool, replacing any tool with the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `tool` - The tool definition
+    /// * `f` - The handler function for the tool
+    pub fn add_tool(&self, tool: Tool, f: ToolHandlerFn) {
+        self.insert(tool, Box::new(move |req, _context| f(req)));
+    }
+
+    /// Adds a tool whose handler receives the request context, replacing any
+    /// tool with the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `tool` - The tool definition
+    /// * `f` - The handler function for the tool
+    pub fn add_tool_with_context(
+        &self,
+        tool: Tool,
+        f: impl Fn(
+                CallToolRequest,
+                RequestContext,
+            ) -> Pin<Box<dyn Future<Output = CallToolResponse> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.insert(tool, Box::new(f));
+    }
+
+    /// Removes a tool.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the tool to remove
+    ///
+    /// # Returns
+    ///
+    /// `true` if the tool was removed, `false` if there was no such tool
+    pub fn remove_tool(&self, name: &str) -> bool {
+        let removed = self
+            .tools
+            .tool_handlers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(name)
+            .is_some();
+        if removed {
+            self.changes.notify();
+        }
+        removed
+    }
+
+    /// Keeps only the tools for which the predicate returns `true`.
+    ///
+    /// All tools are removed at once, with a single change notification.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The predicate, called with each tool definition
+    ///
+    /// # Returns
+    ///
+    /// The number of removed tools
+    pub fn retain(&self, mut f: impl FnMut(&Tool) -> bool) -> usize {
+        let removed = {
+            let mut tool_handlers = self
+                .tools
+                .tool_handlers
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+            let before = tool_handlers.len();
+            tool_handlers.retain(|_, tool_handler| f(&tool_handler.tool));
+            before - tool_handlers.len()
+        };
+        if removed > 0 {
+            self.changes.notify();
+        }
+        removed
+    }
+
+    /// Retrieves a tool definition by name.
+    pub fn get_tool(&self, name: &str) -> Option<Tool> {
+        self.tools.get_tool(name)
+    }
+
+    /// Lists all registered tools.
+    pub fn list_tools(&self) -> Vec<Tool> {
+        self.tools.list_tools()
+    }
+
+    /// Registers a function called after every change to the tools.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The function to call
+    pub fn on_change(&self, f: impl Fn() + Send + Sync + 'static) {
+        self.changes.on_change(f);
+    }
+
+    fn insert(&self, tool: Tool, f: Box<ToolHandlerWithContextFn>) {
+        self.tools
+            .tool_handlers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(tool.name.clone(), Arc::new(ToolHandler { tool, f }));
+        self.changes.notify();
+    }
+}
+
+impl Default for ToolRegistry {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -79,6 +314,15 @@ impl Tools {
 pub type ToolHandlerFn =
     fn(CallToolRequest) -> Pin<Box<dyn Future<Output = CallToolResponse> + Send>>;
 
+/// Type alias for a tool handler that receives the request context.
+///
+/// Unlike `ToolHandlerFn`, the handler can be a closure and gets access to the
+/// `RequestContext` of the `tools/call` request, e.g. to notify the caller or
+/// to stop early when the request is cancelled.
+pub type ToolHandlerWithContextFn = dyn Fn(CallToolRequest, RequestContext) -> Pin<Box<dyn Future<Output = CallToolResponse> + Send>>
+    + Send
+    + Sync;
+
 /// Container for a tool definition and its handler function.
 ///
 /// The `ToolHandler` struct couples a tool definition with the function
@@ -87,5 +331,5 @@ pub(crate) struct ToolHandler {
     /// The tool definition (name, description, parameters, etc.)
     pub tool: Tool,
     /// The handler function that implements the tool
-    pub f: Box<ToolHandlerFn>,
+    pub f: Box<ToolHandlerWithContextFn>,
 }
@@ -0,0 +1,233 @@
+//! # Trace Context Propagation
+//!
+//! This module propagates [W3C Trace Context](https://www.w3.org/TR/trace-context/)
+//! through the `_meta.traceparent` field of MCP requests, so that a client call
+//! and the server's handling of it belong to the same trace.
+//!
+//! Outgoing requests carry a `traceparent` that continues the trace of the request
+//! being handled, if any, or starts a new trace. Incoming requests are handled
+//! within the trace of their `traceparent`. The trace and span IDs are recorded
+//! on the `tracing` spans of the protocol, so that logs of both sides can be
+//! correlated, and exporters such as OpenTelemetry can link them.
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+
+tokio::task_local! {
+    static CURRENT: TraceParent;
+}
+
+/// A W3C `traceparent`, identifying a span within a trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceParent {
+    /// The trace ID, as 32 lowercase hex digits
+    pub trace_id: String,
+    /// The span ID, as 16 lowercase hex digits
+    pub span_id: String,
+    /// The trace flags, e.g. `1` when the trace is sampled
+    pub flags: u8,
+}
+
+impl TraceParent {
+    /// Starts a new, sampled trace.
+    ///
+    /// # Returns
+    ///
+    /// A `TraceParent` with a random trace ID and span ID
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: format!("{:016x}{:016x}", random_u64(), random_u64()),
+            span_id: format!("{:016x}", random_u64()),
+            flags: 1,
+        }
+    }
+
+    /// Creates a new span within the same trace.
+    ///
+    /// # Returns
+    ///
+    /// A `TraceParent` with the same trace ID and a new span ID
+    pub fn child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id.clone(),
+            span_id: format!("{:016x}", random_u64()),
+            flags: self.flags,
+        }
+    }
+
+    /// Parses a `traceparent` header value.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value, e.g. `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`
+    ///
+    /// # Returns
+    ///
+    /// The `TraceParent`, or `None` if the value is not a valid version 00 `traceparent`
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let (version, trace_id, span_id, flags) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        let is_hex = |s: &str, len: usize| {
+            s.len() == len && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        };
+        if parts.next().is_some()
+            || version != "00"
+            || !is_hex(trace_id, 32)
+            || !is_hex(span_id, 16)
+            || !is_hex(flags, 2)
+            || trace_id.bytes().all(|b| b == b'0')
+            || span_id.bytes().all(|b| b == b'0')
+        {
+            return None;
+        }
+        Some(Self {
+            trace_id: trace_id.to_string(),
+            span_id: span_id.to_string(),
+            flags: u8::from_str_radix(flags, 16).ok()?,
+        })
+    }
+
+    /// Reads the `traceparent` of request params.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The params of a request
+    ///
+    /// # Returns
+    ///
+    /// The `TraceParent` in `_meta.traceparent`, if any
+    pub fn from_params(params: Option<&Value>) -> Option<Self> {
+        Self::from_meta(params?.get("_meta"))
+    }
+
+    /// Reads the `traceparent` of request metadata.
+    ///
+    /// # Arguments
+    ///
+    /// * `meta` - The `_meta` of a request
+    ///
+    /// # Returns
+    ///
+    /// The `TraceParent` in `traceparent`, if any
+    pub fn from_meta(meta: Option<&Value>) -> Option<Self> {
+        meta?.get("traceparent")?.as_str().and_then(Self::parse)
+    }
+}
+
+impl fmt::Display for TraceParent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{}-{}-{:02x}",
+            self.trace_id, self.span_id, self.flags
+        )
+    }
+}
+
+/// Returns the trace context of the request being handled by the current task.
+pub fn current() -> Option<TraceParent> {
+    CURRENT.try_with(TraceParent::clone).ok()
+}
+
+/// Runs a future within a trace context.
+///
+/// Requests sent by the future carry a `traceparent` continuing this context.
+///
+/// # Arguments
+///
+/// * `trace_parent` - The trace context
+/// * `f` - The future to run
+///
+/// # Returns
+///
+/// The output of the future
+pub async fn scope<F: Future>(trace_parent: TraceParent, f: F) -> F::Output {
+    CURRENT.scope(trace_parent, f).await
+}
+
+/// Adds a `traceparent` to the `_meta` of outgoing request params.
+///
+/// The `traceparent` continues the current trace context, or starts a new
+/// trace. Params that already carry a `traceparent` are left untouched, as are
+/// params that are not an object.
+///
+/// # Arguments
+///
+/// * `params` - The params of the request
+///
+/// # Returns
+///
+/// The params with the `traceparent`, and the trace context of the request
+pub fn inject(params: Option<Value>) -> (Option<Value>, Option<TraceParent>) {
+    if let Some(existing) = TraceParent::from_params(params.as_ref()) {
+        return (params, Some(existing));
+    }
+    let mut params = params.unwrap_or_else(|| Value::Object(Default::default()));
+    let Some(object) = params.as_object_mut() else {
+        return (Some(params), None);
+    };
+    let trace_parent = current()
+        .map(|current| current.child())
+        .unwrap_or_else(TraceParent::new_root);
+    let meta = object
+        .entry("_meta")
+        .or_insert_with(|| Value::Object(Default::default()));
+    match meta.as_object_mut() {
+        Some(meta) => {
+            meta.insert(
+                "traceparent".to_string(),
+                Value::String(trace_parent.to_string()),
+            );
+            (Some(params), Some(trace_parent))
+        }
+        None => (Some(params), None),
+    }
+}
+
+fn random_u64() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    // Zero IDs are invalid
+    hasher.finish().max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_inject_continues_current_trace() {
+        let (params, root) = inject(None);
+        let root = root.unwrap();
+        assert_eq!(
+            TraceParent::from_params(params.as_ref()),
+            Some(root.clone())
+        );
+
+        let (params, child) = scope(root.clone(), async { inject(Some(json!({ "a": 1 }))) }).await;
+        let child = child.unwrap();
+        assert_eq!(child.trace_id, root.trace_id);
+        assert_ne!(child.span_id, root.span_id);
+        assert_eq!(params.unwrap()["a"], 1);
+
+        assert!(TraceParent::parse(&root.to_string()).is_some());
+        assert!(
+            TraceParent::parse("00-00000000000000000000000000000000-00f067aa0ba902b7-01").is_none()
+        );
+    }
+}
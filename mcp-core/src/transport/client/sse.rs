@@ -1,9 +1,9 @@
+use crate::error::McpError;
 use crate::protocol::{Protocol, ProtocolBuilder, RequestOptions};
 use crate::transport::{
-    JsonRpcError, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, Message, RequestId,
-    Transport,
+    request_span, to_raw_value, JsonRpcError, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse,
+    Message, RawValue, RequestId, Transport,
 };
-use crate::types::ErrorCode;
 use anyhow::Result;
 use async_trait::async_trait;
 use futures::TryStreamExt;
@@ -13,8 +13,8 @@ use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tokio::time::timeout;
-use tracing::debug;
+use tokio::task::JoinHandle;
+use tracing::{debug, Instrument};
 
 /// Client transport that communicates with an MCP server over Server-Sent Events (SSE).
 ///
@@ -33,7 +33,7 @@ use tracing::debug;
 /// # Example
 ///
 /// ```
-/// use mcp_core::transport::ClientSseTransport;
+/// use mcp_core::transport::{ClientSseTransport, Transport};
 ///
 /// async fn example() {
 ///     let transport = ClientSseTransport::builder("https://example.com/sse".to_string())
@@ -55,6 +55,7 @@ pub struct ClientSseTransport {
     session_endpoint: Arc<Mutex<Option<String>>>,
     headers: HashMap<String, String>,
     event_source: Arc<Mutex<Option<EventSource>>>,
+    reader: Arc<Mutex<Option<JoinHandle<()>>>>,
 }
 
 /// Builder for configuring and creating `ClientSseTransport` instances.
@@ -136,6 +137,7 @@ impl ClientSseTransportBuilder {
             session_endpoint: Arc::new(Mutex::new(None)),
             headers: self.headers,
             event_source: Arc::new(Mutex::new(None)),
+            reader: Arc::new(Mutex::new(None)),
         }
     }
 }
@@ -170,6 +172,14 @@ impl Transport for ClientSseTransport {
     /// A `Result` indicating success or failure
     async fn open(&self) -> Result<()> {
         debug!("ClientSseTransport: Opening transport");
+        // Stop the reader of the previous stream first, so that it cannot
+        // close the protocol once the new stream is open
+        if let Some(reader) = self.reader.lock().await.take() {
+            reader.abort();
+            let _ = reader.await;
+            self.protocol.close().await;
+        }
+        self.protocol.mark_open();
 
         let mut request = self.client.get(self.server_url.clone());
 
@@ -192,20 +202,28 @@ impl Transport for ClientSseTransport {
 
         // Spawn a background task to continuously poll messages
         let transport_clone = self.clone();
-        tokio::task::spawn(async move {
+        let peer = self.protocol.peer(None, Arc::new(self.clone()));
+        let reader = tokio::task::spawn(async move {
             loop {
                 match transport_clone.poll_message().await {
                     Ok(Some(message)) => match message {
                         Message::Request(request) => {
-                            let response = transport_clone.protocol.handle_request(request).await;
-                            let _ = transport_clone
-                                .send_response(response.id, response.result, response.error)
-                                .await;
+                            // Handle requests in their own task so that responses
+                            // keep flowing while a handler is running.
+                            let transport = transport_clone.clone();
+                            let peer = peer.clone();
+                            tokio::spawn(async move {
+                                let response =
+                                    transport.protocol.handle_request_from(request, peer).await;
+                                let _ = transport
+                                    .send_response(response.id, response.result, response.error)
+                                    .await;
+                            });
                         }
                         Message::Notification(notification) => {
-                            let _ = transport_clone
+                            transport_clone
                                 .protocol
-                                .handle_notification(notification)
+                                .handle_notification_from(notification, peer.clone())
                                 .await;
                         }
                         Message::Response(response) => {
@@ -213,14 +231,23 @@ impl Transport for ClientSseTransport {
                         }
                     },
                     Ok(None) => continue, // No message or control message, continue polling
+                    Err(e) if e.is::<McpError>() => {
+                        debug!("ClientSseTransport: Ignoring invalid message: {}", e);
+                    }
                     Err(e) => {
                         debug!("ClientSseTransport: Error polling message: {:?}", e);
+                        if transport_clone.event_source.lock().await.is_none() {
+                            break; // The stream has ended or the transport was closed
+                        }
                         // Maybe add some backoff or retry logic here
                         tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
                     }
                 }
             }
+            // Responses to pending requests can only arrive over the stream
+            transport_clone.protocol.close().await;
         });
+        *self.reader.lock().await = Some(reader);
 
         // Wait for the session URL to be set
         let mut attempts = 0;
@@ -238,20 +265,29 @@ impl Transport for ClientSseTransport {
     /// Closes the transport by terminating the SSE connection.
     ///
     /// This method:
-    /// 1. Closes the EventSource connection
-    /// 2. Clears the session endpoint
+    /// 1. Fails all pending requests
+    /// 2. Stops the background task reading the stream
+    /// 3. Closes the EventSource connection
+    /// 4. Clears the session endpoint
     ///
     /// # Returns
     ///
     /// A `Result` indicating success or failure
     async fn close(&self) -> Result<()> {
         debug!("ClientSseTransport: Closing transport");
+        self.protocol.close().await;
+
+        // The reader holds the event source while it waits for the next event,
+        // which may never come on a quiet stream
+        if let Some(reader) = self.reader.lock().await.take() {
+            reader.abort();
+        }
+
         // Close the event source
         *self.event_source.lock().await = None;
 
         // Clear the session URL
         *self.session_endpoint.lock().await = None;
-
         Ok(())
     }
 
@@ -286,16 +322,21 @@ impl Transport for ClientSseTransport {
                         return Ok(None); // This is a control message, not a JSON-RPC message
                     } else {
                         debug!("Received SSE message: {}", m.data);
-                        let message: Message = serde_json::from_str(&m.data)?;
+                        let message =
+                            Message::parse(&m.data).map_err(|e| McpError::from(e.error))?;
                         return Ok(Some(message));
                     }
                 }
                 _ => return Ok(None),
             },
-            Ok(None) => return Ok(None), // Stream ended
+            Ok(None) => {
+                // Stream ended
+                *event_source_guard = None;
+                Err(anyhow::anyhow!("SSE stream ended"))
+            }
             Err(e) => {
                 debug!("Error receiving SSE message: {:?}", e);
-                return Err(anyhow::anyhow!("Failed to parse SSE message: {:?}", e));
+                Err(anyhow::anyhow!("Failed to parse SSE message: {:?}", e))
             }
         }
     }
@@ -330,100 +371,90 @@ impl Transport for ClientSseTransport {
         let method = method.to_owned();
         let headers = self.headers.clone();
 
-        Box::pin(async move {
-            let (id, rx) = protocol.create_request().await;
-            let request = JsonRpcRequest {
-                id,
-                method,
-                jsonrpc: Default::default(),
-                params,
-            };
-
-            // Get the session URL
-            let session_url = {
-                let url = session_endpoint.lock().await;
-                url.as_ref()
-                    .ok_or_else(|| anyhow::anyhow!("No session URL available"))?
-                    .clone()
-            };
-
-            let base_url = if let Some(idx) = server_url.find("://") {
-                let domain_start = idx + 3;
-                let domain_end = server_url[domain_start..]
-                    .find('/')
-                    .map(|i| domain_start + i)
-                    .unwrap_or(server_url.len());
-                &server_url[..domain_end]
-            } else {
-                let domain_end = server_url.find('/').unwrap_or(server_url.len());
-                &server_url[..domain_end]
-            }
-            .to_string();
-
-            debug!("ClientSseTransport: Base URL: {}", base_url);
+        let span = request_span("sse", &method);
+        Box::pin(
+            async move {
+                let params = params.as_ref().map(to_raw_value).transpose()?;
+                let (id, rx) = protocol.create_request().await;
+                tracing::Span::current().record("rpc.request_id", id);
+                if protocol.is_closed() {
+                    return Ok(rx.await?);
+                }
+                let request = JsonRpcRequest {
+                    id,
+                    method,
+                    jsonrpc: Default::default(),
+                    params,
+                };
+
+                let sent = async {
+                    // Get the session URL
+                    let session_url = {
+                        let url = session_endpoint.lock().await;
+                        url.as_ref()
+                            .ok_or_else(|| anyhow::anyhow!("No session URL available"))?
+                            .clone()
+                    };
+
+                    let base_url = if let Some(idx) = server_url.find("://") {
+                        let domain_start = idx + 3;
+                        let domain_end = server_url[domain_start..]
+                            .find('/')
+                            .map(|i| domain_start + i)
+                            .unwrap_or(server_url.len());
+                        &server_url[..domain_end]
+                    } else {
+                        let domain_end = server_url.find('/').unwrap_or(server_url.len());
+                        &server_url[..domain_end]
+                    }
+                    .to_string();
 
-            let full_url = format!("{}{}", base_url, session_url);
-            debug!(
-                "ClientSseTransport: Sending request to {}: {:?}",
-                full_url, request
-            );
+                    debug!("ClientSseTransport: Base URL: {}", base_url);
 
-            let mut req_builder = client.post(&full_url).json(&request);
+                    let full_url = format!("{}{}", base_url, session_url);
+                    debug!(
+                        "ClientSseTransport: Sending request to {}: {:?}",
+                        full_url, request
+                    );
 
-            for (key, value) in headers {
-                req_builder = req_builder.header(key, value);
-            }
+                    let mut req_builder = client.post(&full_url).json(&request);
 
-            if let Some(token) = bearer_token {
-                req_builder = req_builder.header("Authorization", format!("Bearer {}", token));
-            }
+                    for (key, value) in headers {
+                        req_builder = req_builder.header(key, value);
+                    }
 
-            let response = req_builder.send().await?;
+                    if let Some(token) = bearer_token {
+                        req_builder =
+                            req_builder.header("Authorization", format!("Bearer {}", token));
+                    }
 
-            if !response.status().is_success() {
-                let status = response.status();
-                let text = response.text().await?;
-                return Err(anyhow::anyhow!(
-                    "Failed to send request, status: {status}, body: {text}"
-                ));
-            }
+                    let response = req_builder.send().await?;
 
-            debug!("ClientSseTransport: Request sent successfully");
-
-            // Wait for the response with a timeout
-            let result = timeout(options.timeout, rx).await;
-            match result {
-                Ok(inner_result) => match inner_result {
-                    Ok(response) => Ok(response),
-                    Err(_) => {
-                        protocol.cancel_response(id).await;
-                        Ok(JsonRpcResponse {
-                            id,
-                            result: None,
-                            error: Some(JsonRpcError {
-                                code: ErrorCode::RequestTimeout as i32,
-                                message: "Request cancelled".to_string(),
-                                data: None,
-                            }),
-                            ..Default::default()
-                        })
+                    if !response.status().is_success() {
+                        let status = response.status();
+                        let text = response.text().await?;
+                        return Err(anyhow::anyhow!(
+                            "Failed to send request, status: {status}, body: {text}"
+                        ));
                     }
-                },
-                Err(_) => {
+                    Ok(())
+                }
+                .await;
+                if let Err(e) = sent {
+                    // The request never reached the server, so nothing will answer it
                     protocol.cancel_response(id).await;
-                    Ok(JsonRpcResponse {
-                        id,
-                        result: None,
-                        error: Some(JsonRpcError {
-                            code: ErrorCode::RequestTimeout as i32,
-                            message: "Request timed out".to_string(),
-                            data: None,
-                        }),
-                        ..Default::default()
-                    })
+                    return Err(e);
                 }
+
+                debug!("ClientSseTransport: Request sent successfully");
+
+                // Wait for the response with a timeout
+                Ok(protocol
+                    .wait_for_response(id, rx, request.params.as_deref(), &options)
+                    .await)
             }
-        })
+            .instrument(span),
+        )
     }
 
     /// Sends a response to a request previously received from the server.
@@ -440,7 +471,7 @@ impl Transport for ClientSseTransport {
     async fn send_response(
         &self,
         id: RequestId,
-        result: Option<serde_json::Value>,
+        result: Option<Box<RawValue>>,
         error: Option<JsonRpcError>,
     ) -> Result<()> {
         let response = JsonRpcResponse {
@@ -520,6 +551,7 @@ impl Transport for ClientSseTransport {
         method: &str,
         params: Option<serde_json::Value>,
     ) -> Result<()> {
+        let params = params.as_ref().map(to_raw_value).transpose()?;
         let notification = JsonRpcNotification {
             jsonrpc: Default::default(),
             method: method.to_owned(),
@@ -578,4 +610,51 @@ impl Transport for ClientSseTransport {
 
         Ok(())
     }
+
+    fn protocol(&self) -> Option<&Protocol> {
+        Some(&self.protocol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ErrorCode;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_close_idle_stream() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = socket.read(&mut request).await;
+            socket
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\r\n\
+                      event: endpoint\ndata: /message?sessionId=a\n\n",
+                )
+                .await
+                .unwrap();
+            // Keep the stream open without sending anything else
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        });
+
+        let transport = ClientSseTransport::builder(format!("http://{}/sse", addr)).build();
+        transport.open().await.unwrap();
+        let (_, pending) = transport.protocol.create_request().await;
+
+        tokio::time::timeout(Duration::from_secs(1), transport.close())
+            .await
+            .expect("close blocked on the idle stream")
+            .unwrap();
+        assert_eq!(
+            pending.await.unwrap().error.map(|e| e.code),
+            Some(ErrorCode::ConnectionClosed as i32)
+        );
+        let (_, rejected) = transport.protocol.create_request().await;
+        assert!(rejected.await.unwrap().error.is_some());
+    }
 }
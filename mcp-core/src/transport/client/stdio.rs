@@ -1,9 +1,9 @@
+use crate::error::McpError;
 use crate::protocol::{Protocol, ProtocolBuilder, RequestOptions};
 use crate::transport::{
-    JsonRpcError, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, Message, RequestId,
-    Transport,
+    request_span, to_raw_value, JsonRpcError, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse,
+    Message, RawValue, RequestId, Transport,
 };
-use crate::types::ErrorCode;
 use anyhow::Result;
 use async_trait::async_trait;
 use std::future::Future;
@@ -12,8 +12,8 @@ use std::pin::Pin;
 use std::process::Command;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tokio::time::timeout;
-use tracing::debug;
+use tokio::task::JoinHandle;
+use tracing::{debug, Instrument};
 
 /// Client transport that communicates with an MCP server over standard I/O.
 ///
@@ -47,6 +47,7 @@ pub struct ClientStdioTransport {
     stdin: Arc<Mutex<Option<BufWriter<std::process::ChildStdin>>>>,
     stdout: Arc<Mutex<Option<BufReader<std::process::ChildStdout>>>>,
     child: Arc<Mutex<Option<std::process::Child>>>,
+    reader: Arc<Mutex<Option<JoinHandle<()>>>>,
     program: String,
     args: Vec<String>,
 }
@@ -68,6 +69,7 @@ impl ClientStdioTransport {
             stdin: Arc::new(Mutex::new(None)),
             stdout: Arc::new(Mutex::new(None)),
             child: Arc::new(Mutex::new(None)),
+            reader: Arc::new(Mutex::new(None)),
             program: program.to_string(),
             args: args.iter().map(|&s| s.to_string()).collect(),
         })
@@ -88,6 +90,14 @@ impl Transport for ClientStdioTransport {
     /// A `Result` indicating success or failure
     async fn open(&self) -> Result<()> {
         debug!("ClientStdioTransport: Opening transport");
+        // Stop the reader of the previous process first, so that it cannot
+        // close the protocol once the new process is running
+        if let Some(reader) = self.reader.lock().await.take() {
+            reader.abort();
+            let _ = reader.await;
+            self.protocol.close().await;
+        }
+        self.protocol.mark_open();
         let mut child = Command::new(&self.program)
             .args(&self.args)
             .stdin(std::process::Stdio::piped())
@@ -118,20 +128,28 @@ impl Transport for ClientStdioTransport {
 
         // Spawn a background task to continuously poll messages.
         let transport_clone = self.clone();
-        tokio::spawn(async move {
+        let peer = self.protocol.peer(None, Arc::new(self.clone()));
+        let reader = tokio::spawn(async move {
             loop {
                 match transport_clone.poll_message().await {
                     Ok(Some(message)) => match message {
                         Message::Request(request) => {
-                            let response = transport_clone.protocol.handle_request(request).await;
-                            let _ = transport_clone
-                                .send_response(response.id, response.result, response.error)
-                                .await;
+                            // Handle requests in their own task so that responses
+                            // keep flowing while a handler is running.
+                            let transport = transport_clone.clone();
+                            let peer = peer.clone();
+                            tokio::spawn(async move {
+                                let response =
+                                    transport.protocol.handle_request_from(request, peer).await;
+                                let _ = transport
+                                    .send_response(response.id, response.result, response.error)
+                                    .await;
+                            });
                         }
                         Message::Notification(notification) => {
-                            let _ = transport_clone
+                            transport_clone
                                 .protocol
-                                .handle_notification(notification)
+                                .handle_notification_from(notification, peer.clone())
                                 .await;
                         }
                         Message::Response(response) => {
@@ -139,26 +157,36 @@ impl Transport for ClientStdioTransport {
                         }
                     },
                     Ok(None) => break, // EOF encountered.
+                    Err(e) if e.is::<McpError>() => {
+                        // An invalid message does not end the connection
+                        debug!("ClientStdioTransport: Ignoring invalid message: {}", e);
+                    }
                     Err(e) => {
                         debug!("ClientStdioTransport: Error polling message: {:?}", e);
                         break;
                     }
                 }
             }
+            // The server is gone, so pending requests will never get a response
+            transport_clone.protocol.close().await;
         });
+        *self.reader.lock().await = Some(reader);
         Ok(())
     }
 
     /// Closes the transport by terminating the child process and cleaning up resources.
     ///
     /// This method:
-    /// 1. Kills the child process
-    /// 2. Clears the stdin and stdout handles
+    /// 1. Fails all pending requests
+    /// 2. Kills the child process
+    /// 3. Clears the stdin and stdout handles
     ///
     /// # Returns
     ///
     /// A `Result` indicating success or failure
     async fn close(&self) -> Result<()> {
+        self.protocol.close().await;
+
         let mut child_lock = self.child.lock().await;
         if let Some(child) = child_lock.as_mut() {
             let _ = child.kill();
@@ -216,7 +244,7 @@ impl Transport for ClientStdioTransport {
                     "ClientStdioTransport: Received from process: {}",
                     line.trim()
                 );
-                let message: Message = serde_json::from_str(&line)?;
+                let message = Message::parse(&line).map_err(|e| McpError::from(e.error))?;
                 debug!("ClientStdioTransport: Successfully parsed message");
                 Ok(Some(message))
             }
@@ -253,69 +281,58 @@ impl Transport for ClientStdioTransport {
         let protocol = self.protocol.clone();
         let stdin_arc = self.stdin.clone();
         let method = method.to_owned();
-        Box::pin(async move {
-            let (id, rx) = protocol.create_request().await;
-            let request = JsonRpcRequest {
-                id,
-                method,
-                jsonrpc: Default::default(),
-                params,
-            };
-            let serialized = serde_json::to_string(&request)?;
-            debug!("ClientStdioTransport: Sending request: {}", serialized);
-
-            // Get the stdin writer
-            let mut stdin_guard = stdin_arc.lock().await;
-            let mut stdin = stdin_guard
-                .take()
-                .ok_or_else(|| anyhow::anyhow!("Transport not opened"))?;
-
-            // Use a blocking operation in a spawn_blocking task
-            let stdin_result = tokio::task::spawn_blocking(move || {
-                stdin.write_all(serialized.as_bytes())?;
-                stdin.write_all(b"\n")?;
-                stdin.flush()?;
-                Ok::<_, anyhow::Error>(stdin)
-            })
-            .await??;
-
-            // Put the writer back
-            *stdin_guard = Some(stdin_result);
-
-            debug!("ClientStdioTransport: Request sent successfully");
-            let result = timeout(options.timeout, rx).await;
-            match result {
-                Ok(inner_result) => match inner_result {
-                    Ok(response) => Ok(response),
-                    Err(_) => {
-                        protocol.cancel_response(id).await;
-                        Ok(JsonRpcResponse {
-                            id,
-                            result: None,
-                            error: Some(JsonRpcError {
-                                code: ErrorCode::RequestTimeout as i32,
-                                message: "Request cancelled".to_string(),
-                                data: None,
-                            }),
-                            ..Default::default()
-                        })
-                    }
-                },
-                Err(_) => {
-                    protocol.cancel_response(id).await;
-                    Ok(JsonRpcResponse {
-                        id,
-                        result: None,
-                        error: Some(JsonRpcError {
-                            code: ErrorCode::RequestTimeout as i32,
-                            message: "Request timed out".to_string(),
-                            data: None,
-                        }),
-                        ..Default::default()
+        let span = request_span("stdio", &method);
+        Box::pin(
+            async move {
+                let params = params.as_ref().map(to_raw_value).transpose()?;
+                let (id, rx) = protocol.create_request().await;
+                tracing::Span::current().record("rpc.request_id", id);
+                if protocol.is_closed() {
+                    return Ok(rx.await?);
+                }
+                let request = JsonRpcRequest {
+                    id,
+                    method,
+                    jsonrpc: Default::default(),
+                    params,
+                };
+                let sent = async {
+                    let serialized = serde_json::to_string(&request)?;
+                    debug!("ClientStdioTransport: Sending request: {}", serialized);
+
+                    // Get the stdin writer
+                    let mut stdin_guard = stdin_arc.lock().await;
+                    let mut stdin = stdin_guard
+                        .take()
+                        .ok_or_else(|| anyhow::anyhow!("Transport not opened"))?;
+
+                    // Use a blocking operation in a spawn_blocking task
+                    let stdin_result = tokio::task::spawn_blocking(move || {
+                        stdin.write_all(serialized.as_bytes())?;
+                        stdin.write_all(b"\n")?;
+                        stdin.flush()?;
+                        Ok::<_, anyhow::Error>(stdin)
                     })
+                    .await??;
+
+                    // Put the writer back
+                    *stdin_guard = Some(stdin_result);
+                    Ok::<_, anyhow::Error>(())
+                }
+                .await;
+                if let Err(e) = sent {
+                    // The request never reached the server, so nothing will answer it
+                    protocol.cancel_response(id).await;
+                    return Err(e);
                 }
+
+                debug!("ClientStdioTransport: Request sent successfully");
+                Ok(protocol
+                    .wait_for_response(id, rx, request.params.as_deref(), &options)
+                    .await)
             }
-        })
+            .instrument(span),
+        )
     }
 
     /// Sends a response to a request previously received from the child process.
@@ -332,7 +349,7 @@ impl Transport for ClientStdioTransport {
     async fn send_response(
         &self,
         id: RequestId,
-        result: Option<serde_json::Value>,
+        result: Option<Box<RawValue>>,
         error: Option<JsonRpcError>,
     ) -> Result<()> {
         let response = JsonRpcResponse {
@@ -382,6 +399,7 @@ impl Transport for ClientStdioTransport {
         method: &str,
         params: Option<serde_json::Value>,
     ) -> Result<()> {
+        let params = params.as_ref().map(to_raw_value).transpose()?;
         let notification = JsonRpcNotification {
             jsonrpc: Default::default(),
             method: method.to_owned(),
@@ -410,4 +428,8 @@ impl Transport for ClientStdioTransport {
 
         Ok(())
     }
+
+    fn protocol(&self) -> Option<&Protocol> {
+        Some(&self.protocol)
+    }
 }
@@ -12,12 +12,20 @@
 //!
 //! The core component is the `Transport` trait, which defines the operations that
 //! any MCP transport must support, regardless of the underlying mechanism.
+//!
+//! Incoming params and results are kept as raw JSON (`Box<RawValue>`) until a
+//! handler deserializes them into its own types, so large payloads such as
+//! base64 images are neither parsed into a `serde_json::Value` tree nor copied
+//! on the way through. With the `simd-json` feature, that final deserialization
+//! uses `simd-json`.
 
 use std::{future::Future, pin::Pin};
 
 use anyhow::Result;
 use async_trait::async_trait;
-use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+pub use serde_json::value::RawValue;
 
 mod client;
 pub use client::*;
@@ -25,7 +33,13 @@ pub use client::*;
 mod server;
 pub use server::*;
 
-use crate::protocol::RequestOptions;
+#[cfg(test)]
+mod recorder;
+#[cfg(test)]
+pub(crate) use recorder::Recorder;
+
+use crate::error::McpError;
+use crate::protocol::{Protocol, RequestOptions};
 
 /// A message in the MCP protocol.
 ///
@@ -33,6 +47,18 @@ use crate::protocol::RequestOptions;
 /// [MCP specification](https://spec.modelcontextprotocol.io/specification/basic/messages/).
 pub type Message = JsonRpcMessage;
 
+/// Creates the span of an outgoing request sent over a transport.
+///
+/// The transport records the request ID once it is assigned.
+pub(crate) fn request_span(transport: &'static str, method: &str) -> tracing::Span {
+    tracing::debug_span!(
+        "mcp.transport.request",
+        transport,
+        rpc.method = method,
+        rpc.request_id = tracing::field::Empty,
+    )
+}
+
 /// Core trait that defines operations for MCP transports.
 ///
 /// This trait abstracts the transport layer, allowing the protocol to work
@@ -116,9 +142,58 @@ pub trait Transport: Send + Sync + 'static {
     async fn send_response(
         &self,
         id: RequestId,
-        result: Option<serde_json::Value>,
+        result: Option<Box<RawValue>>,
         error: Option<JsonRpcError>,
     ) -> Result<()>;
+
+    /// Returns the protocol that dispatches the messages received by this transport.
+    ///
+    /// This lets clients and servers record what they learn about the peer,
+    /// such as its negotiated capabilities.
+    ///
+    /// # Returns
+    ///
+    /// The `Protocol` of the transport, or `None` if it does not have one
+    fn protocol(&self) -> Option<&Protocol> {
+        None
+    }
+}
+
+/// Serializes a value to raw JSON.
+///
+/// # Arguments
+///
+/// * `value` - The value, e.g. the params of a request or the result of a handler
+///
+/// # Returns
+///
+/// The raw JSON of the value
+pub fn to_raw_value<T: Serialize + ?Sized>(value: &T) -> serde_json::Result<Box<RawValue>> {
+    serde_json::value::to_raw_value(value)
+}
+
+/// Deserializes raw JSON, such as request params, into a typed value.
+///
+/// With the `simd-json` feature enabled, `simd-json` is used to parse the JSON.
+///
+/// # Arguments
+///
+/// * `raw` - The raw JSON
+///
+/// # Returns
+///
+/// The deserialized value
+pub fn from_raw_value<T: DeserializeOwned>(raw: &RawValue) -> serde_json::Result<T> {
+    #[cfg(feature = "simd-json")]
+    {
+        // simd-json parses in place, so it needs its own copy of the input
+        let mut bytes = raw.get().as_bytes().to_vec();
+        simd_json::serde::from_slice(&mut bytes).map_err(serde::de::Error::custom)
+    }
+    #[cfg(not(feature = "simd-json"))]
+    {
+        serde_json::from_str(raw.get())
+    }
 }
 
 /// Type representing a JSON-RPC request ID.
@@ -158,9 +233,9 @@ impl JsonRpcVersion {
 
 /// Represents a JSON-RPC message.
 ///
-/// This enum can be a request, a response, or a notification.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-#[serde(deny_unknown_fields)]
+/// This enum can be a request, a response, or a notification. Deserializing a
+/// message validates it like `JsonRpcMessage::parse`.
+#[derive(Debug, Clone, Serialize)]
 #[serde(untagged)]
 pub enum JsonRpcMessage {
     /// A response to a request
@@ -171,19 +246,235 @@ pub enum JsonRpcMessage {
     Notification(JsonRpcNotification),
 }
 
+impl JsonRpcMessage {
+    /// Parses and validates a JSON-RPC 2.0 message.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The raw message
+    ///
+    /// # Returns
+    ///
+    /// The message, or the error response to send back if the text is not valid
+    /// JSON (`ParseError`) or not a valid JSON-RPC 2.0 message (`InvalidRequest`).
+    /// Check `JsonRpcErrorResponse::should_send` before sending it back.
+    pub fn parse(text: &str) -> Result<Self, JsonRpcErrorResponse> {
+        let envelope: Envelope = serde_json::from_str(text).map_err(|e| match e.classify() {
+            serde_json::error::Category::Data => {
+                let reply =
+                    serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(text)
+                        .is_ok_and(|fields| {
+                            fields.contains_key("result") || fields.contains_key("error")
+                        });
+                JsonRpcErrorResponse::new(None, McpError::invalid_request(e.to_string()))
+                    .with_reply(reply)
+            }
+            _ => JsonRpcErrorResponse::new(None, McpError::parse_error(e.to_string())),
+        })?;
+        // Structs can also be deserialized from arrays
+        if !text.trim_start().starts_with('{') {
+            return Err(JsonRpcErrorResponse::new(
+                None,
+                McpError::invalid_request("Message must be a JSON object"),
+            ));
+        }
+        Self::from_envelope(envelope)
+    }
+
+    /// Validates a JSON value as a JSON-RPC 2.0 message.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The JSON value of the message
+    ///
+    /// # Returns
+    ///
+    /// The message, or the `InvalidRequest` error response to send back
+    pub fn from_value(value: serde_json::Value) -> Result<Self, JsonRpcErrorResponse> {
+        Self::parse(&value.to_string())
+    }
+
+    /// Validates the fields of a message.
+    ///
+    /// The message must have `"jsonrpc": "2.0"`, a numeric `id` for requests
+    /// and responses, a string `method` for requests and notifications, and
+    /// structured (object or array) `params`, if any. Batches are not supported.
+    /// The error response carries the ID of the message when it could be determined,
+    /// and is not to be sent when the message has a `result` or an `error`.
+    fn from_envelope(envelope: Envelope) -> Result<Self, JsonRpcErrorResponse> {
+        // Answering a malformed response, such as an error reply with a null ID,
+        // could start an endless exchange of error responses with the peer
+        let reply = envelope.result.is_some() || envelope.error.is_some();
+        let invalid = |id: Option<RequestId>, message: &str| {
+            JsonRpcErrorResponse::new(id, McpError::invalid_request(message)).with_reply(reply)
+        };
+
+        let id = match &envelope.id {
+            None => None,
+            Some(serde_json::Value::Null) => return Err(invalid(None, "id must not be null")),
+            Some(id) => match id.as_u64() {
+                Some(id) => Some(id),
+                None => return Err(invalid(None, "Unsupported id, expected a positive integer")),
+            },
+        };
+        if envelope.jsonrpc.as_ref().and_then(|v| v.as_str()) != Some("2.0") {
+            return Err(invalid(id, "jsonrpc must be \"2.0\""));
+        }
+
+        match (envelope.method, id) {
+            (Some(method), id) => {
+                let serde_json::Value::String(method) = method else {
+                    return Err(invalid(id, "method must be a string"));
+                };
+                if envelope.result.is_some() || envelope.error.is_some() {
+                    return Err(invalid(id, "A request cannot have a result or an error"));
+                }
+                let params = envelope.params;
+                if params.as_ref().is_some_and(|params| {
+                    !params.get().starts_with('{') && !params.get().starts_with('[')
+                }) {
+                    return Err(invalid(id, "params must be an object or an array"));
+                }
+                Ok(match id {
+                    Some(id) => JsonRpcMessage::Request(JsonRpcRequest {
+                        id,
+                        method,
+                        params,
+                        jsonrpc: Default::default(),
+                    }),
+                    None => JsonRpcMessage::Notification(JsonRpcNotification {
+                        method,
+                        params,
+                        jsonrpc: Default::default(),
+                    }),
+                })
+            }
+            (None, Some(id)) => {
+                if envelope.params.is_some() {
+                    return Err(invalid(Some(id), "A response cannot have params"));
+                }
+                let error = envelope
+                    .error
+                    .map(serde_json::from_value::<JsonRpcError>)
+                    .transpose()
+                    .map_err(|e| invalid(Some(id), &e.to_string()))?;
+                Ok(JsonRpcMessage::Response(JsonRpcResponse {
+                    id,
+                    result: envelope.result,
+                    error,
+                    jsonrpc: Default::default(),
+                }))
+            }
+            (None, None) => Err(invalid(None, "Message has neither a method nor an id")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonRpcMessage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let envelope = Envelope::deserialize(deserializer)?;
+        Self::from_envelope(envelope).map_err(|e| serde::de::Error::custom(e.error.message))
+    }
+}
+
+/// The fields of any JSON-RPC message, with params and results kept raw.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Envelope {
+    jsonrpc: Option<serde_json::Value>,
+    /// `None` when absent, `Some(Value::Null)` when null
+    #[serde(default, deserialize_with = "deserialize_present")]
+    id: Option<serde_json::Value>,
+    method: Option<serde_json::Value>,
+    params: Option<Box<RawValue>>,
+    result: Option<Box<RawValue>>,
+    error: Option<serde_json::Value>,
+}
+
+/// Deserializes a field that is present, so that a `null` value is kept.
+fn deserialize_present<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<serde_json::Value>, D::Error> {
+    serde_json::Value::deserialize(deserializer).map(Some)
+}
+
+/// An error response to a message that could not be parsed or validated.
+///
+/// Unlike `JsonRpcResponse`, the ID may be `null`, as the JSON-RPC specification
+/// requires when the ID of the offending message cannot be determined.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JsonRpcErrorResponse {
+    /// The JSON-RPC version
+    pub jsonrpc: JsonRpcVersion,
+    /// The ID of the offending message, if it could be determined
+    pub id: Option<RequestId>,
+    /// The error
+    pub error: JsonRpcError,
+    /// Whether the offending message was itself a response
+    #[serde(skip)]
+    reply: bool,
+}
+
+impl JsonRpcErrorResponse {
+    /// Creates a new error response.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the offending message, if known
+    /// * `error` - The error
+    ///
+    /// # Returns
+    ///
+    /// A new `JsonRpcErrorResponse` instance
+    pub fn new(id: Option<RequestId>, error: McpError) -> Self {
+        Self {
+            jsonrpc: Default::default(),
+            id,
+            error: error.into(),
+            reply: false,
+        }
+    }
+
+    /// Marks whether the offending message was itself a response.
+    ///
+    /// # Arguments
+    ///
+    /// * `reply` - Whether the message had a `result` or an `error`
+    ///
+    /// # Returns
+    ///
+    /// The modified `JsonRpcErrorResponse` instance
+    pub(crate) fn with_reply(mut self, reply: bool) -> Self {
+        self.reply = reply;
+        self
+    }
+
+    /// Returns whether the error response should be sent back to the peer.
+    ///
+    /// Messages with a `result` or an `error` are never answered, so that two
+    /// peers cannot keep answering each other's error responses.
+    ///
+    /// # Returns
+    ///
+    /// `true` unless the offending message was a response
+    pub fn should_send(&self) -> bool {
+        !self.reply
+    }
+}
+
 /// Represents a JSON-RPC request.
 ///
 /// A request is a message that expects a response with the same ID.
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(deny_unknown_fields)]
 pub struct JsonRpcRequest {
     /// The request ID, used to match with the response
     pub id: RequestId,
     /// The method name to call
     pub method: String,
-    /// Optional parameters for the method
+    /// Optional parameters for the method, as raw JSON
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub params: Option<serde_json::Value>,
+    pub params: Option<Box<RawValue>>,
     /// The JSON-RPC version
     pub jsonrpc: JsonRpcVersion,
 }
@@ -191,16 +482,16 @@ pub struct JsonRpcRequest {
 /// Represents a JSON-RPC notification.
 ///
 /// A notification is a message that does not expect a response.
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
 #[serde(default)]
 pub struct JsonRpcNotification {
     /// The method name for the notification
     pub method: String,
-    /// Optional parameters for the notification
+    /// Optional parameters for the notification, as raw JSON
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub params: Option<serde_json::Value>,
+    pub params: Option<Box<RawValue>>,
     /// The JSON-RPC version
     pub jsonrpc: JsonRpcVersion,
 }
@@ -208,16 +499,16 @@ pub struct JsonRpcNotification {
 /// Represents a JSON-RPC response.
 ///
 /// A response is a message sent in reply to a request with the same ID.
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
 #[serde(default)]
 pub struct JsonRpcResponse {
     /// The request ID this response corresponds to
     pub id: RequestId,
-    /// The result of the request, if successful
+    /// The result of the request, if successful, as raw JSON
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub result: Option<serde_json::Value>,
+    pub result: Option<Box<RawValue>>,
     /// The error, if the request failed
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<JsonRpcError>,
@@ -240,3 +531,82 @@ pub struct JsonRpcError {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<serde_json::Value>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ErrorCode;
+
+    fn error_of(text: &str) -> (Option<RequestId>, i32) {
+        let response = JsonRpcMessage::parse(text).unwrap_err();
+        (response.id, response.error.code)
+    }
+
+    #[test]
+    fn test_parse_message() {
+        let parse_error = ErrorCode::ParseError as i32;
+        let invalid_request = ErrorCode::InvalidRequest as i32;
+
+        assert_eq!(error_of("{"), (None, parse_error));
+        assert_eq!(error_of("[]"), (None, invalid_request));
+        assert_eq!(
+            error_of(r#"{"jsonrpc":"1.0","id":3,"method":"ping"}"#),
+            (Some(3), invalid_request)
+        );
+        assert_eq!(
+            error_of(r#"{"jsonrpc":"2.0","id":"a","method":"ping"}"#),
+            (None, invalid_request)
+        );
+        assert_eq!(
+            error_of(r#"{"jsonrpc":"2.0","id":1,"method":"ping","params":1}"#),
+            (Some(1), invalid_request)
+        );
+
+        assert!(matches!(
+            JsonRpcMessage::parse(r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#),
+            Ok(JsonRpcMessage::Request(_))
+        ));
+        assert!(matches!(
+            JsonRpcMessage::parse(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#),
+            Ok(JsonRpcMessage::Notification(_))
+        ));
+        assert!(matches!(
+            JsonRpcMessage::parse(r#"{"jsonrpc":"2.0","id":1,"result":{}}"#),
+            Ok(JsonRpcMessage::Response(_))
+        ));
+
+        // Params are kept as they were received
+        let Ok(JsonRpcMessage::Request(request)) = JsonRpcMessage::parse(
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name": "echo"}}"#,
+        ) else {
+            panic!("Expected a request");
+        };
+        assert_eq!(request.params.unwrap().get(), r#"{"name": "echo"}"#);
+
+        // A null ID is neither absent nor valid
+        assert_eq!(
+            error_of(r#"{"jsonrpc":"2.0","id":null,"method":"ping"}"#),
+            (None, invalid_request)
+        );
+        assert!(
+            JsonRpcMessage::parse(r#"{"jsonrpc":"2.0","id":null,"method":"ping"}"#)
+                .unwrap_err()
+                .should_send()
+        );
+
+        // Malformed responses are never answered
+        for text in [
+            r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32600,"message":"Invalid"}}"#,
+            r#"{"jsonrpc":"2.0","id":"a","result":{}}"#,
+            r#"{"jsonrpc":"2.0","id":1,"result":{},"extra":1}"#,
+        ] {
+            assert!(!JsonRpcMessage::parse(text).unwrap_err().should_send());
+        }
+
+        let response = JsonRpcErrorResponse::new(None, McpError::parse_error("bad"));
+        assert_eq!(
+            serde_json::to_value(response).unwrap()["id"],
+            serde_json::Value::Null
+        );
+    }
+}
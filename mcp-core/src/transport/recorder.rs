@@ -0,0 +1,72 @@
+//! A transport for tests, recording what is sent through it.
+
+use super::{JsonRpcError, JsonRpcResponse, Message, RawValue, RequestId, Transport};
+use crate::protocol::{Protocol, RequestOptions};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+
+/// A transport recording the notifications sent through it.
+///
+/// Requests are only registered with the protocol, if any, and never
+/// delivered, so they wait until they time out or the protocol is closed.
+#[derive(Default)]
+pub(crate) struct Recorder {
+    pub(crate) protocol: Option<Protocol>,
+    pub(crate) notifications: Mutex<Vec<(String, Option<serde_json::Value>)>>,
+}
+
+#[async_trait]
+impl Transport for Recorder {
+    async fn open(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn poll_message(&self) -> Result<Option<Message>> {
+        Ok(None)
+    }
+
+    fn request(
+        &self,
+        _method: &str,
+        params: Option<serde_json::Value>,
+        options: RequestOptions,
+    ) -> Pin<Box<dyn Future<Output = Result<JsonRpcResponse>> + Send + Sync>> {
+        let protocol = self.protocol.clone();
+        Box::pin(async move {
+            let protocol = protocol.ok_or_else(|| anyhow::anyhow!("Not supported"))?;
+            let params = params.as_ref().map(super::to_raw_value).transpose()?;
+            let (id, rx) = protocol.create_request().await;
+            Ok(protocol
+                .wait_for_response(id, rx, params.as_deref(), &options)
+                .await)
+        })
+    }
+
+    async fn send_notification(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> Result<()> {
+        self.notifications
+            .lock()
+            .unwrap()
+            .push((method.to_string(), params));
+        Ok(())
+    }
+
+    async fn send_response(
+        &self,
+        _id: RequestId,
+        _result: Option<Box<RawValue>>,
+        _error: Option<JsonRpcError>,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
@@ -1,8 +1,10 @@
 use crate::{
-    protocol::{Protocol, RequestOptions},
+    error::McpError,
+    protocol::{LivenessCheck, Protocol, RequestOptions},
     transport::{
-        JsonRpcError, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse,
-        Message, RequestId, Transport,
+        request_span, to_raw_value, JsonRpcError, JsonRpcErrorResponse, JsonRpcMessage,
+        JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, Message, RawValue, RequestId,
+        Transport,
     },
     types::ErrorCode,
 };
@@ -15,13 +17,11 @@ use anyhow::Result;
 use async_trait::async_trait;
 use futures::StreamExt;
 use serde::Deserialize;
+use std::pin::Pin;
 use std::sync::Arc;
 use std::{collections::HashMap, future::Future};
-use std::{pin::Pin, time::Duration};
-use tokio::{
-    sync::{mpsc, Mutex},
-    time::timeout,
-};
+use tokio::sync::{mpsc, Mutex};
+use tracing::Instrument;
 use uuid::Uuid;
 
 /// Server transport that communicates with MCP clients over Server-Sent Events (SSE).
@@ -35,12 +35,15 @@ use uuid::Uuid;
 /// - Supports multiple concurrent client connections
 /// - Uses SSE for efficient server-to-client messaging
 /// - Manages client sessions with unique IDs
-/// - Provides heartbeat/ping functionality to maintain connections
+/// - Optionally pings clients and drops sessions that stop answering
 ///
 /// # Example
 ///
 /// ```
-/// use mcp_core::{protocol::Protocol, transport::ServerSseTransport};
+/// use mcp_core::{
+///     protocol::Protocol,
+///     transport::{ServerSseTransport, Transport},
+/// };
 ///
 /// async fn example() {
 ///     let protocol = Protocol::builder().build();
@@ -55,6 +58,8 @@ pub struct ServerSseTransport {
     sessions: Arc<Mutex<HashMap<String, ServerSseTransportSession>>>,
     host: String,
     port: u16,
+    liveness_check: Option<LivenessCheck>,
+    metrics_endpoint: bool,
 }
 
 impl ServerSseTransport {
@@ -75,9 +80,45 @@ impl ServerSseTransport {
             sessions: Arc::new(Mutex::new(HashMap::new())),
             host,
             port,
+            liveness_check: None,
+            metrics_endpoint: false,
         }
     }
 
+    /// Enables periodic liveness checks of connected clients.
+    ///
+    /// Liveness checks are off by default. When enabled, sessions whose client
+    /// misses too many consecutive pings are removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `liveness_check` - The liveness check configuration
+    ///
+    /// # Returns
+    ///
+    /// The modified transport instance
+    pub fn with_liveness_check(mut self, liveness_check: LivenessCheck) -> Self {
+        self.liveness_check = Some(liveness_check);
+        self
+    }
+
+    /// Serves the metrics of the protocol at `/metrics`.
+    ///
+    /// The endpoint renders the metrics set with `ProtocolBuilder::metrics`, e.g.
+    /// `PrometheusMetrics`, and answers `404 Not Found` if they cannot be rendered.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to serve the metrics
+    ///
+    /// # Returns
+    ///
+    /// The modified transport instance
+    pub fn with_metrics_endpoint(mut self, enabled: bool) -> Self {
+        self.metrics_endpoint = enabled;
+        self
+    }
+
     /// Creates a new session with the given ID.
     ///
     /// This sets up the communication channels needed for the session.
@@ -88,11 +129,15 @@ impl ServerSseTransport {
     async fn create_session(&self, session_id: String) {
         let (tx, rx) = mpsc::channel::<JsonRpcMessage>(100);
         let session = ServerSseTransportSession {
+            session_id: session_id.clone(),
             protocol: self.protocol.clone(),
             tx,
             rx: Arc::new(Mutex::new(rx)),
         };
         self.sessions.lock().await.insert(session_id, session);
+        if let Some(metrics) = self.protocol.metrics() {
+            metrics.session_opened();
+        }
     }
 
     /// Retrieves a session by its ID.
@@ -108,6 +153,55 @@ impl ServerSseTransport {
         let sessions = self.sessions.lock().await;
         sessions.get(session_id).cloned()
     }
+
+    /// Removes a session by its ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The ID of the session to remove
+    async fn remove_session(&self, session_id: &str) {
+        let session = self.sessions.lock().await.remove(session_id);
+        self.protocol.remove_session(Some(session_id));
+        if let (Some(session), Some(metrics)) = (session, self.protocol.metrics()) {
+            metrics.session_closed();
+            // Queued messages are dropped with the session
+            metrics.sse_messages_dequeued(session.tx.max_capacity() - session.tx.capacity());
+        }
+    }
+
+    /// Pings the client of a session until it is declared dead or the session is removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The ID of the session to monitor
+    /// * `liveness_check` - The liveness check configuration
+    async fn monitor_liveness(&self, session_id: String, liveness_check: LivenessCheck) {
+        let mut missed = 0;
+        loop {
+            tokio::time::sleep(liveness_check.interval).await;
+            let Some(session) = self.get_session(&session_id).await else {
+                break;
+            };
+
+            if liveness_check.ping(&session).await {
+                missed = 0;
+                continue;
+            }
+
+            missed += 1;
+            tracing::warn!(
+                "Session {} did not answer ping ({}/{})",
+                session_id,
+                missed,
+                liveness_check.max_missed
+            );
+            if missed >= liveness_check.max_missed {
+                tracing::warn!("Session {} is not responding, removing it", session_id);
+                self.remove_session(&session_id).await;
+                break;
+            }
+        }
+    }
 }
 
 #[async_trait()]
@@ -126,11 +220,16 @@ impl Transport for ServerSseTransport {
     async fn open(&self) -> Result<()> {
         let transport = self.clone();
         let server = HttpServer::new(move || {
-            App::new()
+            let app = App::new()
                 .wrap(Logger::default())
                 .app_data(web::Data::new(transport.clone()))
                 .route("/sse", web::get().to(sse_handler))
-                .route("/message", web::post().to(message_handler))
+                .route("/message", web::post().to(message_handler));
+            if transport.metrics_endpoint {
+                app.route("/metrics", web::get().to(metrics_handler))
+            } else {
+                app
+            }
         })
         .bind((self.host.clone(), self.port))?
         .run();
@@ -203,11 +302,15 @@ impl Transport for ServerSseTransport {
     async fn send_response(
         &self,
         _id: RequestId,
-        _result: Option<serde_json::Value>,
+        _result: Option<Box<RawValue>>,
         _error: Option<JsonRpcError>,
     ) -> Result<()> {
         Ok(())
     }
+
+    fn protocol(&self) -> Option<&Protocol> {
+        Some(&self.protocol)
+    }
 }
 
 /// Handles SSE connection requests.
@@ -216,8 +319,9 @@ impl Transport for ServerSseTransport {
 /// 1. Creates a new session for the client
 /// 2. Establishes an SSE stream
 /// 3. Sends the endpoint info event
-/// 4. Sets up a ping mechanism to keep the connection alive
-/// 5. Streams messages to the client
+/// 4. Starts pinging the client to detect dead clients, if liveness checks are enabled
+/// 5. Streams messages to the client, until the client disconnects and the
+///    session is removed
 ///
 /// # Arguments
 ///
@@ -254,49 +358,67 @@ pub async fn sse_handler(
         session_id
     );
 
-    // Spawn a task to handle ping notifications separately
-    let transport_ping = transport.clone();
-    let session_id_ping = session_id.clone();
-    tokio::spawn(async move {
-        loop {
-            tokio::time::sleep(Duration::from_secs(15)).await;
-            if let Some(session) = transport_ping.get_session(&session_id_ping).await {
-                if let Err(e) = session.send_notification("ping", None).await {
-                    tracing::error!(
-                        "Failed to send ping to session {}: {:?}",
-                        session_id_ping,
-                        e
-                    );
-                }
-            } else {
-                break;
-            }
-        }
-    });
+    // Spawn a task to handle liveness pings separately
+    if let Some(liveness_check) = transport.liveness_check.clone() {
+        let transport_ping = transport.clone();
+        let session_id_ping = session_id.clone();
+        tokio::spawn(async move {
+            transport_ping
+                .monitor_liveness(session_id_ping, liveness_check)
+                .await;
+        });
+    }
 
     let stream = futures::stream::once(async move {
         Ok::<_, std::convert::Infallible>(web::Bytes::from(endpoint_info))
     })
     .chain(futures::stream::unfold(
-        (transport.clone(), session_id.clone(), client_ip.clone()),
+        (
+            transport.clone(),
+            session_id.clone(),
+            client_ip.clone(),
+            SessionGuard {
+                transport: transport.get_ref().clone(),
+                session_id: session_id.clone(),
+            },
+        ),
         move |state| async move {
-            let (transport, session_id, client_ip) = state;
+            let (transport, session_id, client_ip, guard) = state;
             let session = transport.get_session(&session_id).await;
 
             if let Some(session) = session {
-                match session.poll_message().await {
-                    Ok(Some(msg)) => {
-                        tracing::debug!("Sending SSE message to Session {}: {:?}", session_id, msg);
-                        let json = serde_json::to_string(&msg).unwrap();
-                        let sse_data = format!("event: message\ndata: {}\n\n", json);
-                        let response =
-                            Ok::<_, std::convert::Infallible>(web::Bytes::from(sse_data));
-                        Some((response, (transport, session_id, client_ip)))
-                    }
-                    Ok(None) => None,
-                    Err(e) => {
-                        tracing::error!("Error polling message for Session {}: {:?}", client_ip, e);
-                        None
+                loop {
+                    match session.poll_message().await {
+                        Ok(Some(msg)) => {
+                            tracing::debug!(
+                                "Sending SSE message to Session {}: {:?}",
+                                session_id,
+                                msg
+                            );
+                            // Write the frame in place, raw params and results are copied verbatim
+                            let mut frame = b"event: message\ndata: ".to_vec();
+                            if let Err(e) = serde_json::to_writer(&mut frame, &msg) {
+                                tracing::error!(
+                                    "Skipping message for Session {} that failed to serialize: {:?}",
+                                    session_id,
+                                    e
+                                );
+                                continue;
+                            }
+                            frame.extend_from_slice(b"\n\n");
+                            let response =
+                                Ok::<_, std::convert::Infallible>(web::Bytes::from(frame));
+                            break Some((response, (transport, session_id, client_ip, guard)));
+                        }
+                        Ok(None) => break None,
+                        Err(e) => {
+                            tracing::error!(
+                                "Error polling message for Session {}: {:?}",
+                                client_ip,
+                                e
+                            );
+                            break None;
+                        }
                     }
                 }
             } else {
@@ -312,6 +434,48 @@ pub async fn sse_handler(
         .streaming(stream)
 }
 
+/// Removes a session once its SSE stream is dropped, e.g. when the client
+/// disconnects, so that messages posted to the session are rejected.
+struct SessionGuard {
+    transport: ServerSseTransport,
+    session_id: String,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        let transport = self.transport.clone();
+        let session_id = std::mem::take(&mut self.session_id);
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                tracing::info!("SSE stream of session {} closed", session_id);
+                transport.remove_session(&session_id).await;
+            });
+        }
+    }
+}
+
+/// Serves the metrics of the protocol in the Prometheus text exposition format.
+///
+/// # Arguments
+///
+/// * `transport` - The `ServerSseTransport` instance
+///
+/// # Returns
+///
+/// An `HttpResponse` with the rendered metrics, or `404 Not Found` if there are none
+pub async fn metrics_handler(transport: web::Data<ServerSseTransport>) -> HttpResponse {
+    match transport
+        .protocol
+        .metrics()
+        .and_then(|metrics| metrics.render())
+    {
+        Some(body) => HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4")
+            .body(body),
+        None => HttpResponse::NotFound().body("Metrics not available"),
+    }
+}
+
 /// Query parameters for message handling.
 #[derive(Deserialize)]
 pub struct MessageQuery {
@@ -325,13 +489,19 @@ pub struct MessageQuery {
 /// This function:
 /// 1. Extracts the session ID from the query parameters
 /// 2. Retrieves the session
-/// 3. Passes the message to the protocol for processing
+/// 3. Validates the message and passes it to the protocol for processing
 /// 4. Returns a response to the client
 ///
+/// Requests are handled in a separate task and their response is delivered
+/// over the SSE stream, so the HTTP request is accepted right away. Messages
+/// that are not valid JSON-RPC 2.0 are rejected with a `400 Bad Request`
+/// whose body is the `ParseError` or `InvalidRequest` error response; the
+/// session stays open.
+///
 /// # Arguments
 ///
 /// * `query` - The query parameters containing the session ID
-/// * `message` - The JSON-RPC message
+/// * `body` - The raw JSON-RPC message
 /// * `transport` - The `ServerSseTransport` instance
 ///
 /// # Returns
@@ -339,62 +509,97 @@ pub struct MessageQuery {
 /// An `HttpResponse` with the operation result
 pub async fn message_handler(
     query: Query<MessageQuery>,
-    message: web::Json<Message>,
+    body: web::Bytes,
     transport: web::Data<ServerSseTransport>,
 ) -> HttpResponse {
-    if let Some(session_id) = &query.session_id {
-        let sessions = transport.sessions.lock().await;
-        if let Some(transport) = sessions.get(session_id) {
-            match message.into_inner() {
-                JsonRpcMessage::Request(request) => {
-                    tracing::debug!(
-                        "Received request from session {}: {:?}",
-                        session_id,
-                        request
-                    );
-                    let response = transport.protocol.handle_request(request).await;
-                    match transport
-                        .send_response(response.id, response.result, response.error)
-                        .await
-                    {
-                        Ok(_) => {
-                            tracing::debug!("Successfully sent message to session {}", session_id);
-                            HttpResponse::Accepted().finish()
-                        }
-                        Err(e) => {
-                            tracing::error!(
-                                "Failed to send message to session {}: {:?}",
-                                session_id,
-                                e
-                            );
-                            HttpResponse::InternalServerError().finish()
-                        }
+    let Some(session_id) = &query.session_id else {
+        return HttpResponse::BadRequest().body("Session ID not specified");
+    };
+    let Some(session) = transport.get_session(session_id).await else {
+        return HttpResponse::NotFound().body(format!("Session {} not found", session_id));
+    };
+    let message = match std::str::from_utf8(&body) {
+        Ok(text) => Message::parse(text),
+        Err(e) => Err(JsonRpcErrorResponse::new(
+            None,
+            McpError::parse_error(e.to_string()),
+        )),
+    };
+    let message = match message {
+        Ok(message) => message,
+        Err(response) if !response.should_send() => {
+            tracing::debug!(
+                "Dropping invalid response from session {}: {:?}",
+                session_id,
+                response.error
+            );
+            return HttpResponse::Accepted().finish();
+        }
+        Err(response) => {
+            tracing::debug!(
+                "Received invalid message from session {}: {:?}",
+                session_id,
+                response.error
+            );
+            return HttpResponse::BadRequest().json(response);
+        }
+    };
+    let peer = session
+        .protocol
+        .peer(Some(session_id.clone()), Arc::new(session.clone()));
+
+    match message {
+        JsonRpcMessage::Request(request) => {
+            tracing::debug!(
+                "Received request from session {}: {:?}",
+                session_id,
+                request
+            );
+            let session_id = session_id.clone();
+            tokio::spawn(async move {
+                let response = session.protocol.handle_request_from(request, peer).await;
+                match session
+                    .send_response(response.id, response.result, response.error)
+                    .await
+                {
+                    Ok(_) => {
+                        tracing::debug!("Successfully sent message to session {}", session_id);
+                    }
+                    Err(e) => {
+                        tracing::error!(
+                            "Failed to send message to session {}: {:?}",
+                            session_id,
+                            e
+                        );
                     }
                 }
-                JsonRpcMessage::Response(response) => {
-                    tracing::debug!(
-                        "Received response from session {}: {:?}",
-                        session_id,
-                        response
-                    );
-                    transport.protocol.handle_response(response).await;
-                    HttpResponse::Accepted().finish()
-                }
-                JsonRpcMessage::Notification(notification) => {
-                    tracing::debug!(
-                        "Received notification from session {}: {:?}",
-                        session_id,
-                        notification
-                    );
-                    transport.protocol.handle_notification(notification).await;
-                    HttpResponse::Accepted().finish()
-                }
-            }
-        } else {
-            HttpResponse::NotFound().body(format!("Session {} not found", session_id))
+            });
+            HttpResponse::Accepted().finish()
+        }
+        JsonRpcMessage::Response(response) => {
+            tracing::debug!(
+                "Received response from session {}: {:?}",
+                session_id,
+                response
+            );
+            session
+                .protocol
+                .handle_response_from(response, Some(session_id))
+                .await;
+            HttpResponse::Accepted().finish()
+        }
+        JsonRpcMessage::Notification(notification) => {
+            tracing::debug!(
+                "Received notification from session {}: {:?}",
+                session_id,
+                notification
+            );
+            session
+                .protocol
+                .handle_notification_from(notification, peer)
+                .await;
+            HttpResponse::Accepted().finish()
         }
-    } else {
-        HttpResponse::BadRequest().body("Session ID not specified")
     }
 }
 
@@ -404,11 +609,28 @@ pub async fn message_handler(
 /// processing incoming messages and sending outgoing messages.
 #[derive(Clone)]
 pub struct ServerSseTransportSession {
+    session_id: String,
     protocol: Protocol,
     rx: Arc<Mutex<mpsc::Receiver<Message>>>,
     tx: mpsc::Sender<Message>,
 }
 
+impl ServerSseTransportSession {
+    /// Queues a message for delivery over the SSE stream of the session.
+    async fn send(&self, message: Message) -> Result<(), mpsc::error::SendError<Message>> {
+        // Count the message first, so that it is never dequeued before being queued
+        let metrics = self.protocol.metrics();
+        if let Some(metrics) = metrics {
+            metrics.sse_message_queued();
+        }
+        let result = self.tx.send(message).await;
+        if let (Err(_), Some(metrics)) = (&result, metrics) {
+            metrics.sse_messages_dequeued(1);
+        }
+        result
+    }
+}
+
 #[async_trait()]
 impl Transport for ServerSseTransportSession {
     async fn open(&self) -> Result<()> {
@@ -424,6 +646,9 @@ impl Transport for ServerSseTransportSession {
         match rx.recv().await {
             Some(message) => {
                 tracing::debug!("Received message from SSE: {:?}", message);
+                if let Some(metrics) = self.protocol.metrics() {
+                    metrics.sse_messages_dequeued(1);
+                }
                 Ok(Some(message))
             }
             None => Ok(None),
@@ -437,66 +662,43 @@ impl Transport for ServerSseTransportSession {
         options: RequestOptions,
     ) -> Pin<Box<dyn Future<Output = Result<JsonRpcResponse>> + Send + Sync>> {
         let protocol = self.protocol.clone();
-        let tx = self.tx.clone();
+        let session = self.clone();
 
         let method = method.to_owned();
-        let params = params.clone();
-
-        Box::pin(async move {
-            let (id, rx) = protocol.create_request().await;
-            let message = JsonRpcMessage::Request(JsonRpcRequest {
-                id,
-                method: method.clone(),
-                jsonrpc: Default::default(),
-                params,
-            });
 
-            if let Err(e) = tx.send(message).await {
-                return Ok(JsonRpcResponse {
+        let span = request_span("sse", &method);
+        Box::pin(
+            async move {
+                let params = params.as_ref().map(to_raw_value).transpose()?;
+                let (id, rx) = protocol.create_request_for(Some(&session.session_id)).await;
+                tracing::Span::current().record("rpc.request_id", id);
+                let message = JsonRpcMessage::Request(JsonRpcRequest {
                     id,
-                    result: None,
-                    error: Some(JsonRpcError {
-                        code: ErrorCode::InternalError as i32,
-                        message: format!("Failed to send request: {}", e),
-                        data: None,
-                    }),
-                    ..Default::default()
+                    method: method.clone(),
+                    jsonrpc: Default::default(),
+                    params: params.clone(),
                 });
-            }
 
-            let result = timeout(options.timeout, rx).await;
-            match result {
-                Ok(inner_result) => match inner_result {
-                    Ok(response) => Ok(response),
-                    Err(_) => {
-                        protocol.cancel_response(id).await;
-                        Ok(JsonRpcResponse {
-                            id,
-                            result: None,
-                            error: Some(JsonRpcError {
-                                code: ErrorCode::RequestTimeout as i32,
-                                message: "Request cancelled".to_string(),
-                                data: None,
-                            }),
-                            ..Default::default()
-                        })
-                    }
-                },
-                Err(_) => {
+                if let Err(e) = session.send(message).await {
                     protocol.cancel_response(id).await;
-                    Ok(JsonRpcResponse {
+                    return Ok(JsonRpcResponse {
                         id,
                         result: None,
                         error: Some(JsonRpcError {
-                            code: ErrorCode::RequestTimeout as i32,
-                            message: "Request cancelled".to_string(),
+                            code: ErrorCode::ConnectionClosed as i32,
+                            message: format!("Failed to send request: {}", e),
                             data: None,
                         }),
                         ..Default::default()
-                    })
+                    });
                 }
+
+                Ok(protocol
+                    .wait_for_response(id, rx, params.as_deref(), &options)
+                    .await)
             }
-        })
+            .instrument(span),
+        )
     }
 
     async fn send_notification(
@@ -504,13 +706,13 @@ impl Transport for ServerSseTransportSession {
         method: &str,
         params: Option<serde_json::Value>,
     ) -> Result<()> {
+        let params = params.as_ref().map(to_raw_value).transpose()?;
         let message = JsonRpcMessage::Notification(JsonRpcNotification {
             method: method.to_owned(),
             params,
             jsonrpc: Default::default(),
         });
-        self.tx
-            .send(message)
+        self.send(message)
             .await
             .map_err(|e| anyhow::anyhow!("Send notification error: {:?}", e))
     }
@@ -518,7 +720,7 @@ impl Transport for ServerSseTransportSession {
     async fn send_response(
         &self,
         id: RequestId,
-        result: Option<serde_json::Value>,
+        result: Option<Box<RawValue>>,
         error: Option<JsonRpcError>,
     ) -> Result<()> {
         let message = JsonRpcMessage::Response(JsonRpcResponse {
@@ -527,9 +729,67 @@ impl Transport for ServerSseTransportSession {
             error,
             jsonrpc: Default::default(),
         });
-        self.tx
-            .send(message)
+        self.send(message)
             .await
             .map_err(|e| anyhow::anyhow!("Send response error: {:?}", e))
     }
+
+    fn protocol(&self) -> Option<&Protocol> {
+        Some(&self.protocol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use std::sync::atomic::{AtomicI64, Ordering};
+
+    #[derive(Default)]
+    struct QueueDepth {
+        sessions: AtomicI64,
+        depth: AtomicI64,
+    }
+
+    impl Metrics for QueueDepth {
+        fn session_opened(&self) {
+            self.sessions.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn session_closed(&self) {
+            self.sessions.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        fn sse_message_queued(&self) {
+            self.depth.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn sse_messages_dequeued(&self, count: usize) {
+            self.depth.fetch_sub(count as i64, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queue_depth() {
+        let metrics = Arc::new(QueueDepth::default());
+        let protocol = Protocol::builder().metrics(metrics.clone()).build();
+        let transport = ServerSseTransport::new("127.0.0.1".to_string(), 0, protocol);
+
+        transport.create_session("a".to_string()).await;
+        let session = transport.get_session("a").await.unwrap();
+        assert_eq!(metrics.sessions.load(Ordering::SeqCst), 1);
+
+        for _ in 0..3 {
+            session.send_notification("test", None).await.unwrap();
+        }
+        assert_eq!(metrics.depth.load(Ordering::SeqCst), 3);
+
+        session.poll_message().await.unwrap().unwrap();
+        assert_eq!(metrics.depth.load(Ordering::SeqCst), 2);
+
+        // The messages still queued are dropped with the session
+        transport.remove_session("a").await;
+        assert_eq!(metrics.depth.load(Ordering::SeqCst), 0);
+        assert_eq!(metrics.sessions.load(Ordering::SeqCst), 0);
+    }
 }
@@ -1,16 +1,16 @@
+use crate::error::McpError;
 use crate::protocol::{Protocol, RequestOptions};
 use crate::transport::{
-    JsonRpcError, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, Message, RequestId,
-    Transport,
+    request_span, to_raw_value, JsonRpcError, JsonRpcErrorResponse, JsonRpcNotification,
+    JsonRpcRequest, JsonRpcResponse, Message, RawValue, RequestId, Transport,
 };
-use crate::types::ErrorCode;
 use anyhow::Result;
 use async_trait::async_trait;
 use std::future::Future;
 use std::io::{self, BufRead, Write};
 use std::pin::Pin;
-use tokio::time::timeout;
-use tracing::debug;
+use std::sync::Arc;
+use tracing::{debug, Instrument};
 
 /// Server transport that communicates with MCP clients over standard I/O.
 ///
@@ -66,34 +66,62 @@ impl Transport for ServerStdioTransport {
     /// 3. Sends responses as needed
     /// 4. Continues until EOF is received on stdin
     ///
+    /// Requests are handled in their own task, so that a handler can wait for
+    /// a response from the client while the loop keeps reading messages.
+    /// Invalid messages are skipped, but I/O errors end the loop.
+    ///
     /// # Returns
     ///
-    /// A `Result` indicating success or failure
+    /// A `Result` indicating success, or the I/O error that ended the loop
     async fn open(&self) -> Result<()> {
-        loop {
+        let peer = self.protocol.peer(None, Arc::new(self.clone()));
+        if let Some(metrics) = self.protocol.metrics() {
+            metrics.session_opened();
+        }
+        let result = loop {
             match self.poll_message().await {
                 Ok(Some(message)) => match message {
                     Message::Request(request) => {
-                        let response = self.protocol.handle_request(request).await;
-                        self.send_response(response.id, response.result, response.error)
-                            .await?;
+                        let transport = self.clone();
+                        let peer = peer.clone();
+                        tokio::spawn(async move {
+                            let response =
+                                transport.protocol.handle_request_from(request, peer).await;
+                            if let Err(e) = transport
+                                .send_response(response.id, response.result, response.error)
+                                .await
+                            {
+                                tracing::error!("Error sending response: {:?}", e);
+                            }
+                        });
                     }
                     Message::Notification(notification) => {
-                        self.protocol.handle_notification(notification).await;
+                        self.protocol
+                            .handle_notification_from(notification, peer.clone())
+                            .await;
                     }
                     Message::Response(response) => {
                         self.protocol.handle_response(response).await;
                     }
                 },
                 Ok(None) => {
-                    break;
+                    break Ok(());
                 }
-                Err(e) => {
+                Err(e) if e.downcast_ref::<McpError>().is_some() => {
                     tracing::error!("Error receiving message: {:?}", e);
                 }
+                Err(e) => {
+                    tracing::error!("Error reading from stdin: {:?}", e);
+                    break Err(e);
+                }
             }
+        };
+        self.protocol.close_session(None);
+        if let Some(metrics) = self.protocol.metrics() {
+            metrics.session_closed();
         }
-        Ok(())
+        self.protocol.close().await;
+        result
     }
 
     /// Closes the transport.
@@ -110,22 +138,57 @@ impl Transport for ServerStdioTransport {
     /// Polls for incoming messages from stdin.
     ///
     /// This method reads a line from stdin and parses it as a JSON-RPC message.
+    /// The blocking read runs on a dedicated thread so that other tasks keep running.
+    ///
+    /// Lines that are not valid UTF-8 or not valid JSON-RPC 2.0 messages are
+    /// answered with a `ParseError` or `InvalidRequest` error response and
+    /// reported as an `McpError`, so that the caller can carry on with the next
+    /// message. Invalid responses are reported without being answered.
     ///
     /// # Returns
     ///
     /// A `Result` containing an `Option<Message>`. `None` indicates EOF.
     async fn poll_message(&self) -> Result<Option<Message>> {
-        let stdin = io::stdin();
-        let mut reader = stdin.lock();
-        let mut line = String::new();
-        reader.read_line(&mut line)?;
+        let line = tokio::task::spawn_blocking(|| {
+            let mut line = Vec::new();
+            io::stdin()
+                .lock()
+                .read_until(b'\n', &mut line)
+                .map(|_| line)
+        })
+        .await??;
         if line.is_empty() {
             return Ok(None);
         }
 
-        debug!("Received: {line}");
-        let message: Message = serde_json::from_str(&line)?;
-        Ok(Some(message))
+        let message = match String::from_utf8(line) {
+            Ok(line) => {
+                debug!("Received: {line}");
+                Message::parse(&line)
+            }
+            Err(e) => Err(JsonRpcErrorResponse::new(
+                None,
+                McpError::parse_error(e.to_string()),
+            )),
+        };
+        match message {
+            Ok(message) => Ok(Some(message)),
+            Err(response) if !response.should_send() => {
+                debug!("Dropping invalid response: {:?}", response.error);
+                Err(McpError::from(response.error).into())
+            }
+            Err(response) => {
+                let serialized = serde_json::to_string(&response)?;
+                {
+                    let stdout = io::stdout();
+                    let mut writer = stdout.lock();
+                    writer.write_all(serialized.as_bytes())?;
+                    writer.write_all(b"\n")?;
+                    writer.flush()?;
+                }
+                Err(McpError::from(response.error).into())
+            }
+        }
     }
 
     /// Sends a request to the client and waits for a response.
@@ -153,58 +216,42 @@ impl Transport for ServerStdioTransport {
     ) -> Pin<Box<dyn Future<Output = Result<JsonRpcResponse>> + Send + Sync>> {
         let protocol = self.protocol.clone();
         let method = method.to_owned();
-        Box::pin(async move {
-            let (id, rx) = protocol.create_request().await;
-            let request = JsonRpcRequest {
-                id,
-                method,
-                jsonrpc: Default::default(),
-                params,
-            };
-            let serialized = serde_json::to_string(&request).unwrap_or_default();
-            debug!("Sending: {serialized}");
-
-            // Use Tokio's async stdout to perform thread-safe, nonblocking writes.
-            let mut stdout = io::stdout();
-            stdout.write_all(serialized.as_bytes())?;
-            stdout.write_all(b"\n")?;
-            stdout.flush()?;
+        let span = request_span("stdio", &method);
+        Box::pin(
+            async move {
+                let params = params.as_ref().map(to_raw_value).transpose()?;
+                let (id, rx) = protocol.create_request().await;
+                tracing::Span::current().record("rpc.request_id", id);
+                let request = JsonRpcRequest {
+                    id,
+                    method,
+                    jsonrpc: Default::default(),
+                    params,
+                };
+                let serialized = serde_json::to_string(&request).unwrap_or_default();
+                debug!("Sending: {serialized}");
 
-            let result = timeout(options.timeout, rx).await;
-            match result {
-                // The request future completed before the timeout.
-                Ok(inner_result) => match inner_result {
-                    Ok(response) => Ok(response),
-                    Err(_) => {
-                        protocol.cancel_response(id).await;
-                        Ok(JsonRpcResponse {
-                            id,
-                            result: None,
-                            error: Some(JsonRpcError {
-                                code: ErrorCode::RequestTimeout as i32,
-                                message: "Request cancelled".to_string(),
-                                data: None,
-                            }),
-                            ..Default::default()
-                        })
-                    }
-                },
-                // The timeout expired.
-                Err(_) => {
+                // Hold the stdout lock so that concurrent writes do not interleave.
+                let sent = {
+                    let stdout = io::stdout();
+                    let mut writer = stdout.lock();
+                    writer
+                        .write_all(serialized.as_bytes())
+                        .and_then(|_| writer.write_all(b"\n"))
+                        .and_then(|_| writer.flush())
+                };
+                if let Err(e) = sent {
+                    // The request never reached the client, so nothing will answer it
                     protocol.cancel_response(id).await;
-                    Ok(JsonRpcResponse {
-                        id,
-                        result: None,
-                        error: Some(JsonRpcError {
-                            code: ErrorCode::RequestTimeout as i32,
-                            message: "Request cancelled".to_string(),
-                            data: None,
-                        }),
-                        ..Default::default()
-                    })
+                    return Err(e.into());
                 }
+
+                Ok(protocol
+                    .wait_for_response(id, rx, request.params.as_deref(), &options)
+                    .await)
             }
-        })
+            .instrument(span),
+        )
     }
 
     /// Sends a notification to the client.
@@ -225,6 +272,7 @@ impl Transport for ServerStdioTransport {
         method: &str,
         params: Option<serde_json::Value>,
     ) -> Result<()> {
+        let params = params.as_ref().map(to_raw_value).transpose()?;
         let notification = JsonRpcNotification {
             jsonrpc: Default::default(),
             method: method.to_owned(),
@@ -256,7 +304,7 @@ impl Transport for ServerStdioTransport {
     async fn send_response(
         &self,
         id: RequestId,
-        result: Option<serde_json::Value>,
+        result: Option<Box<RawValue>>,
         error: Option<JsonRpcError>,
     ) -> Result<()> {
         let response = JsonRpcResponse {
@@ -274,4 +322,8 @@ impl Transport for ServerStdioTransport {
         writer.flush()?;
         Ok(())
     }
+
+    fn protocol(&self) -> Option<&Protocol> {
+        Some(&self.protocol)
+    }
 }
@@ -1,10 +1,17 @@
 use std::collections::HashMap;
 
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
+use crate::error::McpError;
+use crate::transport::RequestId;
+
 /// Supported versions of the Model Context Protocol
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// Versions are ordered chronologically, so `ProtocolVersion::V2024_11_05 <
+/// ProtocolVersion::V2025_03_26`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum ProtocolVersion {
     /// 2024-11-05 protocol version
@@ -13,6 +20,9 @@ pub enum ProtocolVersion {
     /// 2025-03-26 protocol version
     #[serde(rename = "2025-03-26")]
     V2025_03_26,
+    /// 2025-06-18 protocol version
+    #[serde(rename = "2025-06-18")]
+    V2025_06_18,
 }
 
 impl ProtocolVersion {
@@ -21,12 +31,67 @@ impl ProtocolVersion {
         match self {
             ProtocolVersion::V2024_11_05 => "2024-11-05",
             ProtocolVersion::V2025_03_26 => "2025-03-26",
+            ProtocolVersion::V2025_06_18 => "2025-06-18",
         }
     }
+
+    /// Parses a protocol version string.
+    ///
+    /// # Arguments
+    ///
+    /// * `version` - The version string, e.g. `"2025-03-26"`
+    ///
+    /// # Returns
+    ///
+    /// The matching `ProtocolVersion`, or `None` if the version is not supported
+    pub fn parse(version: &str) -> Option<Self> {
+        SUPPORTED_PROTOCOL_VERSIONS
+            .iter()
+            .find(|supported| supported.as_str() == version)
+            .cloned()
+    }
+
+    /// Returns whether tool annotations are part of this version.
+    pub fn supports_tool_annotations(&self) -> bool {
+        *self >= ProtocolVersion::V2025_03_26
+    }
+
+    /// Returns whether audio content is part of this version.
+    pub fn supports_audio_content(&self) -> bool {
+        *self >= ProtocolVersion::V2025_03_26
+    }
+
+    /// Returns whether the `completions` capability is part of this version.
+    pub fn supports_completions(&self) -> bool {
+        *self >= ProtocolVersion::V2025_03_26
+    }
+
+    /// Returns whether tool output schemas and structured tool results are part of this version.
+    pub fn supports_structured_output(&self) -> bool {
+        *self >= ProtocolVersion::V2025_06_18
+    }
+
+    /// Returns whether elicitation is part of this version.
+    pub fn supports_elicitation(&self) -> bool {
+        *self >= ProtocolVersion::V2025_06_18
+    }
 }
 
+impl std::fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// All versions of the Model Context Protocol supported by this library, oldest first
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[ProtocolVersion] = &[
+    ProtocolVersion::V2024_11_05,
+    ProtocolVersion::V2025_03_26,
+    ProtocolVersion::V2025_06_18,
+];
+
 /// The latest version of the Model Context Protocol
-pub const LATEST_PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion::V2025_03_26;
+pub const LATEST_PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion::V2025_06_18;
 
 /// Describes the name and version of an MCP implementation
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -96,6 +161,36 @@ pub struct ServerCapabilities {
     pub resources: Option<ResourceCapabilities>,
 }
 
+impl ServerCapabilities {
+    /// Checks that the server supports a request sent by a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method of the request
+    /// * `version` - The protocol version negotiated with the peer
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the request is supported or needs no capability, or an
+    /// error naming the missing capability
+    pub fn check_request(&self, method: &str, version: &ProtocolVersion) -> Result<(), McpError> {
+        let supported = match required_capability(method, version) {
+            Some("tools") => self.tools.is_some(),
+            Some("resources") => self.resources.is_some(),
+            Some("resources.subscribe") => self
+                .resources
+                .as_ref()
+                .and_then(|resources| resources.subscribe)
+                .unwrap_or(false),
+            Some("prompts") => self.prompts.is_some(),
+            Some("logging") => self.logging.is_some(),
+            Some("completions") => self.completions.is_some(),
+            _ => true,
+        };
+        check_capability(method, version, supported)
+    }
+}
+
 /// Tool-related capabilities
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
@@ -143,6 +238,70 @@ pub struct ClientCapabilities {
     /// Root directory capabilities
     #[serde(skip_serializing_if = "Option::is_none")]
     pub roots: Option<RootCapabilities>,
+    /// Elicitation capabilities
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elicitation: Option<serde_json::Value>,
+}
+
+impl ClientCapabilities {
+    /// Checks that the client supports a request sent by a server.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method of the request
+    /// * `version` - The protocol version negotiated with the peer
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the request is supported or needs no capability, or an
+    /// error naming the missing capability
+    pub fn check_request(&self, method: &str, version: &ProtocolVersion) -> Result<(), McpError> {
+        let supported = match required_capability(method, version) {
+            Some("sampling") => self.sampling.is_some(),
+            Some("roots") => self.roots.is_some(),
+            Some("elicitation") => self.elicitation.is_some(),
+            _ => true,
+        };
+        check_capability(method, version, supported)
+    }
+}
+
+/// Returns the capability a request method depends on, if any.
+///
+/// # Arguments
+///
+/// * `method` - The method of the request
+/// * `version` - The protocol version negotiated with the peer
+///
+/// # Returns
+///
+/// The name of the capability, e.g. `resources.subscribe`, or `None` if the
+/// method needs no capability in this version
+pub fn required_capability(method: &str, version: &ProtocolVersion) -> Option<&'static str> {
+    match method {
+        "tools/list" | "tools/call" => Some("tools"),
+        "resources/list" | "resources/read" | "resources/templates/list" => Some("resources"),
+        "resources/subscribe" | "resources/unsubscribe" => Some("resources.subscribe"),
+        "prompts/list" | "prompts/get" => Some("prompts"),
+        "logging/setLevel" => Some("logging"),
+        // Servers of earlier versions offer completion without a capability
+        "completion/complete" if version.supports_completions() => Some("completions"),
+        "sampling/createMessage" => Some("sampling"),
+        "roots/list" => Some("roots"),
+        "elicitation/create" => Some("elicitation"),
+        _ => None,
+    }
+}
+
+fn check_capability(
+    method: &str,
+    version: &ProtocolVersion,
+    supported: bool,
+) -> Result<(), McpError> {
+    match (supported, required_capability(method, version)) {
+        (false, Some(capability)) => Err(McpError::capability_not_supported(method, capability)),
+        _ => Ok(()),
+    }
 }
 
 /// Root directory-related capabilities
@@ -169,6 +328,12 @@ pub struct Tool {
     pub description: Option<String>,
     /// A JSON Schema object defining the expected parameters for the tool
     pub input_schema: serde_json::Value,
+    /// An optional JSON Schema object defining the structured content the tool returns
+    ///
+    /// Tools with an output schema must return `structured_content` matching
+    /// it, unless the call ends in an error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
     /// Optional additional tool information
     #[serde(skip_serializing_if = "Option::is_none")]
     pub annotations: Option<ToolAnnotations>,
@@ -244,6 +409,9 @@ pub struct CallToolRequest {
 pub struct CallToolResponse {
     /// The content returned by the tool
     pub content: Vec<ToolResponseContent>,
+    /// The result of the tool as a JSON object, matching its output schema
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<serde_json::Value>,
     /// Whether the tool call ended in an error
     ///
     /// If not set, this is assumed to be false (the call was successful).
@@ -254,8 +422,27 @@ pub struct CallToolResponse {
     pub meta: Option<serde_json::Value>,
 }
 
+impl CallToolResponse {
+    /// Decodes the structured content of the response.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the decoded content, or an error if the tool
+    /// returned no structured content or it does not match the type
+    pub fn structured_content_as<T: serde::de::DeserializeOwned>(&self) -> anyhow::Result<T> {
+        let content = self
+            .structured_content
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("Tool response has no structured content"))?;
+        serde_json::from_value(content)
+            .map_err(|e| anyhow::anyhow!("Failed to decode structured content: {}", e))
+    }
+}
+
 /// Content types that can be returned by a tool
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Each variant serializes as its content, which carries the `type` tag itself.
+#[derive(Debug, Clone, Deserialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum ToolResponseContent {
     /// Text content
@@ -272,6 +459,17 @@ pub enum ToolResponseContent {
     Resource(EmbeddedResource),
 }
 
+impl Serialize for ToolResponseContent {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ToolResponseContent::Text(content) => content.serialize(serializer),
+            ToolResponseContent::Image(content) => content.serialize(serializer),
+            ToolResponseContent::Audio(content) => content.serialize(serializer),
+            ToolResponseContent::Resource(content) => content.serialize(serializer),
+        }
+    }
+}
+
 /// Text content provided to or from an LLM
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -378,6 +576,46 @@ pub struct ResourceContents {
     pub blob: Option<String>,
 }
 
+impl ResourceContents {
+    /// Creates the text contents of a resource.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The URI of the resource
+    /// * `text` - The text of the resource
+    ///
+    /// # Returns
+    ///
+    /// The contents, without a MIME type
+    pub fn text(uri: Url, text: impl Into<String>) -> Self {
+        Self {
+            uri,
+            mime_type: None,
+            text: Some(text.into()),
+            blob: None,
+        }
+    }
+
+    /// Creates the binary contents of a resource.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The URI of the resource
+    /// * `data` - The binary data, which is base64-encoded
+    ///
+    /// # Returns
+    ///
+    /// The contents, without a MIME type
+    pub fn blob(uri: Url, data: impl AsRef<[u8]>) -> Self {
+        Self {
+            uri,
+            mime_type: None,
+            text: None,
+            blob: Some(base64::engine::general_purpose::STANDARD.encode(data)),
+        }
+    }
+}
+
 /// Request to read a resource
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -386,6 +624,135 @@ pub struct ReadResourceRequest {
     pub uri: Url,
 }
 
+/// Request to subscribe to, or unsubscribe from, updates of a resource
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscribeRequest {
+    /// The URI of the resource
+    pub uri: Url,
+}
+
+/// Notification that a subscribed resource has changed and may need to be read again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceUpdatedNotification {
+    /// The URI of the resource that has been updated, possibly a sub-resource
+    /// of the subscribed resource
+    pub uri: String,
+}
+
+/// The severity of a log message, as defined by RFC 5424
+///
+/// Levels are ordered from the least to the most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoggingLevel {
+    Debug,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
+
+impl From<tracing::Level> for LoggingLevel {
+    fn from(level: tracing::Level) -> Self {
+        match level {
+            tracing::Level::TRACE | tracing::Level::DEBUG => LoggingLevel::Debug,
+            tracing::Level::INFO => LoggingLevel::Info,
+            tracing::Level::WARN => LoggingLevel::Warning,
+            tracing::Level::ERROR => LoggingLevel::Error,
+        }
+    }
+}
+
+/// Request to set the minimum level of the log messages sent to the client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetLevelRequest {
+    /// The level of the least severe messages the client wants to receive
+    pub level: LoggingLevel,
+}
+
+/// A log message sent from the server to the client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoggingMessageNotification {
+    /// The severity of the message
+    pub level: LoggingLevel,
+    /// The name of the logger that issued the message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logger: Option<String>,
+    /// The message, any JSON value
+    pub data: serde_json::Value,
+}
+
+/// What a completion request completes an argument of
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum CompletionReference {
+    /// An argument of a prompt
+    #[serde(rename = "ref/prompt")]
+    Prompt {
+        /// The name of the prompt
+        name: String,
+    },
+    /// A variable of a resource template
+    #[serde(rename = "ref/resource")]
+    Resource {
+        /// The URI template of the resource template
+        uri: String,
+    },
+}
+
+/// The argument being completed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionArgument {
+    /// The name of the argument, or of the template variable
+    pub name: String,
+    /// The value typed so far
+    pub value: String,
+}
+
+/// Request for the possible values of a prompt argument or template variable
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompleteRequest {
+    /// The prompt or resource template the argument belongs to
+    #[serde(rename = "ref")]
+    pub reference: CompletionReference,
+    /// The argument being completed
+    pub argument: CompletionArgument,
+}
+
+/// The possible values of an argument
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Completion {
+    /// The values, at most 100
+    pub values: Vec<String>,
+    /// The total number of values, which may exceed the number of values sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u32>,
+    /// Whether there are more values than the ones sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_more: Option<bool>,
+}
+
+/// Response to a completion/complete request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompleteResponse {
+    /// The possible values of the argument
+    pub completion: Completion,
+    /// Optional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<serde_json::Value>,
+}
+
 /// Response to a resource read request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -437,6 +804,31 @@ pub struct PromptsListResponse {
     pub meta: Option<HashMap<String, serde_json::Value>>,
 }
 
+/// Request to get a prompt, filled in with arguments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetPromptRequest {
+    /// The name of the prompt or prompt template
+    pub name: String,
+    /// Arguments to use for templating the prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<HashMap<String, String>>,
+}
+
+/// Response to a prompts/get request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetPromptResponse {
+    /// An optional description for the prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The messages of the prompt
+    pub messages: Vec<PromptMessage>,
+    /// Optional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<serde_json::Value>,
+}
+
 /// A prompt or prompt template that the server offers
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -501,6 +893,39 @@ pub struct Resource {
     pub size: Option<usize>,
 }
 
+/// A template description for resources available on the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceTemplate {
+    /// An RFC 6570 URI template that can be used to construct resource URIs
+    pub uri_template: String,
+    /// A human-readable name for the type of resource this template refers to
+    pub name: String,
+    /// A description of what this template is for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The MIME type of all resources matching this template, if they have the same type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// Optional annotations for the client
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Annotations>,
+}
+
+/// Response to a resources/templates/list request
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceTemplatesListResponse {
+    /// The list of available resource templates
+    pub resource_templates: Vec<ResourceTemplate>,
+    /// An opaque token representing the pagination position after the last returned result
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// Optional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<HashMap<String, serde_json::Value>>,
+}
+
 /// The sender or recipient of messages and data in a conversation
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -525,7 +950,9 @@ pub struct PromptMessage {
 }
 
 /// Content types that can be included in a prompt message
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Each variant serializes as its content, which carries the `type` tag itself.
+#[derive(Debug, Clone, Deserialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum PromptMessageContent {
     /// Text content
@@ -542,6 +969,17 @@ pub enum PromptMessageContent {
     Resource(EmbeddedResource),
 }
 
+impl Serialize for PromptMessageContent {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            PromptMessageContent::Text(content) => content.serialize(serializer),
+            PromptMessageContent::Image(content) => content.serialize(serializer),
+            PromptMessageContent::Audio(content) => content.serialize(serializer),
+            PromptMessageContent::Resource(content) => content.serialize(serializer),
+        }
+    }
+}
+
 /// The server's preferences for model selection, requested of the client during sampling
 ///
 /// Because LLMs can vary along multiple dimensions, choosing the "best" model is
@@ -577,6 +1015,265 @@ pub struct ModelHint {
     pub name: Option<String>,
 }
 
+/// A directory or file the server may operate on, exposed by the client
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Root {
+    /// The URI of the root, a `file://` URI
+    pub uri: Url,
+    /// An optional name for the root
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// The client's response to a roots/list request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListRootsResult {
+    /// The roots exposed by the client
+    pub roots: Vec<Root>,
+    /// Optional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<serde_json::Value>,
+}
+
+/// Request from the server to ask the user for information through the client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElicitRequest {
+    /// The message to present to the user
+    pub message: String,
+    /// The schema of the requested information
+    pub requested_schema: ElicitationSchema,
+}
+
+/// The schema of the information requested by an elicitation
+///
+/// Only flat objects are allowed, whose properties have a primitive type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElicitationSchema {
+    /// The schema type, always "object"
+    #[serde(rename = "type")]
+    #[serde(default = "default_object_type")]
+    pub schema_type: String,
+    /// The properties of the object, by name
+    pub properties: HashMap<String, PrimitiveSchema>,
+    /// The names of the required properties
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required: Option<Vec<String>>,
+}
+
+/// The schema of a property of an elicitation schema
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum PrimitiveSchema {
+    /// A string, possibly restricted to a format or to a set of values
+    String(StringSchema),
+    /// A number
+    Number(NumberSchema),
+    /// An integer
+    Integer(NumberSchema),
+    /// A boolean
+    Boolean(BooleanSchema),
+}
+
+/// The schema of a string property
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StringSchema {
+    /// A short name for the property
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// A description of the property
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The minimum number of characters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<u32>,
+    /// The maximum number of characters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<u32>,
+    /// The format of the string: `email`, `uri`, `date` or `date-time`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// The allowed values
+    #[serde(rename = "enum", skip_serializing_if = "Option::is_none")]
+    pub enum_values: Option<Vec<String>>,
+    /// Display names for the allowed values
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enum_names: Option<Vec<String>>,
+}
+
+/// The schema of a number or integer property
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NumberSchema {
+    /// A short name for the property
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// A description of the property
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The minimum value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    /// The maximum value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
+}
+
+/// The schema of a boolean property
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BooleanSchema {
+    /// A short name for the property
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// A description of the property
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The default value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<bool>,
+}
+
+/// How the user responded to an elicitation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ElicitAction {
+    /// The user submitted the requested information
+    Accept,
+    /// The user explicitly declined to provide the information
+    Decline,
+    /// The user dismissed the request without choosing
+    Cancel,
+}
+
+/// The client's response to an elicitation/create request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElicitResult {
+    /// How the user responded
+    pub action: ElicitAction,
+    /// The submitted information, present when the action is `accept`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Optional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<serde_json::Value>,
+}
+
+/// Describes a message issued to or received from an LLM API during sampling
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplingMessage {
+    /// The sender or recipient of the message
+    pub role: Role,
+    /// The content of the message
+    pub content: SamplingContent,
+}
+
+/// Content types that can be included in a sampling message
+///
+/// Each variant serializes as its content, which carries the `type` tag itself.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SamplingContent {
+    /// Text content
+    #[serde(rename = "text")]
+    Text(TextContent),
+    /// Image content
+    #[serde(rename = "image")]
+    Image(ImageContent),
+    /// Audio content
+    #[serde(rename = "audio")]
+    Audio(AudioContent),
+}
+
+impl Serialize for SamplingContent {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            SamplingContent::Text(content) => content.serialize(serializer),
+            SamplingContent::Image(content) => content.serialize(serializer),
+            SamplingContent::Audio(content) => content.serialize(serializer),
+        }
+    }
+}
+
+/// Which MCP servers the client should include the context of in the prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IncludeContext {
+    /// No context
+    None,
+    /// The context of the server sending the request
+    ThisServer,
+    /// The context of all the servers the client is connected to
+    AllServers,
+}
+
+/// Request from the server to sample the LLM of the client
+///
+/// The client has full discretion over which model to select, and should
+/// inform the user before sampling, allowing them to inspect the request
+/// (human in the loop) and decide whether to approve it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageRequest {
+    /// The conversation to sample from
+    pub messages: Vec<SamplingMessage>,
+    /// The server's preferences for which model to select
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_preferences: Option<ModelPreferences>,
+    /// An optional system prompt the server wants to use for sampling
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// A request to include context from one or more MCP servers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_context: Option<IncludeContext>,
+    /// The sampling temperature
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// The maximum number of tokens to sample
+    pub max_tokens: u32,
+    /// Sequences that stop sampling
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+    /// Optional metadata to pass through to the LLM provider
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// The client's response to a sampling/createMessage request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageResult {
+    /// The role of the sampled message, usually the assistant
+    pub role: Role,
+    /// The sampled content
+    pub content: SamplingContent,
+    /// The name of the model that generated the message
+    pub model: String,
+    /// The reason why sampling stopped, e.g. `endTurn`, `stopSequence` or `maxTokens`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+    /// Optional metadata
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<serde_json::Value>,
+}
+
+/// Notification sent by either side to cancel a request it previously sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelledNotification {
+    /// The ID of the request to cancel
+    pub request_id: RequestId,
+    /// An optional reason for the cancellation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
 /// Error codes used in the Model Context Protocol
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ErrorCode {
@@ -596,6 +1293,27 @@ pub enum ErrorCode {
     InvalidParams = -32602,
     /// Internal JSON-RPC error
     InternalError = -32603,
+
+    // MCP-specific error codes
+    /// The requested resource does not exist
+    ResourceNotFound = -32002,
+}
+
+impl ErrorCode {
+    /// Returns the `ErrorCode` matching a numeric error code, if it is known
+    pub fn from_code(code: i32) -> Option<Self> {
+        match code {
+            -1 => Some(ErrorCode::ConnectionClosed),
+            -2 => Some(ErrorCode::RequestTimeout),
+            -32700 => Some(ErrorCode::ParseError),
+            -32600 => Some(ErrorCode::InvalidRequest),
+            -32601 => Some(ErrorCode::MethodNotFound),
+            -32602 => Some(ErrorCode::InvalidParams),
+            -32603 => Some(ErrorCode::InternalError),
+            -32002 => Some(ErrorCode::ResourceNotFound),
+            _ => None,
+        }
+    }
 }
 
 fn default_text_type() -> String {
@@ -614,6 +1332,10 @@ fn default_resource_type() -> String {
     "resource".to_string()
 }
 
+fn default_object_type() -> String {
+    "object".to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -625,6 +1347,17 @@ mod tests {
         assert_eq!(json, "{}");
     }
 
+    #[test]
+    fn test_completions_capability() {
+        let capabilities = ServerCapabilities::default();
+        assert!(capabilities
+            .check_request("completion/complete", &ProtocolVersion::V2024_11_05)
+            .is_ok());
+        assert!(capabilities
+            .check_request("completion/complete", &ProtocolVersion::V2025_03_26)
+            .is_err());
+    }
+
     #[test]
     fn test_tool_annotations() {
         let annotations = ToolAnnotations {